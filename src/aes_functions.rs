@@ -1,12 +1,24 @@
 use crate::base64_trait::{Base64StringConversions, Base64VecU8Conversions};
 use log::info;
+use openssl::aes::{unwrap_key, wrap_key, AesKey};
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
 use openssl::rand::rand_bytes;
-use openssl::symm::{decrypt, encrypt, Cipher};
+use openssl::symm::{decrypt, decrypt_aead, encrypt_aead, Cipher};
 use std::error::Error;
 use std::fmt;
+use zeroize::Zeroize;
 
 const KEY_LENGTH: usize = 32;
-const IV_LENGTH: usize = 16;
+/// recommended iv length of AES-GCM
+const IV_LENGTH: usize = 12;
+/// the authentication tag is appended to the encrypted data
+const TAG_LENGTH: usize = 16;
+const SALT_LENGTH: usize = 16;
+/// rfc 3394 adds 8 bytes to the wrapped key
+const WRAPPED_KEY_LENGTH: usize = KEY_LENGTH + 8;
+/// iterations of PBKDF2 to derive a key from a passphrase
+const PBKDF2_ITERATIONS: usize = 600_000;
 
 /// Used to return AES encrypted data
 pub struct AesEncryptionData {
@@ -31,25 +43,35 @@ impl fmt::Display for AesEncryptionError {
 }
 
 impl EncryptAes for String {
-    /// AES encrypt a `String`with randomly chosen key and iv.
+    /// AES-GCM encrypt a `String`with randomly chosen key and iv.
     ///
-    /// The encryted `String`, key and iv are returned as base64 encoded values
+    /// The encryted `String` (with the authentication tag appended), key
+    /// and iv are returned as base64 encoded values
     ///
     /// # Returns
     ///
     /// - `AesEncryptionData`
     fn to_aes_enrypted_b64(&self) -> Result<AesEncryptionData, AesEncryptionError> {
-        let cipher = Cipher::aes_256_cbc();
+        let cipher = Cipher::aes_256_gcm();
         let mut key_buf = [0; KEY_LENGTH];
         rand_bytes(&mut key_buf).unwrap();
         let mut iv_buf = [0; IV_LENGTH];
         rand_bytes(&mut iv_buf).unwrap();
-        match encrypt(cipher, &key_buf, Some(&iv_buf), self.as_bytes()) {
+        let mut tag_buf = [0; TAG_LENGTH];
+        match encrypt_aead(
+            cipher,
+            &key_buf,
+            Some(&iv_buf),
+            &[],
+            self.as_bytes(),
+            &mut tag_buf,
+        ) {
             Err(e) => {
                 info!("{}: {:?}", AesEncryptionError, &e);
                 Err(AesEncryptionError)
             }
-            Ok(encrypted_data) => {
+            Ok(mut encrypted_data) => {
+                encrypted_data.extend_from_slice(&tag_buf);
                 let base64_encrypted_data = encrypted_data.to_base64_urlsafe_encoded();
                 let base64_key = key_buf.to_base64_urlsafe_encoded();
                 let base64_iv = iv_buf.to_base64_urlsafe_encoded();
@@ -67,10 +89,16 @@ impl EncryptAes for String {
 /// This trait is used to AES decrypt a `String`
 pub trait DecryptAes {
     fn decrypt_b64_aes(&self, key_base64: &str, iv_base64: &str) -> Result<String, Box<dyn Error>>;
+    fn decrypt_b64_aes_cbc(
+        &self,
+        key_base64: &str,
+        iv_base64: &str,
+    ) -> Result<String, Box<dyn Error>>;
 }
 
 impl DecryptAes for String {
-    /// Decrypt a AES encoded `String`.
+    /// Decrypt a AES-GCM encoded `String`. Data that has been
+    /// modified is detected by the authentication tag.
     ///
     /// # Arguments
     ///
@@ -81,6 +109,35 @@ impl DecryptAes for String {
     ///
     /// - `String` - plaintext
     fn decrypt_b64_aes(&self, key_base64: &str, iv_base64: &str) -> Result<String, Box<dyn Error>> {
+        let encrypted_data = Vec::from_base64_urlsafe_encoded(self.trim_matches(char::from(0)))?;
+        if encrypted_data.len() < TAG_LENGTH {
+            return Err("aes encrypted data is too short".into());
+        }
+        let (encrypted_data, tag) = encrypted_data.split_at(encrypted_data.len() - TAG_LENGTH);
+        let iv = Vec::from_base64_urlsafe_encoded(iv_base64.trim_matches(char::from(0)))?;
+        let key = Vec::from_base64_urlsafe_encoded(key_base64.trim_matches(char::from(0)))?;
+        let cipher = Cipher::aes_256_gcm();
+        let plaintext = decrypt_aead(cipher, &key, Some(&iv), &[], encrypted_data, tag)?;
+        let p: String = String::from_utf8(plaintext)?;
+        Ok(p)
+    }
+
+    /// Decrypt a AES-CBC encoded `String`. Only used to read secrets
+    /// that have been stored before AES-GCM was introduced.
+    ///
+    /// # Arguments
+    ///
+    /// - key_base64 - base64 encoded key that was used to encrypt the data
+    /// - iv_base64 - base64 encoded iv that was used to encrypt the data
+    ///
+    /// # Returns
+    ///
+    /// - `String` - plaintext
+    fn decrypt_b64_aes_cbc(
+        &self,
+        key_base64: &str,
+        iv_base64: &str,
+    ) -> Result<String, Box<dyn Error>> {
         let encrypted_data = Vec::from_base64_urlsafe_encoded(self.trim_matches(char::from(0)))?;
         let iv = Vec::from_base64_urlsafe_encoded(iv_base64.trim_matches(char::from(0)))?;
         let key = Vec::from_base64_urlsafe_encoded(key_base64.trim_matches(char::from(0)))?;
@@ -90,3 +147,204 @@ impl DecryptAes for String {
         Ok(p)
     }
 }
+
+/// Creates a random data key that encrypts all fields
+/// of a stored secret (envelope encryption).
+///
+/// # Returns
+///
+/// - `Vec<u8>` - the data key
+pub fn create_data_key() -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut data_key = vec![0; KEY_LENGTH];
+    rand_bytes(&mut data_key)?;
+    Ok(data_key)
+}
+
+/// AES-GCM encrypts a String slice with a data key and a randomly
+/// chosen iv. The iv, the encrypted data and the authentication tag
+/// are returned as one base64 encoded value.
+///
+/// # Arguments
+///
+/// - data_key - key created by `create_data_key()`
+/// - plaintext - data to encrypt
+///
+/// # Returns
+///
+/// - `String` - base64 encoded iv, encrypted data and tag
+pub fn encrypt_with_data_key(data_key: &[u8], plaintext: &str) -> Result<String, Box<dyn Error>> {
+    let envelope = encrypt_bytes_with_data_key(data_key, plaintext.as_bytes())?;
+    Ok(envelope.to_base64_urlsafe_encoded())
+}
+
+/// Decrypts a value that has been encrypted with `encrypt_with_data_key()`.
+///
+/// # Arguments
+///
+/// - data_key - key that was used to encrypt the data
+/// - encrypted_data_base64 - base64 encoded iv, encrypted data and tag
+///
+/// # Returns
+///
+/// - `String` - plaintext
+pub fn decrypt_with_data_key(
+    data_key: &[u8],
+    encrypted_data_base64: &str,
+) -> Result<String, Box<dyn Error>> {
+    let envelope = Vec::from_base64_urlsafe_encoded(encrypted_data_base64)?;
+    let plaintext = decrypt_bytes_with_data_key(data_key, &envelope)?;
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// AES-GCM encrypts bytes with a data key and a randomly chosen iv.
+///
+/// # Arguments
+///
+/// - data_key - 32 bytes key
+/// - plaintext - data to encrypt
+///
+/// # Returns
+///
+/// - `Vec<u8>` - iv, encrypted data and tag
+pub fn encrypt_bytes_with_data_key(
+    data_key: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut iv_buf = [0; IV_LENGTH];
+    rand_bytes(&mut iv_buf)?;
+    let mut tag_buf = [0; TAG_LENGTH];
+    let encrypted_data = encrypt_aead(
+        Cipher::aes_256_gcm(),
+        data_key,
+        Some(&iv_buf),
+        &[],
+        plaintext,
+        &mut tag_buf,
+    )?;
+    let mut envelope = iv_buf.to_vec();
+    envelope.extend_from_slice(&encrypted_data);
+    envelope.extend_from_slice(&tag_buf);
+    Ok(envelope)
+}
+
+/// Decrypts bytes that have been encrypted with `encrypt_bytes_with_data_key()`.
+///
+/// # Arguments
+///
+/// - data_key - key that was used to encrypt the data
+/// - envelope - iv, encrypted data and tag
+///
+/// # Returns
+///
+/// - `Vec<u8>` - plaintext
+pub fn decrypt_bytes_with_data_key(
+    data_key: &[u8],
+    envelope: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if envelope.len() < IV_LENGTH + TAG_LENGTH {
+        return Err("aes encrypted data is too short".into());
+    }
+    let (iv, encrypted_data) = envelope.split_at(IV_LENGTH);
+    let (encrypted_data, tag) = encrypted_data.split_at(encrypted_data.len() - TAG_LENGTH);
+    let plaintext = decrypt_aead(
+        Cipher::aes_256_gcm(),
+        data_key,
+        Some(iv),
+        &[],
+        encrypted_data,
+        tag,
+    )?;
+    Ok(plaintext)
+}
+
+/// Creates a random salt that is used to derive
+/// a key from a passphrase.
+///
+/// # Returns
+///
+/// - `String` - base64 encoded salt
+pub fn create_passphrase_salt() -> Result<String, Box<dyn Error>> {
+    let mut salt_buf = [0; SALT_LENGTH];
+    rand_bytes(&mut salt_buf)?;
+    Ok(salt_buf.to_base64_urlsafe_encoded())
+}
+
+/// Derives a key encryption key from a passphrase with PBKDF2.
+fn derive_key_from_passphrase(
+    passphrase: &str,
+    salt_base64: &str,
+) -> Result<[u8; KEY_LENGTH], Box<dyn Error>> {
+    let salt = Vec::from_base64_urlsafe_encoded(salt_base64)?;
+    let mut derived_key = [0; KEY_LENGTH];
+    pbkdf2_hmac(
+        passphrase.as_bytes(),
+        &salt,
+        PBKDF2_ITERATIONS,
+        MessageDigest::sha256(),
+        &mut derived_key,
+    )?;
+    Ok(derived_key)
+}
+
+/// Wraps an AES key (rfc 3394) with a key that is derived
+/// from a passphrase, so that the AES key alone cannot be
+/// used to decrypt the data.
+///
+/// # Arguments
+///
+/// - key_base64 - base64 encoded key that should be wrapped
+/// - passphrase - passphrase chosen by the sender
+/// - salt_base64 - base64 encoded salt to derive the key from the passphrase
+///
+/// # Returns
+///
+/// - `String` - base64 encoded wrapped key
+pub fn wrap_key_with_passphrase(
+    key_base64: &str,
+    passphrase: &str,
+    salt_base64: &str,
+) -> Result<String, Box<dyn Error>> {
+    let mut key = Vec::from_base64_urlsafe_encoded(key_base64)?;
+    let mut derived_key = derive_key_from_passphrase(passphrase, salt_base64)?;
+    let key_encryption_key = AesKey::new_encrypt(&derived_key);
+    derived_key.zeroize();
+    let key_encryption_key = key_encryption_key.map_err(|_| "cannot build key encryption key")?;
+    let mut wrapped_key = [0; WRAPPED_KEY_LENGTH];
+    let result = wrap_key(&key_encryption_key, None, &mut wrapped_key, &key);
+    key.zeroize();
+    result.map_err(|_| "cannot wrap key")?;
+    Ok(wrapped_key.to_base64_urlsafe_encoded())
+}
+
+/// Unwraps an AES key that has been wrapped with `wrap_key_with_passphrase()`.
+/// A wrong passphrase is detected by the integrity check of rfc 3394.
+///
+/// # Arguments
+///
+/// - wrapped_key_base64 - base64 encoded wrapped key
+/// - passphrase - passphrase entered by the receiver
+/// - salt_base64 - base64 encoded salt to derive the key from the passphrase
+///
+/// # Returns
+///
+/// - `String` - base64 encoded key
+pub fn unwrap_key_with_passphrase(
+    wrapped_key_base64: &str,
+    passphrase: &str,
+    salt_base64: &str,
+) -> Result<String, Box<dyn Error>> {
+    let wrapped_key = Vec::from_base64_urlsafe_encoded(wrapped_key_base64)?;
+    if wrapped_key.len() != WRAPPED_KEY_LENGTH {
+        return Err("wrapped key has an invalid length".into());
+    }
+    let mut derived_key = derive_key_from_passphrase(passphrase, salt_base64)?;
+    let key_encryption_key = AesKey::new_decrypt(&derived_key);
+    derived_key.zeroize();
+    let key_encryption_key = key_encryption_key.map_err(|_| "cannot build key encryption key")?;
+    let mut key = [0; KEY_LENGTH];
+    unwrap_key(&key_encryption_key, None, &mut key, &wrapped_key)
+        .map_err(|_| "wrong passphrase")?;
+    let key_base64 = key.to_base64_urlsafe_encoded();
+    key.zeroize();
+    Ok(key_base64)
+}
@@ -49,7 +49,7 @@ impl fmt::Display for AuthenticatedUser {
 }
 
 /// Administrators are still users just with a different scope
-pub struct AuthenticatedAdministrator(AuthenticatedUser);
+pub struct AuthenticatedAdministrator(pub AuthenticatedUser);
 
 /// Holds the information of an authenticated user
 impl AuthenticatedUser {
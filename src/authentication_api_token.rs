@@ -0,0 +1,99 @@
+use crate::configuration::ApplicationConfiguration;
+use actix_web::{dev::ServiceRequest, web, Error, HttpMessage};
+use actix_web_httpauth::extractors::bearer::{BearerAuth, Config};
+use actix_web_httpauth::extractors::AuthenticationError;
+use log::{info, warn};
+use openssl::{memcmp, sha::sha256};
+use secstr::SecStr;
+use serde::Deserialize;
+
+/// Holds a token that allows automation, e.g. provisioning
+/// scripts, to send secrets through the api.
+#[derive(Clone, Deserialize, Debug)]
+pub struct ApiToken {
+    /// name of the token, e.g. the script using it. Only used for logging.
+    pub name: String,
+    pub token: SecStr,
+    /// mail addresses that may be used as sender with this token,
+    /// otherwise every holder of the token could impersonate anyone.
+    pub allowed_senders: Vec<String>,
+}
+
+impl ApiToken {
+    /// Compares the given token with the stored one. Both tokens are
+    /// hashed first, so that the comparison takes constant time
+    /// regardless of the token length.
+    ///
+    /// # Arguments
+    ///
+    /// - `token`: token sent by the client
+    ///
+    /// # Returns
+    ///
+    /// - `bool`
+    pub fn matches(&self, token: &str) -> bool {
+        memcmp::eq(&sha256(self.token.unsecure()), &sha256(token.as_bytes()))
+    }
+
+    /// Checks if secrets may be sent in the name of the given
+    /// mail address with this token.
+    ///
+    /// # Arguments
+    ///
+    /// - `from_email`: sender of the secret
+    ///
+    /// # Returns
+    ///
+    /// - `bool`
+    pub fn is_allowed_sender(&self, from_email: &str) -> bool {
+        self.allowed_senders
+            .iter()
+            .any(|allowed_sender| allowed_sender.eq_ignore_ascii_case(from_email.trim()))
+    }
+}
+
+/// Validates the bearer token of an api request against the
+/// configured `api_tokens`.
+///
+/// # Arguments
+///
+/// - `req`:         the incoming api request
+/// - `credentials`: bearer token sent in the `Authorization` header
+///
+/// # Returns
+///
+/// - `Result<ServiceRequest, (Error, ServiceRequest)>`
+pub async fn validate_api_token(
+    req: ServiceRequest,
+    credentials: BearerAuth,
+) -> Result<ServiceRequest, (Error, ServiceRequest)> {
+    let peer_address = match req.peer_addr() {
+        Some(peer_address) => peer_address.ip().to_string(),
+        None => "unknown".to_string(),
+    };
+    let app_data: Option<&web::Data<ApplicationConfiguration>> = req.app_data();
+    let api_token = app_data.and_then(|application_configuration| {
+        application_configuration
+            .configuration_file
+            .api_tokens
+            .as_ref()?
+            .iter()
+            .find(|api_token| api_token.matches(credentials.token()))
+            .cloned()
+    });
+    match api_token {
+        Some(api_token) => {
+            info!(
+                "api request from {} authenticated with token {}",
+                &peer_address, &api_token.name
+            );
+            // the handler checks the sender against the token
+            req.extensions_mut().insert(api_token);
+            Ok(req)
+        }
+        None => {
+            warn!("api request from {} with invalid token", &peer_address);
+            Err((AuthenticationError::from(Config::default()).into(), req))
+        }
+    }
+}
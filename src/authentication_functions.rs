@@ -38,11 +38,11 @@ pub fn get_authenticated_user(req: &HttpRequest) -> Result<AuthenticatedUser, Er
             debug!("cookie = {}", &cookie);
             let plain_cookie;
             {
-                let rsa_read_lock = application_configuration.rsa_keys.read().unwrap();
+                let rsa_read_lock = application_configuration.master_key.read().unwrap();
                 // when the rsa key pair already has been loaded,
                 // the cookie value is encrypted with the rsa public
                 // key otherwise its simply base64 encoded.
-                plain_cookie = get_plain_cookie_string(&cookie, &rsa_read_lock);
+                plain_cookie = get_plain_cookie_string(&cookie, rsa_read_lock.as_ref());
             }
             if let Ok(parsed_cookie_uuid) = Uuid::parse_str(&plain_cookie) {
                 if let Some(authenticated_user) = application_configuration
@@ -94,11 +94,11 @@ pub fn update_authenticated_user_cookie_lifetime(req: &HttpRequest) -> HttpRespo
             &header_value
         );
         if let Some(cookie) = header_value.get_value_for_cookie_with_name(COOKIE_NAME) {
-            let rsa_read_lock = application_configuration.rsa_keys.read().unwrap();
+            let rsa_read_lock = application_configuration.master_key.read().unwrap();
             // when the rsa key pair already has been loaded,
             // the cookie value is encrypted with the rsa public
             // key otherwise its simply base64 encoded.
-            let plain_cookie = get_plain_cookie_string(&cookie, &rsa_read_lock);
+            let plain_cookie = get_plain_cookie_string(&cookie, rsa_read_lock.as_ref());
             if let Ok(parsed_cookie_uuid) = Uuid::parse_str(&plain_cookie) {
                 let mut shared_authenticated_users_write_lock = application_configuration
                     .shared_authenticated_users
@@ -116,14 +116,14 @@ pub fn update_authenticated_user_cookie_lifetime(req: &HttpRequest) -> HttpRespo
                     // will not remove this entry
                     authenticated_user.update_timestamp();
                     // create cookie with the same value but renewed cookie lifetime
-                    let rsa_read_lock = application_configuration.rsa_keys.read().unwrap();
+                    let rsa_read_lock = application_configuration.master_key.read().unwrap();
                     let updated_cookie = build_new_authentication_cookie(
                         &parsed_cookie_uuid.to_string(),
                         application_configuration
                             .configuration_file
                             .max_cookie_age_seconds,
                         &application_configuration.configuration_file.get_domain(),
-                        &rsa_read_lock,
+                        rsa_read_lock.as_ref(),
                     );
                     let cookie_response = build_new_cookie_response(
                         &updated_cookie,
@@ -318,7 +318,7 @@ impl Login for LdapCommonConfiguration {
                         &peer_ip,
                     )
                 {
-                    let rsa_read_lock = application_configuration.rsa_keys.read().unwrap();
+                    let rsa_read_lock = application_configuration.master_key.read().unwrap();
                     // when the rsa key pair already has been loaded,
                     // the cookie value is encrypted with the rsa public
                     // key otherwise its simply base64 encoded.
@@ -328,7 +328,7 @@ impl Login for LdapCommonConfiguration {
                             .configuration_file
                             .max_cookie_age_seconds,
                         &application_configuration.configuration_file.get_domain(),
-                        &rsa_read_lock,
+                        rsa_read_lock.as_ref(),
                     );
                     let proto_fqdn = format!(
                         "https://{}",
@@ -228,11 +228,11 @@ where
             debug!("looking for cookie {}", &COOKIE_NAME);
             if let Some(cookie) = header_value.get_value_for_cookie_with_name(COOKIE_NAME) {
                 debug!("possible authorization cookie = {}", &cookie);
-                let rsa_read_lock = application_configuration.rsa_keys.read().unwrap();
+                let rsa_read_lock = application_configuration.master_key.read().unwrap();
                 // when the rsa key pair already has been loaded,
                 // the cookie value is encrypted with the rsa public
                 // key otherwise its simply base64 encoded.
-                let plain_cookie = get_plain_cookie_string(&cookie, &rsa_read_lock);
+                let plain_cookie = get_plain_cookie_string(&cookie, rsa_read_lock.as_ref());
                 if let Ok(parsed_cookie_uuid) = Uuid::parse_str(&plain_cookie) {
                     if let Some(auth_request) = application_configuration
                         .shared_authenticated_users
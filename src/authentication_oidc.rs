@@ -420,7 +420,7 @@ impl Login for OidcConfiguration {
                 &email
             );
             
-            let rsa_read_lock = application_configuration.rsa_keys.read().unwrap();
+            let rsa_read_lock = application_configuration.master_key.read().unwrap();
             // when the rsa key pair already has been loaded,
             // the cookie value is encrypted with the rsa public
             // key otherwise its simply base64 encoded.
@@ -430,7 +430,7 @@ impl Login for OidcConfiguration {
                     .configuration_file
                     .max_cookie_age_seconds,
                 &application_configuration.configuration_file.get_domain(),
-                &rsa_read_lock,
+                rsa_read_lock.as_ref(),
             );
             return build_redirect_to_resource_url_response(
                 &cookie,
@@ -0,0 +1,129 @@
+use lmtyas::base64_trait::Base64StringConversions;
+use lmtyas::cli_parser::*;
+use lmtyas::secret_functions::{Secret, SECRET_FORMAT_VERSION};
+use reqwest::blocking::Client;
+use reqwest::{header, Certificate};
+use std::error::Error;
+use std::fs::{read, read_to_string};
+use std::io::Read;
+use std::process::ExitCode;
+use zeroize::Zeroize;
+
+fn main() -> ExitCode {
+    let clap_arg_matches = build_cli_client_command().get_matches();
+    let result = match clap_arg_matches.subcommand() {
+        Some((CMD_SEND, send_arg_matches)) => send_secret(&clap_arg_matches, send_arg_matches),
+        _ => Err(Box::<dyn Error>::from("unknown command")),
+    };
+    match result {
+        Ok(output) => {
+            println!("{}", output);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Sends a secret through the api of the webservice.
+///
+/// # Arguments
+///
+/// - `arg_matches`:      global command line parameters
+/// - `send_arg_matches`: command line parameters of the `send` command
+///
+/// # Returns
+///
+/// - `Result<String, Box<dyn Error>>` - either the json response with the secret id or an error
+fn send_secret(
+    arg_matches: &clap::ArgMatches,
+    send_arg_matches: &clap::ArgMatches,
+) -> Result<String, Box<dyn Error>> {
+    let mut plain_secret = read_secret(get_arg(send_arg_matches, ARG_SECRET_FILE))?;
+    let secret = Secret {
+        version: SECRET_FORMAT_VERSION,
+        from_email: get_arg(send_arg_matches, ARG_FROM).to_string(),
+        from_display_name: get_arg(send_arg_matches, ARG_FROM_DISPLAY_NAME).to_string(),
+        to_email: get_arg(send_arg_matches, ARG_TO).to_string(),
+        to_display_name: String::new(),
+        context: get_arg(send_arg_matches, ARG_CONTEXT).to_string(),
+        secret: plain_secret.to_base64_encoded(),
+        valid_for_hours: send_arg_matches
+            .get_one::<i64>(ARG_VALID_FOR_HOURS)
+            .copied(),
+        expires_at: None,
+        receivers: Vec::new(),
+        passphrase: None,
+        passphrase_salt: None,
+        data_key: None,
+        key_id: None,
+        attachment: None,
+    };
+    plain_secret.zeroize();
+    let mut json_secret = serde_json::to_string(&secret)?;
+
+    let mut client_builder = Client::builder().https_only(true);
+    if let Some(ca_file) = arg_matches.get_one::<String>(ARG_CA_FILE) {
+        client_builder =
+            client_builder.add_root_certificate(Certificate::from_pem(&read(ca_file)?)?);
+    }
+    if arg_matches.get_flag(ARG_INSECURE) {
+        client_builder = client_builder.danger_accept_invalid_certs(true);
+    }
+    let client = client_builder.build()?;
+    let url = format!(
+        "{}/api/v1/secret",
+        get_arg(arg_matches, ARG_URL).trim_end_matches('/')
+    );
+    let response = client
+        .post(url)
+        .bearer_auth(read_api_token(arg_matches)?)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(json_secret.clone())
+        .send();
+    json_secret.zeroize();
+    let response = response?;
+    let status = response.status();
+    let text = response.text()?;
+    if status.is_success() {
+        Ok(text)
+    } else {
+        Err(Box::<dyn Error>::from(format!("{}: {}", status, text)))
+    }
+}
+
+/// Reads the secret from a file or from stdin if the file name is `-`.
+/// A trailing line break, e.g. added by `echo`, is removed.
+fn read_secret(secret_file: &str) -> Result<String, Box<dyn Error>> {
+    let mut secret = String::new();
+    if secret_file == "-" {
+        std::io::stdin().read_to_string(&mut secret)?;
+    } else {
+        secret = read_to_string(secret_file)?;
+    }
+    let secret_len = secret.trim_end_matches(['\r', '\n']).len();
+    secret.truncate(secret_len);
+    Ok(secret)
+}
+
+/// Reads the api token from the given file or the environment.
+fn read_api_token(arg_matches: &clap::ArgMatches) -> Result<String, Box<dyn Error>> {
+    match arg_matches.get_one::<String>(ARG_API_TOKEN_FILE) {
+        Some(api_token_file) => Ok(read_to_string(api_token_file)?.trim().to_string()),
+        None => match std::env::var(ENV_API_TOKEN) {
+            Ok(api_token) => Ok(api_token),
+            Err(_) => Err(Box::<dyn Error>::from(format!(
+                "no api token given, use --token-file or set ${}",
+                ENV_API_TOKEN
+            ))),
+        },
+    }
+}
+
+/// Returns a command line parameter that is either required
+/// or has a default value.
+fn get_arg<'a>(arg_matches: &'a clap::ArgMatches, id: &str) -> &'a str {
+    arg_matches.get_one::<String>(id).unwrap()
+}
@@ -3,11 +3,13 @@ use crate::authentication_middleware::cleanup_authentication_state_hashmap;
 #[cfg(feature = "oidc-auth-ldap")]
 use crate::authentication_oidc::cleanup_oidc_authentication_data_hashmap;
 use crate::configuration::ApplicationConfiguration;
+use crate::secret_functions::cleanup_expired_secret_files;
 use crate::TIMER_VEC_CAPACITY;
 use log::info;
 use timer::{Guard, Timer};
 
 const TIMER_INTERVAL: i64 = 5;
+const SECRET_TIMER_INTERVAL: i64 = 60;
 pub struct TimerGuard(Vec<(Guard, Timer)>);
 
 /// Timer that calls a cleanup routine every 15 seconds
@@ -79,6 +81,59 @@ fn build_cleanup_oidc_authentication_state_hashmap_timer(
     )
 }
 
+/// Timer that calls a cleanup routine every 60 seconds
+/// and removes expired secret files
+fn build_cleanup_expired_secret_files_timer(
+    application_configuration: &ApplicationConfiguration,
+) -> (Guard, Timer) {
+    let secret_duration = application_configuration
+        .configuration_file
+        .max_secret_age_seconds;
+    let cleanup_expired_secret_files_timer = Timer::new();
+    let secret_directory = application_configuration
+        .configuration_file
+        .secret_directory
+        .clone();
+    let master_key = application_configuration.master_key.clone();
+    let email_configuration = application_configuration
+        .configuration_file
+        .email_configuration
+        .clone();
+    (
+        cleanup_expired_secret_files_timer.schedule_repeating(
+            chrono::Duration::seconds(SECRET_TIMER_INTERVAL),
+            move || {
+                cleanup_expired_secret_files(
+                    &secret_directory,
+                    secret_duration,
+                    &master_key,
+                    &email_configuration,
+                )
+            },
+        ),
+        cleanup_expired_secret_files_timer,
+    )
+}
+
+/// Timer that checks every 60 seconds if the master key has been
+/// loaded for more than `auto_seal_seconds` and seals the server
+fn build_auto_seal_timer(
+    application_configuration: &ApplicationConfiguration,
+) -> Option<(Guard, Timer)> {
+    let auto_seal_seconds = application_configuration
+        .configuration_file
+        .auto_seal_seconds?;
+    let auto_seal_timer = Timer::new();
+    let application_configuration = application_configuration.clone();
+    Some((
+        auto_seal_timer.schedule_repeating(
+            chrono::Duration::seconds(SECRET_TIMER_INTERVAL),
+            move || application_configuration.auto_seal(auto_seal_seconds),
+        ),
+        auto_seal_timer,
+    ))
+}
+
 /// Build a vector of timer guards and timers to keep
 /// the references until the program ends.
 pub fn build_cleaup_timers(application_configuration: &ApplicationConfiguration) -> TimerGuard {
@@ -89,10 +144,16 @@ pub fn build_cleaup_timers(application_configuration: &ApplicationConfiguration)
     timer_guards.push(build_cleanup_authenticated_users_hashmap_timer(
         application_configuration,
     ));
+    timer_guards.push(build_cleanup_expired_secret_files_timer(
+        application_configuration,
+    ));
     #[cfg(feature = "oidc-auth-ldap")]
     timer_guards.push(build_cleanup_oidc_authentication_state_hashmap_timer(
         application_configuration,
     ));
-    info!("started {} cleanup timers", TIMER_VEC_CAPACITY);
+    if let Some(auto_seal_timer) = build_auto_seal_timer(application_configuration) {
+        timer_guards.push(auto_seal_timer);
+    }
+    info!("started {} cleanup timers", timer_guards.len());
     TimerGuard(timer_guards)
 }
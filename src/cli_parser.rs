@@ -4,22 +4,201 @@ use crate::PROGRAM_NAME;
 use crate::PROGRAM_VERSION;
 
 pub const ARG_CONFIG_FILE: &str = "configfile";
+pub const ARG_SPLIT_RSA_PASSWORD: &str = "splitrsapassword";
+pub const CMD_KEYGEN: &str = "keygen";
+pub const CMD_CHANGE_PASSPHRASE: &str = "change-passphrase";
+pub const CMD_VERIFY_CONFIG: &str = "verify-config";
+pub const ARG_PRIVATE_KEY_FILE: &str = "privatekeyfile";
+pub const ARG_PUBLIC_KEY_FILE: &str = "publickeyfile";
+pub const ARG_KEY_BITS: &str = "keybits";
+/// bit size of new rsa keys
+pub const DEFAULT_RSA_KEY_BITS: &str = "4096";
 
-/// Parse the command line parameters with help of clap.
-pub fn parse_cli_parameters() -> clap::ArgMatches {
+/// Build the json configuration file argument of the webservice.
+fn config_file_arg() -> clap::Arg {
+    clap::Arg::new(ARG_CONFIG_FILE)
+        .short('c')
+        .long("config-file")
+        .value_name("json configuration file")
+        .help("json file with the configuration of the webservice")
+        .num_args(1)
+}
+
+/// Build the private key file argument of the key management subcommands.
+fn private_key_file_arg() -> clap::Arg {
+    clap::Arg::new(ARG_PRIVATE_KEY_FILE)
+        .long("private-key-file")
+        .value_name("pem file")
+        .help("passphrase protected private key, see `rsa_private_key_file`")
+        .num_args(1)
+        .required(true)
+}
+
+/// Build the command line definition of the webservice with help of clap.
+pub fn build_cli_command() -> clap::Command {
     clap::Command::new(PROGRAM_NAME)
         .version(PROGRAM_VERSION)
         .author(PROGRAM_AUTHORS)
         .about(PROGRAM_DESCRIPTION)
+        .arg(config_file_arg().required_unless_present(ARG_SPLIT_RSA_PASSWORD))
+        .arg(
+            clap::Arg::new(ARG_SPLIT_RSA_PASSWORD)
+                .long("split-rsa-password")
+                .value_names(["threshold", "shares"])
+                .help("read the rsa password from stdin and split it into shares for the administrators")
+                .num_args(2)
+                .value_parser(clap::value_parser!(u8))
+                .conflicts_with(ARG_CONFIG_FILE),
+        )
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+        .subcommand(
+            clap::Command::new(CMD_KEYGEN)
+                .about("create a new rsa key pair, the passphrase is read from stdin")
+                .arg(private_key_file_arg())
+                .arg(
+                    clap::Arg::new(ARG_PUBLIC_KEY_FILE)
+                        .long("public-key-file")
+                        .value_name("pem file")
+                        .help("public key, see `rsa_public_key_file`")
+                        .num_args(1)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new(ARG_KEY_BITS)
+                        .long("bits")
+                        .value_name("bits")
+                        .help("size of the rsa key")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(u32))
+                        .default_value(DEFAULT_RSA_KEY_BITS),
+                ),
+        )
+        .subcommand(
+            clap::Command::new(CMD_CHANGE_PASSPHRASE)
+                .about("encrypt the private key with a new passphrase, the old and the new passphrase are read from stdin")
+                .arg(private_key_file_arg()),
+        )
+        .subcommand(
+            clap::Command::new(CMD_VERIFY_CONFIG)
+                .about("check the configuration file and unlock the keys with the password file or the password read from stdin")
+                .arg(config_file_arg().required(true)),
+        )
+        .after_help(r##"See README.md for details."##)
+}
+
+/// Parse the command line parameters with help of clap.
+pub fn parse_cli_parameters() -> clap::ArgMatches {
+    build_cli_command().get_matches()
+}
+
+pub const CLI_PROGRAM_NAME: &str = "lmtyas-cli";
+pub const ARG_URL: &str = "url";
+pub const ARG_API_TOKEN_FILE: &str = "apitokenfile";
+pub const ARG_CA_FILE: &str = "cafile";
+pub const ARG_INSECURE: &str = "insecure";
+pub const CMD_SEND: &str = "send";
+pub const ARG_FROM: &str = "from";
+pub const ARG_FROM_DISPLAY_NAME: &str = "fromdisplayname";
+pub const ARG_TO: &str = "to";
+pub const ARG_CONTEXT: &str = "context";
+pub const ARG_SECRET_FILE: &str = "secretfile";
+pub const ARG_VALID_FOR_HOURS: &str = "validforhours";
+/// environment variable that holds the api token if no token file is given
+pub const ENV_API_TOKEN: &str = "LMTYAS_API_TOKEN";
+
+/// Build the command line definition of the client with help of clap.
+pub fn build_cli_client_command() -> clap::Command {
+    clap::Command::new(CLI_PROGRAM_NAME)
+        .version(PROGRAM_VERSION)
+        .author(PROGRAM_AUTHORS)
+        .about("command line client that sends secrets through the api of the lmtyas webservice")
+        .subcommand_required(true)
         .arg(
-            clap::Arg::new(ARG_CONFIG_FILE)
-                .short('c')
-                .long("config-file")
-                .value_name("json configuration file")
-                .help("json file with the configuration of the webservice")
+            clap::Arg::new(ARG_URL)
+                .short('u')
+                .long("url")
+                .value_name("url")
+                .help("base url of the webservice")
                 .num_args(1)
-                .required(true),
+                .default_value("https://127.0.0.1:8844"),
+        )
+        .arg(
+            clap::Arg::new(ARG_API_TOKEN_FILE)
+                .short('t')
+                .long("token-file")
+                .value_name("file")
+                .help(format!(
+                    "file with the api token, otherwise the token is read from ${}",
+                    ENV_API_TOKEN
+                ))
+                .num_args(1),
+        )
+        .arg(
+            clap::Arg::new(ARG_CA_FILE)
+                .long("ca-file")
+                .value_name("pem file")
+                .help("CA certificate to verify the webservice")
+                .num_args(1),
+        )
+        .arg(
+            clap::Arg::new(ARG_INSECURE)
+                .long("insecure")
+                .help("do not verify the certificate of the webservice")
+                .action(clap::ArgAction::SetTrue),
+        )
+        .subcommand(
+            clap::Command::new(CMD_SEND)
+                .about("send a secret to a receiver")
+                .arg(
+                    clap::Arg::new(ARG_FROM)
+                        .short('f')
+                        .long("from")
+                        .value_name("email address")
+                        .help("email address of the sender")
+                        .num_args(1)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new(ARG_FROM_DISPLAY_NAME)
+                        .long("from-display-name")
+                        .value_name("name")
+                        .help("display name of the sender")
+                        .num_args(1)
+                        .default_value(""),
+                )
+                .arg(
+                    clap::Arg::new(ARG_TO)
+                        .long("to")
+                        .value_name("email address")
+                        .help("email address of the receiver, separate multiple receivers with commas")
+                        .num_args(1)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new(ARG_CONTEXT)
+                        .long("context")
+                        .value_name("context")
+                        .help("what the secret is for, e.g. \"VPN\"")
+                        .num_args(1)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new(ARG_SECRET_FILE)
+                        .long("secret-file")
+                        .value_name("file")
+                        .help("file with the secret, `-` reads the secret from stdin")
+                        .num_args(1)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new(ARG_VALID_FOR_HOURS)
+                        .long("valid-for-hours")
+                        .value_name("hours")
+                        .help("lifetime of the secret, must be one of `secret_valid_for_hours`")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(i64)),
+                ),
         )
         .after_help(r##"See README.md for details."##)
-        .get_matches()
 }
@@ -1,4 +1,5 @@
 use crate::authenticated_user::SharedAuthenticatedUsersHashMap;
+use crate::authentication_api_token::ApiToken;
 use crate::authentication_middleware::SharedRequestData;
 #[cfg(feature = "authentication-oidc")]
 use crate::authentication_oidc::{OidcConfiguration, SharedOidcVerificationDataHashMap};
@@ -9,8 +10,14 @@ use crate::ldap_common::LdapCommonConfiguration;
 #[cfg(any(feature = "ldap-auth", feature = "authentication-oidc"))]
 use crate::login_user_trait::Login;
 use crate::mail_configuration::SendEMailConfiguration;
+use crate::master_key_trait::{read_master_key_from_files, MasterKey};
+#[cfg(feature = "pkcs11")]
+use crate::pkcs11_functions::{Pkcs11Configuration, Pkcs11Keys};
 use crate::rsa_functions::{RsaKeys, RsaPrivateKeyPassword};
 use crate::secret_functions::SharedSecretData;
+use crate::shamir_functions::{ShamirConfiguration, ShamirShareCollector, MIN_SHAMIR_THRESHOLD};
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
 #[cfg(feature = "authentication-oidc")]
 use openidconnect::{
     core::{CoreClient, CoreProviderMetadata},
@@ -18,13 +25,22 @@ use openidconnect::{
     ClientId, ClientSecret, IssuerUrl, RedirectUrl,
 };
 use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslMethod, SslOptions};
+use secstr::SecStr;
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 
+/// name of the systemd credential (`LoadCredential=`)
+/// with the password for the RSA private key
+pub const RSA_PASSWORD_CREDENTIAL: &str = "lmtyas-rsa-password";
+
+/// default for `max_secret_age_seconds` in configuration files
+/// that were written before the setting existed: 7 days
+pub const DEFAULT_MAX_SECRET_AGE_SECONDS: i64 = 604800;
+
 /// valid secure cipers for TLS1v2 and TLS 1v3
 const CIPHER_LIST: &str = concat!(
     "TLS_AES_128_GCM_SHA256:",
@@ -40,6 +56,11 @@ const CIPHER_LIST: &str = concat!(
     "DHE-RSA-AES256-GCM-SHA384"
 );
 
+/// serde default of `max_secret_age_seconds`
+fn default_max_secret_age_seconds() -> i64 {
+    DEFAULT_MAX_SECRET_AGE_SECONDS
+}
+
 /// Holds the deserialized entries of the json file
 /// that is passed to the program
 #[derive(Clone, Deserialize, Debug)]
@@ -49,11 +70,22 @@ pub struct ConfigurationFile {
     pub ssl_certificate_chain_file: String,
     pub rsa_private_key_file: String,
     pub rsa_public_key_file: String,
+    pub rsa_key_ring_directory: Option<String>,
+    pub rsa_private_key_password_file: Option<String>,
+    pub shamir_configuration: Option<ShamirConfiguration>,
+    pub auto_seal_seconds: Option<i64>,
+    #[cfg(feature = "pkcs11")]
+    pub pkcs11_configuration: Option<Pkcs11Configuration>,
     pub secret_directory: String,
     pub email_configuration: SendEMailConfiguration,
     pub admin_accounts: Vec<String>,
     pub max_authrequest_age_seconds: i64,
     pub max_cookie_age_seconds: i64,
+    #[serde(default = "default_max_secret_age_seconds")]
+    pub max_secret_age_seconds: i64,
+    #[serde(default)]
+    pub secret_valid_for_hours: Vec<i64>,
+    pub accept_pkcs1_links_until: Option<String>,
     pub fqdn: String,
     #[cfg(feature = "ldap-common")]
     pub ldap_common_configuration: LdapCommonConfiguration,
@@ -62,6 +94,9 @@ pub struct ConfigurationFile {
     pub login_hint: String,
     pub mail_hint: Option<String>,
     pub imprint: Imprint,
+    pub api_tokens: Option<Vec<ApiToken>>,
+    pub external_receiver_domains: Option<Vec<String>>,
+    pub max_attachment_bytes: Option<usize>,
 }
 
 impl ConfigurationFile {
@@ -73,6 +108,174 @@ impl ConfigurationFile {
             None => self.fqdn.clone(),
         }
     }
+
+    /// get the file with the password for the RSA private key, either
+    /// `rsa_private_key_password_file` or the systemd credential
+    /// `RSA_PASSWORD_CREDENTIAL` in `$CREDENTIALS_DIRECTORY`.
+    pub fn get_rsa_private_key_password_file(&self) -> Option<PathBuf> {
+        if let Some(rsa_private_key_password_file) = &self.rsa_private_key_password_file {
+            return Some(PathBuf::from(rsa_private_key_password_file));
+        }
+        std::env::var_os("CREDENTIALS_DIRECTORY")
+            .map(|credentials_directory| {
+                Path::new(&credentials_directory).join(RSA_PASSWORD_CREDENTIAL)
+            })
+            .filter(|credential| credential.is_file())
+    }
+
+    /// Reads the master key from the configured key files and key ring
+    /// directory or opens the PKCS#11 token.
+    ///
+    /// # Arguments
+    ///
+    /// - `rsa_private_key_password`: password of the private key or pin of the token
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Box<dyn MasterKey>, Box<dyn Error>>`
+    pub fn read_master_key(
+        &self,
+        rsa_private_key_password: &SecStr,
+    ) -> Result<Box<dyn MasterKey>, Box<dyn Error>> {
+        #[cfg(feature = "pkcs11")]
+        if let Some(pkcs11_configuration) = &self.pkcs11_configuration {
+            // the password is the pin of the token
+            let pkcs11_keys = Pkcs11Keys::open(
+                pkcs11_configuration,
+                &self.rsa_public_key_file,
+                rsa_private_key_password,
+            )?;
+            return Ok(Box::new(pkcs11_keys));
+        }
+        read_master_key_from_files(
+            &self.rsa_private_key_file,
+            &self.rsa_public_key_file,
+            self.rsa_key_ring_directory.as_ref(),
+            rsa_private_key_password,
+        )
+    }
+
+    /// Checks the files and directories of the configuration that are
+    /// only accessed later on, e.g. when a secret is stored or a mail is sent.
+    ///
+    /// # Returns
+    ///
+    /// - `Vec<String>`: the problems that have been found
+    pub fn verify(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        for ssl_file in [&self.ssl_private_key_file, &self.ssl_certificate_chain_file] {
+            if !Path::new(ssl_file).is_file() {
+                problems.push(format!("ssl file {} does not exist", ssl_file));
+            }
+        }
+        match SslAcceptor::mozilla_intermediate(SslMethod::tls()) {
+            Ok(_) if !problems.is_empty() => {}
+            Ok(mut ssl_acceptor_builder) => {
+                if let Err(e) = ssl_acceptor_builder
+                    .set_private_key_file(&self.ssl_private_key_file, SslFiletype::PEM)
+                {
+                    problems.push(format!("ssl_private_key_file: {}", e));
+                } else if let Err(e) = ssl_acceptor_builder
+                    .set_certificate_chain_file(&self.ssl_certificate_chain_file)
+                {
+                    problems.push(format!("ssl_certificate_chain_file: {}", e));
+                } else if let Err(e) = ssl_acceptor_builder.check_private_key() {
+                    problems.push(format!("ssl key does not match the certificate: {}", e));
+                }
+            }
+            Err(e) => problems.push(format!("cannot build ssl acceptor: {}", e)),
+        }
+        if let Some(rsa_key_ring_directory) = &self.rsa_key_ring_directory {
+            if !Path::new(rsa_key_ring_directory).is_dir() {
+                problems.push(format!(
+                    "rsa_key_ring_directory {} is not a directory",
+                    rsa_key_ring_directory
+                ));
+            }
+        }
+        if let Some(rsa_private_key_password_file) = &self.rsa_private_key_password_file {
+            if !Path::new(rsa_private_key_password_file).is_file() {
+                problems.push(format!(
+                    "rsa_private_key_password_file {} does not exist",
+                    rsa_private_key_password_file
+                ));
+            }
+        }
+        if !Path::new(&self.secret_directory).is_dir() {
+            problems.push(format!(
+                "secret_directory {} is not a directory",
+                &self.secret_directory
+            ));
+        }
+        if let Err(e) = self.email_configuration.load_mail_template() {
+            problems.push(format!("mail_template_file: {}", e));
+        }
+        if let Err(e) = self.email_configuration.load_mail_template_html() {
+            problems.push(format!("mail_template_html_file: {}", e));
+        }
+        let mail_templates = self
+            .email_configuration
+            .localized_mail_templates
+            .iter()
+            .flat_map(|localized_mail_templates| localized_mail_templates.values())
+            .chain(self.email_configuration.reveal_receipt.iter())
+            .chain(self.email_configuration.expiry_notice.iter())
+            .chain(self.email_configuration.secret_request.iter())
+            .chain(self.email_configuration.external_receiver_link.iter())
+            .chain(self.email_configuration.external_receiver_code.iter());
+        for mail_template in mail_templates {
+            if let Err(e) = mail_template.load_mail_template() {
+                problems.push(format!(
+                    "{}: {}",
+                    mail_template.mail_template_file.display(),
+                    e
+                ));
+            }
+            if let Err(e) = mail_template.load_mail_template_html() {
+                problems.push(format!("mail_template_html_file: {}", e));
+            }
+        }
+        for mail_ca_certificate_file in self
+            .email_configuration
+            .mail_ca_certificate_files
+            .iter()
+            .flatten()
+        {
+            if !mail_ca_certificate_file.is_file() {
+                problems.push(format!(
+                    "mail_ca_certificate_file {} does not exist",
+                    mail_ca_certificate_file.display()
+                ));
+            }
+        }
+        problems
+    }
+
+    /// checks if links with PKCS#1 v1.5 padding, mailed by older versions,
+    /// are still accepted. They are only accepted until the point in time
+    /// `accept_pkcs1_links_until` because decrypting them exposes a padding oracle.
+    pub fn accepts_pkcs1_links(&self) -> bool {
+        match &self.accept_pkcs1_links_until {
+            Some(accept_pkcs1_links_until) => {
+                DateTime::parse_from_rfc3339(accept_pkcs1_links_until)
+                    .map(|until| Utc::now() < until)
+                    .unwrap_or(false)
+            }
+            None => false,
+        }
+    }
+
+    /// checks if the mail address belongs to one of the
+    /// `external_receiver_domains`, such receivers are
+    /// not looked up and reveal secrets without login.
+    pub fn is_external_receiver(&self, mail: &str) -> bool {
+        match (&self.external_receiver_domains, mail.rsplit_once('@')) {
+            (Some(external_receiver_domains), Some((_, domain))) => external_receiver_domains
+                .iter()
+                .any(|d| d.eq_ignore_ascii_case(domain)),
+            _ => false,
+        }
+    }
 }
 
 /// Loads a json file and deserializes it into an
@@ -88,17 +291,71 @@ impl ConfigurationFile {
         // check if tke rsa key files exists because they are loaded later on,
         // when the password is entered by the administator.
         // The server must not start if such a key component is missing.
-        if !Path::new(&parsed_config.rsa_private_key_file).exists() {
-            panic!(
+        #[cfg(feature = "pkcs11")]
+        let rsa_private_key_on_token = parsed_config.pkcs11_configuration.is_some();
+        #[cfg(not(feature = "pkcs11"))]
+        let rsa_private_key_on_token = false;
+        if !rsa_private_key_on_token && !Path::new(&parsed_config.rsa_private_key_file).exists() {
+            return Err(format!(
                 "rsa private key file {} does not exist!",
                 &parsed_config.rsa_private_key_file
-            );
+            )
+            .into());
         }
         if !Path::new(&parsed_config.rsa_public_key_file).exists() {
-            panic!(
+            return Err(format!(
                 "rsa public key file {} does not exist!",
                 &parsed_config.rsa_public_key_file
-            );
+            )
+            .into());
+        }
+        if let Some(shamir_configuration) = &parsed_config.shamir_configuration {
+            if shamir_configuration.threshold < MIN_SHAMIR_THRESHOLD {
+                return Err(format!(
+                    "shamir threshold must be at least {}!",
+                    MIN_SHAMIR_THRESHOLD
+                )
+                .into());
+            }
+            // a password file would unlock the keys without the shares
+            if let Some(rsa_private_key_password_file) =
+                parsed_config.get_rsa_private_key_password_file()
+            {
+                return Err(format!(
+                    "shamir_configuration cannot be used with the password file {}!",
+                    rsa_private_key_password_file.display()
+                )
+                .into());
+            }
+        }
+        if parsed_config.max_secret_age_seconds <= 0 {
+            return Err("max_secret_age_seconds must be greater than 0!".into());
+        }
+        if let Some(auto_seal_seconds) = parsed_config.auto_seal_seconds {
+            if auto_seal_seconds <= 0 {
+                return Err("auto_seal_seconds must be greater than 0!".into());
+            }
+        }
+        let max_valid_for_hours = parsed_config.max_secret_age_seconds / 3600;
+        if let Some(valid_for_hours) = parsed_config
+            .secret_valid_for_hours
+            .iter()
+            .find(|h| **h <= 0 || **h > max_valid_for_hours)
+        {
+            return Err(format!(
+                "secret_valid_for_hours entry {} must be between 1 and {} (max_secret_age_seconds)!",
+                valid_for_hours, max_valid_for_hours
+            )
+            .into());
+        }
+        if let Some(accept_pkcs1_links_until) = &parsed_config.accept_pkcs1_links_until {
+            if DateTime::parse_from_rfc3339(accept_pkcs1_links_until).is_err() {
+                return Err(format!(
+                    "accept_pkcs1_links_until {} is not a rfc3339 timestamp!",
+                    accept_pkcs1_links_until
+                )
+                .into());
+            }
         }
         #[cfg(feature = "ldap-auth")]
         parsed_config
@@ -117,8 +374,12 @@ pub struct ApplicationConfiguration {
     pub configuration_file: ConfigurationFile,
     /// password for rsa private key
     pub rsa_password: Arc<RwLock<RsaPrivateKeyPassword>>,
-    // RSA keys
-    pub rsa_keys: Arc<RwLock<RsaKeys>>,
+    // RSA or EC keys
+    pub master_key: Arc<RwLock<Box<dyn MasterKey>>>,
+    /// time when the master key has been loaded, `None` while sealed
+    pub master_key_loaded_at: Arc<RwLock<Option<DateTime<Utc>>>>,
+    /// shares of the rsa password entered by the administrators
+    pub shamir_shares: Arc<RwLock<ShamirShareCollector>>,
     // SharedSecret (context for creating uuids)
     pub shared_secret: Arc<RwLock<SharedSecretData>>,
     /// stores authenticated users
@@ -163,7 +424,9 @@ impl ApplicationConfiguration {
             rsa_password: Arc::new(RwLock::new(RsaPrivateKeyPassword {
                 rsa_private_key_password: None,
             })),
-            rsa_keys: Arc::new(RwLock::new(RsaKeys::new())),
+            master_key: Arc::new(RwLock::new(Box::new(RsaKeys::new()))),
+            master_key_loaded_at: Arc::new(RwLock::new(None)),
+            shamir_shares: Arc::new(RwLock::new(ShamirShareCollector::new())),
             shared_secret: Arc::new(RwLock::new(SharedSecretData::new())),
             shared_authenticated_users: Arc::new(RwLock::new(
                 SharedAuthenticatedUsersHashMap::new(config_file.admin_accounts),
@@ -194,24 +457,25 @@ impl ApplicationConfiguration {
         }
     }
 
-    /// Reads the RSA key files which are referenced in the configuration file
+    /// Reads the RSA or EC key files which are referenced in the configuration
+    /// file and the older keys of the optional key ring directory. It is also
+    /// used to reload the key ring after the primary key has been replaced.
     ///
     /// If the files are not to be found or cannot be read, the function will
-    /// return a boxed error.
+    /// return a boxed error and the keys that have been loaded before are kept.
     ///
     /// # Returns
     ///
     /// - `Result<(), Box<dyn Error>>`
-    pub fn load_rsa_keys(&self) -> Result<(), Box<dyn Error>> {
+    pub fn load_master_key(&self) -> Result<(), Box<dyn Error>> {
         if let Some(rsa_private_key_password) =
             &self.rsa_password.read().unwrap().rsa_private_key_password
         {
-            let mut rsa_write_lock = self.rsa_keys.write().unwrap();
-            rsa_write_lock.read_from_files(
-                &self.configuration_file.rsa_private_key_file,
-                &self.configuration_file.rsa_public_key_file,
-                rsa_private_key_password,
-            )
+            let master_key = self
+                .configuration_file
+                .read_master_key(rsa_private_key_password)?;
+            self.set_master_key(master_key);
+            Ok(())
         } else {
             const RSA_PASSWORD_NOT_SET: &str = "Password not set, inform system administrator";
             let boxed_error = Box::<dyn Error + Send + Sync>::from(RSA_PASSWORD_NOT_SET);
@@ -219,6 +483,80 @@ impl ApplicationConfiguration {
         }
     }
 
+    /// Replaces the master key, the time of the first load after
+    /// unsealing is kept when the key ring is reloaded.
+    fn set_master_key(&self, master_key: Box<dyn MasterKey>) {
+        *self.master_key.write().unwrap() = master_key;
+        self.master_key_loaded_at
+            .write()
+            .unwrap()
+            .get_or_insert_with(Utc::now);
+    }
+
+    /// Seals the server again: the password is cleared and the master key
+    /// is dropped, so that no secret can be revealed until the password is
+    /// entered again. Every session is removed, because the cookies are
+    /// encrypted with the master key.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Box<dyn Error>>`
+    pub fn seal(&self) -> Result<(), Box<dyn Error>> {
+        self.clear_rsa_password()?;
+        // the private key is cleared by openssl when it is freed
+        *self.master_key.write().unwrap() = Box::new(RsaKeys::new());
+        *self.master_key_loaded_at.write().unwrap() = None;
+        self.shamir_shares.write().unwrap().clear();
+        self.shared_authenticated_users
+            .write()
+            .unwrap()
+            .authenticated_users_hashmap
+            .clear();
+        Ok(())
+    }
+
+    /// Seals the server when the master key has been loaded
+    /// for more than `max_unsealed_seconds`, see `seal()`.
+    ///
+    /// # Arguments
+    ///
+    /// - `max_unsealed_seconds`: configured `auto_seal_seconds`
+    pub fn auto_seal(&self, max_unsealed_seconds: i64) {
+        let master_key_loaded_at = *self.master_key_loaded_at.read().unwrap();
+        if let Some(master_key_loaded_at) = master_key_loaded_at {
+            if master_key_loaded_at < Utc::now() - Duration::seconds(max_unsealed_seconds) {
+                info!("sealing server after {} seconds", max_unsealed_seconds);
+                if let Err(e) = self.seal() {
+                    warn!("cannot seal server: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Loads the keys at startup with the password from the file that is
+    /// returned by `ConfigurationFile::get_rsa_private_key_password_file()`,
+    /// so that no administrator has to enter the password.
+    ///
+    /// If the keys cannot be loaded, the password is cleared again.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<bool, Box<dyn Error>>`: `false` if there is no password file
+    pub fn load_master_key_with_password_file(&self) -> Result<bool, Box<dyn Error>> {
+        let rsa_private_key_password_file =
+            match self.configuration_file.get_rsa_private_key_password_file() {
+                Some(rsa_private_key_password_file) => rsa_private_key_password_file,
+                None => return Ok(false),
+            };
+        let rsa_password = RsaPrivateKeyPassword::read_from_file(rsa_private_key_password_file)?;
+        *self.rsa_password.write().unwrap() = rsa_password;
+        if let Err(e) = self.load_master_key() {
+            self.clear_rsa_password()?;
+            return Err(e);
+        }
+        Ok(true)
+    }
+
     /// Clears the passsword for the RSA key files which are referenced in the configuration file
     ///
     /// If the files are not to be found or cannot be read, the function will
@@ -1,5 +1,5 @@
 use crate::base64_trait::{Base64StringConversions, Base64VecU8Conversions};
-use crate::rsa_functions::RsaKeys;
+use crate::master_key_trait::MasterKey;
 use actix_web::{
     cookie::time::Duration, cookie::time::OffsetDateTime, cookie::Cookie, http, http::StatusCode,
     HttpResponse,
@@ -15,7 +15,7 @@ pub const COOKIE_PATH: &str = "/";
 ///
 /// - `cookie_value`:    containing the value that should be placed inside the cookie
 /// - `max_age_seconds`: lifetime of the cookie in seconds
-/// - `rsa`:             rsa keys to encrypt the cookie
+/// - `rsa`:             rsa or ec keys to encrypt the cookie
 ///
 /// # Returns
 ///
@@ -24,7 +24,7 @@ pub fn build_new_encrypted_authentication_cookie(
     cookie_value: &str,
     max_age_seconds: i64,
     domain: &str,
-    rsa: &RsaKeys,
+    rsa: &dyn MasterKey,
 ) -> Cookie<'static> {
     let encrypted_cookie_value = match rsa.encrypt_str(cookie_value) {
         Err(_) => String::from("invalid_rsa_cookie"),
@@ -86,7 +86,7 @@ pub fn build_new_base64_authentication_cookie(
 ///
 /// - `cookie_value`:    containing the value that should be placed inside the cookie
 /// - `max_age_seconds`: lifetime of the cookie in seconds
-/// - `rsa`:             rsa keys to encrypt the cookie
+/// - `rsa`:             rsa or ec keys to encrypt the cookie
 ///
 /// # Returns
 ///
@@ -95,9 +95,9 @@ pub fn build_new_authentication_cookie(
     cookie_value: &str,
     max_age_seconds: i64,
     domain: &str,
-    rsa: &RsaKeys,
+    rsa: &dyn MasterKey,
 ) -> Cookie<'static> {
-    let new_cookie = match rsa.rsa_private_key {
+    let new_cookie = match rsa.key_id() {
         Some(_) => {
             build_new_encrypted_authentication_cookie(cookie_value, max_age_seconds, domain, rsa)
         }
@@ -154,16 +154,16 @@ pub fn build_redirect_to_resource_url_response(
 /// # Arguments
 ///
 /// - `transmitted_cookie`: cookie value that is either rsa encrypted or base64 encoded
-/// - `rsa`:                rsa keys to decrypt the cookie
+/// - `rsa`:                rsa or ec keys to decrypt the cookie
 ///
 /// # Returns
 ///
 /// - plain cookie value as String
-pub fn get_plain_cookie_string(transmitted_cookie: &str, rsa: &RsaKeys) -> String {
+pub fn get_plain_cookie_string(transmitted_cookie: &str, rsa: &dyn MasterKey) -> String {
     // when the rsa key pair already has been loaded,
     // the cookie value is encrypted with the rsa public
     // key otherwise its simply base64 encoded.
-    match rsa.rsa_private_key {
+    match rsa.key_id() {
         Some(_) => rsa
             .decrypt_str(transmitted_cookie)
             .unwrap_or_else(|_| -> String { "invalid_rsa_cookie_value".to_string() }),
@@ -0,0 +1,353 @@
+use crate::aes_functions::{decrypt_bytes_with_data_key, encrypt_bytes_with_data_key};
+use crate::base64_trait::{Base64StringConversions, Base64VecU8Conversions};
+use crate::master_key_trait::{key_id_from_public_key_der, MasterKey};
+use crate::unsecure_string::SecureStringToUnsecureString;
+use log::{debug, info, warn};
+use openssl::bn::BigNumContext;
+use openssl::derive::Deriver;
+use openssl::ec::{EcKey, EcPoint, PointConversionForm};
+use openssl::md::Md;
+use openssl::pkey::{HasPublic, Id, PKey, Private, Public};
+use openssl::pkey_ctx::PkeyCtx;
+use secstr::SecStr;
+use std::error::Error;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// length of the AES key derived from the shared secret
+const DERIVED_KEY_LENGTH: usize = 32;
+/// HKDF info that binds the derived key to its purpose
+const HKDF_INFO: &[u8] = b"lmtyas ecies aes-256-gcm";
+/// file extension of the private keys in the key ring directory
+const KEY_RING_FILE_EXTENSION: &str = "key";
+
+/// Holds the elliptic curve private and public key for
+/// encryption and decryption (ECIES). NIST curves like
+/// P-256 and X25519 keys are supported.
+pub struct EcKeys {
+    // Option<> to construct empty values
+    pub ec_private_key: Option<PKey<Private>>,
+    // Option<> to construct empty values
+    pub ec_public_key: Option<PKey<Public>>,
+    /// id of the primary key pair, see `key_id_from_public_key_der()`
+    pub ec_key_id: Option<String>,
+    /// older private keys that are only used for decryption,
+    /// together with their key ids
+    pub previous_ec_private_keys: Vec<(String, PKey<Private>)>,
+}
+
+impl Default for EcKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EcKeys {
+    /// Constructs data struct with
+    /// None<> to be able to put it into a
+    /// arc<RwLock<Box<dyn MasterKey>>>
+    ///
+    /// # Returns
+    ///
+    /// - EcKeys{ec_private_key: None, ec_public_key: None, ...}
+    pub fn new() -> EcKeys {
+        EcKeys {
+            ec_private_key: None,
+            ec_public_key: None,
+            ec_key_id: None,
+            previous_ec_private_keys: Vec::new(),
+        }
+    }
+
+    /// Loads elliptic curve private and public key from the given paths.
+    /// To load the private key, the passphrase is needed.
+    ///
+    /// # Arguments
+    ///
+    /// - ec_private_key_path: Path
+    /// - ec_public_key_path:  Path
+    /// - secure_passphrase:   SecStr
+    ///
+    /// # Returns
+    ///
+    /// - Result<(), Box<dyn Error>>
+    pub fn read_from_files<P: AsRef<Path>>(
+        &mut self,
+        ec_private_key_path: P,
+        ec_public_key_path: P,
+        secure_passphrase: &SecStr,
+    ) -> Result<(), Box<dyn Error>> {
+        let ec_private_key_file = std::fs::read_to_string(ec_private_key_path)?;
+        let ec_private_key = read_private_key(&ec_private_key_file, secure_passphrase)?;
+        let ec_public_key_file = std::fs::read_to_string(ec_public_key_path)?;
+        let ec_public_key = PKey::public_key_from_pem(ec_public_key_file.as_bytes())?;
+        let public_key_id = ec_key_id(&ec_public_key)?;
+        if public_key_id != ec_key_id(&ec_private_key)? {
+            warn!("ec private and public key do not belong together");
+            return Err("EC private and public key do not match".into());
+        }
+        debug!("ec key id = {}", &public_key_id);
+        self.ec_key_id = Some(public_key_id);
+        self.ec_private_key = Some(ec_private_key);
+        self.ec_public_key = Some(ec_public_key);
+        Ok(())
+    }
+
+    /// Loads the older elliptic curve private keys of the key ring,
+    /// every file ending with `.key` in the given directory. They
+    /// must be protected by the same passphrase as the primary key.
+    ///
+    /// # Arguments
+    ///
+    /// - key_ring_directory: Path
+    /// - secure_passphrase:  SecStr
+    ///
+    /// # Returns
+    ///
+    /// - Result<(), Box<dyn Error>>
+    pub fn read_key_ring_from_directory<P: AsRef<Path>>(
+        &mut self,
+        key_ring_directory: P,
+        secure_passphrase: &SecStr,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut previous_ec_private_keys = Vec::new();
+        for dir_entry in std::fs::read_dir(key_ring_directory)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some(KEY_RING_FILE_EXTENSION) {
+                continue;
+            }
+            let ec_private_key_file = std::fs::read_to_string(&path)?;
+            let ec_private_key = match read_private_key(&ec_private_key_file, secure_passphrase) {
+                Ok(k) => k,
+                Err(e) => {
+                    warn!("cannot load ec private key {}: {}", path.display(), e);
+                    return Err(format!("Cannot load ec key {}!", path.display()).into());
+                }
+            };
+            let private_key_id = ec_key_id(&ec_private_key)?;
+            if Some(&private_key_id) == self.ec_key_id.as_ref() {
+                continue;
+            }
+            info!(
+                "ec key {} with id {} is used for decryption",
+                path.display(),
+                &private_key_id
+            );
+            previous_ec_private_keys.push((private_key_id, ec_private_key));
+        }
+        self.previous_ec_private_keys = previous_ec_private_keys;
+        Ok(())
+    }
+
+    /// Returns the private keys that may decrypt data, the primary
+    /// key first. If a key id is given, only the key with this
+    /// id is returned.
+    fn decryption_keys(&self, key_id: Option<&str>) -> Vec<&PKey<Private>> {
+        let primary_key = self.ec_key_id.as_deref().zip(self.ec_private_key.as_ref());
+        primary_key
+            .into_iter()
+            .chain(
+                self.previous_ec_private_keys
+                    .iter()
+                    .map(|(id, key)| (id.as_str(), key)),
+            )
+            .filter(|(id, _)| key_id.is_none() || key_id == Some(id))
+            .map(|(_, key)| key)
+            .collect()
+    }
+}
+
+impl MasterKey for EcKeys {
+    fn key_id(&self) -> Option<String> {
+        self.ec_key_id.clone()
+    }
+
+    /// Encrypts with an ephemeral key: the shared secret of the
+    /// ephemeral private key and the stored public key is used to
+    /// derive an AES key (HKDF-SHA256). The ephemeral public key is
+    /// prepended to the AES-GCM encrypted data.
+    fn encrypt_str(&self, plaintext_data: &str) -> Result<String, Box<dyn Error>> {
+        let public_key = match &self.ec_public_key {
+            Some(public_key) => public_key,
+            None => return Err("EC public key is not set!".into()),
+        };
+        let ephemeral_key = generate_ephemeral_key(public_key)?;
+        let ephemeral_public_key = public_key_bytes(&ephemeral_key)?;
+        let mut aes_key = derive_aes_key(&ephemeral_key, public_key, &ephemeral_public_key)?;
+        let encrypted_data = encrypt_bytes_with_data_key(&aes_key, plaintext_data.as_bytes());
+        aes_key.zeroize();
+        let mut envelope = ephemeral_public_key;
+        envelope.extend_from_slice(&encrypted_data?);
+        Ok(envelope.to_base64_encoded())
+    }
+
+    fn decrypt_str_with_key_id(
+        &self,
+        encrypted_data: &str,
+        key_id: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        if self.ec_private_key.is_none() {
+            return Err("EC private key is not set!".into());
+        }
+        let envelope = Vec::from_base64_encoded(encrypted_data)?;
+        let decrypted_data =
+            self.decryption_keys(key_id).into_iter().find_map(
+                |private_key| match decrypt_envelope(private_key, &envelope) {
+                    Ok(decrypted_data) => Some(decrypted_data),
+                    Err(e) => {
+                        debug!("Could not ec decrypt given value: {}", &e);
+                        None
+                    }
+                },
+            );
+        match decrypted_data {
+            None => {
+                info!("Could not ec decrypt given value with any key");
+                Err("Could not ec decrypt given value".into())
+            }
+            Some(decrypted_data) => Ok(String::from_utf8(decrypted_data)?),
+        }
+    }
+}
+
+/// Builds the id of an elliptic curve key from the hash of its public key.
+///
+/// # Arguments
+///
+/// - `ec_key`: public or private key
+///
+/// # Returns
+///
+/// - `Result<String, Box<dyn Error>>`: hex encoded key id
+pub fn ec_key_id<T: HasPublic>(ec_key: &PKey<T>) -> Result<String, Box<dyn Error>> {
+    Ok(key_id_from_public_key_der(&ec_key.public_key_to_der()?))
+}
+
+/// Decrypts a PEM encoded private key with the passphrase and
+/// makes sure that it is an elliptic curve key.
+fn read_private_key(
+    ec_private_key_pem: &str,
+    secure_passphrase: &SecStr,
+) -> Result<PKey<Private>, Box<dyn Error>> {
+    let mut unsecure_passphrase = secure_passphrase.to_unsecure_string();
+    let ec_private_key = PKey::private_key_from_pem_passphrase(
+        ec_private_key_pem.as_bytes(),
+        unsecure_passphrase.as_bytes(),
+    );
+    unsecure_passphrase.zeroize();
+    let ec_private_key = match ec_private_key {
+        Ok(k) => k,
+        Err(e) => {
+            warn!("cannot load ec private key: {}", e);
+            return Err("Cannot load ec keys!".into());
+        }
+    };
+    match ec_private_key.id() {
+        Id::EC | Id::X25519 => Ok(ec_private_key),
+        _ => Err("private key is not an EC or X25519 key".into()),
+    }
+}
+
+/// Creates a new random key of the same curve as the given key.
+fn generate_ephemeral_key(public_key: &PKey<Public>) -> Result<PKey<Private>, Box<dyn Error>> {
+    match public_key.id() {
+        Id::X25519 => Ok(PKey::generate_x25519()?),
+        _ => {
+            let ec_key = EcKey::generate(public_key.ec_key()?.group())?;
+            Ok(PKey::from_ec_key(ec_key)?)
+        }
+    }
+}
+
+/// Encodes a public key as raw bytes, an uncompressed point for
+/// NIST curves and 32 bytes for X25519.
+fn public_key_bytes<T: HasPublic>(key: &PKey<T>) -> Result<Vec<u8>, Box<dyn Error>> {
+    match key.id() {
+        Id::X25519 => Ok(key.raw_public_key()?),
+        _ => {
+            let ec_key = key.ec_key()?;
+            let mut bn_context = BigNumContext::new()?;
+            Ok(ec_key.public_key().to_bytes(
+                ec_key.group(),
+                PointConversionForm::UNCOMPRESSED,
+                &mut bn_context,
+            )?)
+        }
+    }
+}
+
+/// Length of the raw ephemeral public key that is
+/// prepended to the encrypted data.
+fn public_key_bytes_length(private_key: &PKey<Private>) -> Result<usize, Box<dyn Error>> {
+    match private_key.id() {
+        Id::X25519 => Ok(32),
+        _ => {
+            let field_length = (private_key.ec_key()?.group().degree() as usize).div_ceil(8);
+            Ok(1 + 2 * field_length)
+        }
+    }
+}
+
+/// Decodes the raw ephemeral public key for the curve of the given key.
+fn public_key_from_bytes(
+    private_key: &PKey<Private>,
+    public_key_bytes: &[u8],
+) -> Result<PKey<Public>, Box<dyn Error>> {
+    match private_key.id() {
+        Id::X25519 => Ok(PKey::public_key_from_raw_bytes(
+            public_key_bytes,
+            Id::X25519,
+        )?),
+        _ => {
+            let ec_key = private_key.ec_key()?;
+            let mut bn_context = BigNumContext::new()?;
+            let point = EcPoint::from_bytes(ec_key.group(), public_key_bytes, &mut bn_context)?;
+            Ok(PKey::from_ec_key(EcKey::from_public_key(
+                ec_key.group(),
+                &point,
+            )?)?)
+        }
+    }
+}
+
+/// Derives the AES key from the shared secret of the two keys, the
+/// ephemeral public key is used as salt.
+fn derive_aes_key<T: HasPublic>(
+    private_key: &PKey<Private>,
+    peer_key: &PKey<T>,
+    ephemeral_public_key: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut deriver = Deriver::new(private_key)?;
+    deriver.set_peer(peer_key)?;
+    let mut shared_secret = deriver.derive_to_vec()?;
+    let mut hkdf = PkeyCtx::new_id(Id::HKDF)?;
+    let derived = hkdf
+        .derive_init()
+        .and_then(|_| hkdf.set_hkdf_md(Md::sha256()))
+        .and_then(|_| hkdf.set_hkdf_key(&shared_secret))
+        .and_then(|_| hkdf.set_hkdf_salt(ephemeral_public_key))
+        .and_then(|_| hkdf.add_hkdf_info(HKDF_INFO));
+    shared_secret.zeroize();
+    derived?;
+    let mut aes_key = vec![0; DERIVED_KEY_LENGTH];
+    hkdf.derive(Some(&mut aes_key))?;
+    Ok(aes_key)
+}
+
+/// Decrypts the ephemeral public key and the AES-GCM encrypted data
+/// with one private key.
+fn decrypt_envelope(
+    private_key: &PKey<Private>,
+    envelope: &[u8],
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let public_key_length = public_key_bytes_length(private_key)?;
+    if envelope.len() < public_key_length {
+        return Err("ec encrypted data is too short".into());
+    }
+    let (ephemeral_public_key, encrypted_data) = envelope.split_at(public_key_length);
+    let peer_key = public_key_from_bytes(private_key, ephemeral_public_key)?;
+    let mut aes_key = derive_aes_key(private_key, &peer_key, ephemeral_public_key)?;
+    let decrypted_data = decrypt_bytes_with_data_key(&aes_key, encrypted_data);
+    aes_key.zeroize();
+    decrypted_data
+}
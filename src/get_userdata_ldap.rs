@@ -1,5 +1,5 @@
 use crate::configuration::ApplicationConfiguration;
-use crate::get_userdata_trait::GetUserData;
+use crate::get_userdata_trait::{GetUserData, ReceiverDetails};
 pub use crate::ldap_common::{LdapCommonConfiguration, LdapSearchResult};
 use actix_web::web;
 use async_trait::async_trait;
@@ -7,18 +7,18 @@ use log::warn;
 
 pub struct GetUserDataLdapBackend;
 
-/// This trait is used to force one implementation of a get_display_name()
+/// This trait is used to force one implementation of a get_receiver_details()
 /// function. The implementation may change depending on the authentication
 /// method used.
 #[async_trait]
 impl GetUserData for GetUserDataLdapBackend {
     /// This function is called when a secret is transmitted
-    /// to get the display name of the receiver. At this
-    /// point we only know of the email address
-    async fn get_receiver_display_name(
+    /// to get the display name and preferred language of the
+    /// receiver. At this point we only know of the email address
+    async fn get_receiver_details(
         mail: &str,
         application_configuration: &web::Data<ApplicationConfiguration>,
-    ) -> Result<String, String> {
+    ) -> Result<ReceiverDetails, String> {
         // 2. check if user exists
         let ldap_search_result = match &application_configuration
             .configuration_file
@@ -44,19 +44,62 @@ impl GetUserData for GetUserDataLdapBackend {
         };
         // dirty hack to build a json string from the ldap query result,
         // so it can be serialized.
-        let ldap_result = match serde_json::from_str(&ldap_search_result.replace(['[', ']'], ""))
-            as Result<LdapSearchResult, _>
+        let ldap_search_result = ldap_search_result.replace(['[', ']'], "");
+        let ldap_result =
+            match serde_json::from_str(&ldap_search_result) as Result<LdapSearchResult, _> {
+                Err(e) => {
+                    let error_message = format!(
+                        "can not serde_json::from_str({}): {}",
+                        &ldap_search_result, &e
+                    );
+                    return Err(error_message);
+                }
+                Ok(r) => r,
+            };
+        let display_name = format!("{} {}", &ldap_result.first_name, &ldap_result.last_name);
+        // the language attribute is configurable, so it cannot be part of `LdapSearchResult`
+        let language = match &application_configuration
+            .configuration_file
+            .ldap_common_configuration
+            .language_attribute
         {
-            Err(e) => {
-                let error_message = format!(
-                    "can not serde_json::from_str({}): {}",
-                    &ldap_search_result, &e
-                );
-                return Err(error_message);
+            Some(language_attribute) => {
+                serde_json::from_str::<serde_json::Value>(&ldap_search_result)
+                    .ok()
+                    .and_then(|value| {
+                        value
+                            .get(language_attribute)
+                            .and_then(|language| language.as_str())
+                            .map(String::from)
+                    })
             }
-            Ok(r) => r,
+            None => None,
         };
-        let display_name = format!("{} {}", &ldap_result.first_name, &ldap_result.last_name);
-        Ok(display_name)
+        Ok(ReceiverDetails {
+            display_name,
+            language,
+        })
+    }
+
+    /// This function is called when a secret is transmitted
+    /// to a group, the members are searched with the `group_filter`
+    async fn get_group_members(
+        group: &str,
+        application_configuration: &web::Data<ApplicationConfiguration>,
+    ) -> Result<Vec<String>, String> {
+        match application_configuration
+            .configuration_file
+            .ldap_common_configuration
+            .ldap_search_group_members(group)
+            .await
+        {
+            Err(e) => {
+                let error_message =
+                    format!("error while looking up members of group {}: {}", &group, &e);
+                warn!("{}", &error_message);
+                Err(error_message)
+            }
+            Ok(members) => Ok(members),
+        }
     }
 }
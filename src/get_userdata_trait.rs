@@ -4,14 +4,24 @@ use crate::configuration::ApplicationConfiguration;
 use actix_web::web;
 use async_trait::async_trait;
 
-/// This trait is used to force one implementation of a get_display_name()
+/// Details about the receiver of a secret
+#[derive(Clone, Debug, Default)]
+pub struct ReceiverDetails {
+    /// display name of the receiver
+    pub display_name: String,
+    /// optional preferred language of the receiver, e.g. `de`
+    pub language: Option<String>,
+}
+
+/// This trait is used to force one implementation of a get_receiver_details()
 /// function. The implementation may change depending on the authentication
 /// method used.
 #[async_trait]
 pub trait GetUserData {
     /// This function is called when a secret is transmitted
-    /// to get the display name of the receiver. At this
-    /// point we only know of the email address.
+    /// to get the display name and the preferred language
+    /// of the receiver. At this point we only know of the
+    /// email address.
     ///
     /// Arguments
     ///
@@ -20,20 +30,46 @@ pub trait GetUserData {
     ///
     /// # Returns
     ///
-    /// - `Result<String, Box<dyn Error>>`
-    async fn get_receiver_display_name(
+    /// - `Result<ReceiverDetails, String>`
+    async fn get_receiver_details(
         mail: &str,
         application_configuration: &web::Data<ApplicationConfiguration>,
-    ) -> Result<String, String>;
+    ) -> Result<ReceiverDetails, String>;
+
+    /// This function is called when a secret is transmitted
+    /// to a group to get the email addresses of all members.
+    ///
+    /// Arguments
+    ///
+    /// - `group`:                     name of the group
+    /// - `application_configuration`: application configuration
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Vec<String>, String>`
+    async fn get_group_members(
+        group: &str,
+        application_configuration: &web::Data<ApplicationConfiguration>,
+    ) -> Result<Vec<String>, String>;
 }
 
 pub struct NoUserDataBackend;
 
 #[async_trait]
 impl GetUserData for NoUserDataBackend {
-    async fn get_receiver_display_name(_mail: &str,
+    async fn get_receiver_details(_mail: &str,
+        _application_configuration: &web::Data<ApplicationConfiguration>,
+    ) -> Result<ReceiverDetails, String>{
+        Ok(ReceiverDetails::default())
+    }
+
+    async fn get_group_members(
+        group: &str,
         _application_configuration: &web::Data<ApplicationConfiguration>,
-    ) -> Result<String, String>{
-        Ok("".to_string())
+    ) -> Result<Vec<String>, String> {
+        Err(format!(
+            "cannot look up members of group {} without user data backend",
+            group
+        ))
     }
 }
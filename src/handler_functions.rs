@@ -1,9 +1,13 @@
 //#[macro_use]
 extern crate env_logger;
-use crate::aes_functions::{DecryptAes, EncryptAes};
+use crate::aes_functions::{
+    create_data_key, create_passphrase_salt, decrypt_with_data_key, encrypt_with_data_key,
+    unwrap_key_with_passphrase, wrap_key_with_passphrase, EncryptAes,
+};
 use crate::authenticated_user::{AuthenticatedAdministrator, AuthenticatedUser};
+use crate::authentication_api_token::ApiToken;
 use crate::authentication_functions::update_authenticated_user_cookie_lifetime;
-use crate::base64_trait::Base64VecU8Conversions;
+use crate::base64_trait::{Base64StringConversions, Base64VecU8Conversions};
 use crate::configuration::ApplicationConfiguration;
 #[cfg(feature = "get-userdata-ldap")]
 use crate::get_userdata_ldap::GetUserDataLdapBackend;
@@ -11,16 +15,26 @@ use crate::get_userdata_trait::GetUserData;
 #[cfg(feature = "no-userdata-backend")]
 use crate::get_userdata_trait::NoUserDataBackend;
 use crate::http_traits::CustomHttpResponse;
-#[cfg(feature = "mail-noauth-notls")]
-pub use crate::mail_noauth_notls::SendEMail;
-use crate::secret_functions::Secret;
+pub use crate::mail_configuration::SendEMail;
+use crate::mail_configuration::{MailTemplateConfiguration, SendEMailConfiguration};
+use crate::master_key_trait::MasterKey;
+use crate::secret_functions::{
+    create_one_time_code, hash_one_time_code, is_expired, mark_receiver_revealed,
+    record_failed_attempt, Attachment, Receiver, Secret, SECRET_FORMAT_VERSION,
+};
+use crate::shamir_functions::ShamirShare;
 use actix_files::NamedFile;
 use actix_web::web::Bytes;
-use actix_web::{http::header, http::StatusCode, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{
+    http::header, http::StatusCode, web, HttpMessage, HttpRequest, HttpResponse, Responder,
+};
+use chrono::{Duration, Utc};
 use log::{debug, info, warn};
 use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
 use secstr::SecStr;
 use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
 use std::fs::read_to_string;
 use std::fs::remove_file;
 use std::path::Path;
@@ -38,6 +52,21 @@ const FRAGMENT: &AsciiSet = &CONTROLS.add(b'/').add(b'=');
 const MAX_FORM_BYTES_LEN: usize = 1024;
 /// max length of form fields
 const MAX_FORM_INPUT_LEN: usize = 128;
+/// max length of a (base64 decoded) secret, e.g. a ssh private key
+const MAX_SECRET_LEN: usize = 8192;
+/// max length of form data with a base64 encoded secret
+const MAX_SECRET_FORM_BYTES_LEN: usize = MAX_FORM_BYTES_LEN + MAX_SECRET_LEN.div_ceil(3) * 4;
+/// max length of an uploaded csv file with secrets
+const MAX_BULK_CSV_BYTES_LEN: usize = 65536;
+/// max number of receivers of one secret, including group members
+const MAX_RECEIVERS: usize = 25;
+/// receivers starting with this prefix are groups, e.g. `cn=netops`
+const GROUP_PREFIX: &str = "cn=";
+/// wrong one-time codes or passphrases a receiver may enter
+const MAX_FAILED_ATTEMPTS: u8 = 3;
+/// the same error for every link that cannot be decrypted or parsed,
+/// otherwise the response would tell if the padding was valid
+const INVALID_LINK_ERROR: &str = "ERROR: invalid link!";
 
 /// Redirect browser to our index page.
 pub async fn redirect_to_index(
@@ -109,6 +138,58 @@ pub async fn get_imprint_link(
     )
 }
 
+/// Returns the lifetimes in hours a sender can choose from.
+pub async fn get_secret_valid_for_hours(
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> impl Responder {
+    let valid_for_hours = ValidForHours {
+        valid_for_hours: application_configuration
+            .configuration_file
+            .secret_valid_for_hours
+            .clone(),
+    };
+    match serde_json::to_string(&valid_for_hours) {
+        Err(e) => HttpResponse::err_text_response(format!(
+            "ERROR: cannot serialize secret lifetimes: {}",
+            &e
+        )),
+        Ok(json) => HttpResponse::ok_json_response(json),
+    }
+}
+
+/// Returns the max size of a file attached to a secret,
+/// `null` if attachments are not allowed.
+pub async fn get_max_attachment_bytes(
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> impl Responder {
+    let max_attachment_bytes = MaxAttachmentBytes {
+        max_attachment_bytes: application_configuration
+            .configuration_file
+            .max_attachment_bytes,
+    };
+    match serde_json::to_string(&max_attachment_bytes) {
+        Err(e) => HttpResponse::err_text_response(format!(
+            "ERROR: cannot serialize max attachment size: {}",
+            &e
+        )),
+        Ok(json) => HttpResponse::ok_json_response(json),
+    }
+}
+
+/// Max size of a file attached to a secret
+#[derive(Serialize)]
+struct MaxAttachmentBytes {
+    #[serde(rename = "MaxAttachmentBytes")]
+    max_attachment_bytes: Option<usize>,
+}
+
+/// Lifetimes of a secret that can be chosen by the sender
+#[derive(Serialize)]
+struct ValidForHours {
+    #[serde(rename = "ValidForHours")]
+    valid_for_hours: Vec<i64>,
+}
+
 /// Return the custom colors.css file if it exists.
 pub async fn get_colors_css() -> impl Responder {
     let path_local = Path::new("local/css/colors.css");
@@ -185,6 +266,15 @@ pub async fn set_password_for_rsa_rivate_key(
     base64_encoded_password: web::Path<String>,
     application_configuration: web::Data<ApplicationConfiguration>,
 ) -> HttpResponse {
+    if application_configuration
+        .configuration_file
+        .shamir_configuration
+        .is_some()
+    {
+        return HttpResponse::err_text_response(
+            "ERROR: the password must be entered as shares by the administrators",
+        );
+    }
     if base64_encoded_password.len() > MAX_FORM_BYTES_LEN {
         warn!("form data exceeds {} bytes!", MAX_FORM_BYTES_LEN);
         return HttpResponse::err_text_response(format!(
@@ -204,7 +294,7 @@ pub async fn set_password_for_rsa_rivate_key(
         }
     };
 
-    let mut decoded_password = match String::from_utf8(base64_decoded_password) {
+    let decoded_password = match String::from_utf8(base64_decoded_password) {
         Ok(password) => password.trim_matches(char::from(0)).to_string(),
         Err(e) => {
             warn!("could not base64 decode password: {}", &e);
@@ -218,18 +308,35 @@ pub async fn set_password_for_rsa_rivate_key(
             MAX_FORM_INPUT_LEN
         ));
     }
+    load_master_key_with_password(&application_configuration, SecStr::from(decoded_password))
+}
+
+/// Stores the password for the rsa private key and loads the keys.
+/// The password is thrown away if the keys cannot be loaded.
+///
+/// # Arguments
+///
+/// - `application_configuration`: application configuration
+/// - `rsa_private_key_password`:  the password
+///
+/// # Returns
+///
+/// - `HttpResponse`
+fn load_master_key_with_password(
+    application_configuration: &ApplicationConfiguration,
+    rsa_private_key_password: SecStr,
+) -> HttpResponse {
     if let Ok(mut rsa_password_write_lock) = application_configuration.rsa_password.write() {
-        rsa_password_write_lock.rsa_private_key_password = Some(SecStr::from(decoded_password));
+        rsa_password_write_lock.rsa_private_key_password = Some(rsa_private_key_password);
     } else {
-        decoded_password.zeroize();
         return HttpResponse::err_text_response("ERROR: can not acquire a lock on system data!");
     }
     // the lock must be removed at this point because
-    // application_configuration.load_rsa_keys()
+    // application_configuration.load_master_key()
     // will quire a lock on itself. If we didn't remove the
     // lock here, we will never return...
     info!("password has been set, loading rsa keys");
-    match application_configuration.load_rsa_keys() {
+    match application_configuration.load_master_key() {
         Err(e) => {
             // loading the rsa keys did not work, throw the password away
             let _result = application_configuration.clear_rsa_password();
@@ -245,258 +352,1605 @@ pub async fn set_password_for_rsa_rivate_key(
     }
 }
 
-/// Stores a secret and its meta date as encrypted file on disk.
+/// Collects a share of the password for the rsa private key when
+/// `shamir_configuration` is set. The keys are loaded as soon as
+/// shares of `threshold` different administrators have been entered.
 ///
 /// # Arguments
 ///
-/// - `bytes`:                     the POSTed bytes from the form
+/// - `admin`:                     the administrator who enters the share
+/// - `share`:                     tail of the url = share
 /// - `application_configuration`: application configuration
 ///
 /// # Returns
 ///
 /// - `HttpResponse`
-pub async fn store_secret(
-    bytes: Bytes,
+pub async fn add_rsa_password_share(
+    admin: AuthenticatedAdministrator,
+    share: web::Path<String>,
     application_configuration: web::Data<ApplicationConfiguration>,
 ) -> HttpResponse {
-    debug!("store_secret()");
-    let bytes_vec = bytes.to_vec();
-    let form_data = match String::from_utf8(bytes_vec) {
-        Ok(form_data) => form_data,
-        Err(_) => {
-            return HttpResponse::err_text_response("ERROR: could not parse form data");
+    let shamir_configuration = match &application_configuration
+        .configuration_file
+        .shamir_configuration
+    {
+        Some(shamir_configuration) => shamir_configuration,
+        None => {
+            return HttpResponse::err_text_response("ERROR: password shares are not configured!")
         }
     };
-    debug!("{}", form_data);
-    if form_data.len() > MAX_FORM_BYTES_LEN {
+    if share.len() > MAX_FORM_BYTES_LEN {
         warn!("form data exceeds {} bytes!", MAX_FORM_BYTES_LEN);
         return HttpResponse::err_text_response(format!(
             "ERROR: more than {} bytes of data sent",
             &MAX_FORM_BYTES_LEN
         ));
     }
-    let mut parsed_form_data = match serde_json::from_str(&form_data) as Result<Secret, _> {
-        Ok(parsed_form_data) => parsed_form_data,
+    let share = match ShamirShare::parse(share.as_str()) {
+        Ok(share) => share,
+        Err(e) => {
+            warn!("cannot parse rsa password share: {}", &e);
+            return HttpResponse::err_text_response("ERROR: invalid share");
+        }
+    };
+    let add_share_result = match application_configuration.shamir_shares.write() {
+        Ok(mut shamir_shares_write_lock) => {
+            let add_share_result =
+                shamir_shares_write_lock.add_share(&admin.0.user_name, share, shamir_configuration);
+            (
+                add_share_result,
+                shamir_shares_write_lock.collected_shares(),
+            )
+        }
         Err(_) => {
-            return HttpResponse::err_text_response("ERROR: could not parse json form data");
+            return HttpResponse::err_text_response("ERROR: can not acquire a lock on system data!")
         }
     };
-    debug!("parsed_form_data={:?}", &parsed_form_data);
-    if parsed_form_data.from_email.len() > MAX_FORM_INPUT_LEN {
-        return HttpResponse::err_text_response(format!(
-            "ERROR: from email > {} chars",
-            MAX_FORM_INPUT_LEN
-        ));
-    }
-    if parsed_form_data.to_email.len() > MAX_FORM_INPUT_LEN {
-        return HttpResponse::err_text_response(format!(
-            "ERROR: to email > {} chars",
-            MAX_FORM_INPUT_LEN
-        ));
-    }
-    if parsed_form_data.context.len() > MAX_FORM_INPUT_LEN {
-        return HttpResponse::err_text_response(format!(
-            "ERROR: context > {} chars",
-            MAX_FORM_INPUT_LEN
-        ));
-    }
-    let secret_length = get_base64_encoded_secret_len(&parsed_form_data.secret);
-    if secret_length > MAX_FORM_INPUT_LEN {
-        return HttpResponse::err_text_response(format!(
-            "ERROR: secret > {} bytes!",
-            MAX_FORM_INPUT_LEN
-        ));
-    }
-    let display_name = match <UserDataImpl as GetUserData>::get_receiver_display_name(
-        &parsed_form_data.to_email,
-        &application_configuration,
-    )
-    .await
-    {
-        Ok(display_name) => display_name,
-        Err(e) => {
+    match add_share_result {
+        (Err(e), _) => {
+            warn!("share of {} was rejected: {}", &admin.0.user_name, &e);
+            HttpResponse::err_text_response(format!("ERROR: {}", e))
+        }
+        (Ok(None), collected_shares) => {
             info!(
-                "cannot find mail address {}, error: {}",
-                &parsed_form_data.to_email, &e
+                "{} entered share, {} of {} shares collected",
+                &admin.0.user_name, collected_shares, shamir_configuration.threshold
             );
-            return HttpResponse::err_text_response(format!(
-                "ERROR: cannot find mail address {}",
-                &parsed_form_data.to_email
-            ));
+            HttpResponse::ok_json_response(format!(
+                "{{\"sharesCollected\": {}, \"sharesRequired\": {}}}",
+                collected_shares, shamir_configuration.threshold
+            ))
         }
-    };
-    parsed_form_data.to_display_name = display_name;
-    // aes encrypt the secret before rsa encryption
-    let aes_encryption_result = match parsed_form_data.secret.to_aes_enrypted_b64() {
-        Ok(aes_encryption_result) => aes_encryption_result,
-        Err(e) => {
-            return HttpResponse::err_text_response(format!("ERROR: {}", &e));
+        (Ok(Some(rsa_private_key_password)), _) => {
+            info!("{} entered the last share", &admin.0.user_name);
+            load_master_key_with_password(&application_configuration, rsa_private_key_password)
         }
-    };
-    // store aes encrypted secret instead of plaintext secret
-    parsed_form_data.secret = aes_encryption_result.encrypted_data.clone();
-    // rsa encrypt all data
-    let encrypted_form_data =
-        match parsed_form_data.to_encrypted(&application_configuration.rsa_keys.read().unwrap()) {
-            Ok(encrypted_form_data) => encrypted_form_data,
-            Err(e) => {
-                return HttpResponse::err_text_response(format!("ERROR: {}", &e));
-            }
-        };
-
-    // write data to disk
-    let uuid = application_configuration
-        .shared_secret
-        .write()
-        .unwrap()
-        .create_uuid();
+    }
+}
 
-    let path = Path::new(
-        &application_configuration
-            .configuration_file
-            .secret_directory,
-    )
-    .join(uuid.to_string());
-    info!("writing secret to file {}", &path.display());
-    if let Err(e) = encrypted_form_data.write_to_disk(&path).await {
-        warn!("{}", &e);
-        return HttpResponse::err_text_response(format!(
-            "ERROR: could not write secret {} to disk!",
-            &path.display()
-        ));
+/// Returns how many shares of the password for the rsa private key
+/// have been entered and how many are required, see `add_rsa_password_share()`.
+///
+/// # Arguments
+///
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn get_rsa_password_shares(
+    _admin: AuthenticatedAdministrator,
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    let (shares_required, shares_collected) = match &application_configuration
+        .configuration_file
+        .shamir_configuration
+    {
+        Some(shamir_configuration) => {
+            let mut shamir_shares_write_lock =
+                application_configuration.shamir_shares.write().unwrap();
+            shamir_shares_write_lock
+                .remove_expired_shares(shamir_configuration.max_share_age_seconds);
+            (
+                shamir_configuration.threshold,
+                shamir_shares_write_lock.collected_shares(),
+            )
+        }
+        None => (0, 0),
     };
+    HttpResponse::ok_json_response(format!(
+        "{{\"sharesCollected\": {}, \"sharesRequired\": {}}}",
+        shares_collected, shares_required
+    ))
+}
 
-    info!("success, file {} written", &path.display());
-    // build url payload for email
-    let url_payload = format!(
-        "{};{};{}",
-        &uuid.to_string(),
-        &aes_encryption_result.encryption_iv,
-        aes_encryption_result.encryption_key
-    );
-    debug!("url_payload = {}", &url_payload);
-    // rsa encrypt url payload
-    let encrypted_url_payload = match application_configuration
-        .rsa_keys
+/// Reloads the rsa key ring, e.g. after the primary key has been
+/// replaced by a new one and the old key has been moved to the key
+/// ring directory. The keys loaded before are kept if the reload fails.
+///
+/// # Arguments
+///
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn reload_rsa_keys(
+    _admin: AuthenticatedAdministrator,
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    if application_configuration
+        .rsa_password
         .read()
         .unwrap()
-        .encrypt_str(&url_payload)
+        .rsa_private_key_password
+        .is_none()
     {
-        Ok(encrypted_url_payload) => encrypted_url_payload,
+        return HttpResponse::err_text_response("ERROR: rsa password has not been set yet!");
+    }
+    info!("reloading rsa keys");
+    match application_configuration.load_master_key() {
         Err(e) => {
-            return HttpResponse::err_text_response(format!("ERROR: {}", &e));
+            warn!("error reloading rsa keys: {:?}", e);
+            HttpResponse::err_text_response("ERROR: could not reload rsa keys!")
         }
-    };
-    let encrypted_percent_encoded_url_payload =
-        utf8_percent_encode(&encrypted_url_payload, FRAGMENT);
-    debug!(
-        "encrypted_percent_encoded_url_payload = {}",
-        &encrypted_percent_encoded_url_payload
-    );
-    // send email to receiver
-    let mail_body_template = match application_configuration
-        .configuration_file
-        .email_configuration
-        .load_mail_template()
-    {
-        Ok(mail_body_template) => mail_body_template,
+        Ok(_) => {
+            info!("rsa keys have been reloaded successfully");
+            HttpResponse::ok_text_response("OK")
+        }
+    }
+}
+
+/// Seals the server: the password for the rsa private key is cleared,
+/// the keys are dropped and every session is removed. The password
+/// must be entered again before secrets can be revealed.
+///
+/// # Arguments
+///
+/// - `admin`:                     the administrator who seals the server
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn seal_server(
+    admin: AuthenticatedAdministrator,
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    info!("{} seals the server", &admin.0.user_name);
+    match application_configuration.seal() {
         Err(e) => {
-            warn!("error loading mail template: {}", &e);
-            return HttpResponse::err_text_response("ERROR: cannot send email!");
+            warn!("error sealing server: {:?}", e);
+            HttpResponse::err_text_response("ERROR: could not seal server!")
         }
-    };
-    let mail_body = &parsed_form_data.build_mail_body(
-        &mail_body_template,
-        &encrypted_percent_encoded_url_payload.to_string(),
-    );
-    let mail_subject = &parsed_form_data.build_context(
-        &application_configuration
-            .configuration_file
-            .email_configuration
-            .mail_subject,
-    );
-    info!(
-        "sending email to {} for secret {}",
-        &parsed_form_data.to_email,
-        &uuid.to_string()
-    );
-    if let Err(e) = &application_configuration
-        .configuration_file
-        .email_configuration
-        .send_mail(&parsed_form_data.to_email, mail_subject, mail_body)
-    {
-        warn!(
-            "error sending email to {} for secret {}: {}",
-            &parsed_form_data.to_email,
-            &uuid.to_string(),
-            &e
-        );
-        return HttpResponse::err_text_response("ERROR: cannot send email!");
-    };
-    HttpResponse::ok_text_response("OK")
+        Ok(_) => {
+            info!("server has been sealed");
+            // the session of the administrator has been removed as well
+            HttpResponse::ok_text_response_with_empty_unix_epoch_cookie("OK")
+        }
+    }
 }
 
-/// Returns the length of a base64 decoded secret. If it cannot be decoded
-/// at all, MAX_FORM_INPUT_LEN + 1 will be returned as length.
-fn get_base64_encoded_secret_len(parsed_secret: &str) -> usize {
-    let decoded_secret = match Vec::from_base64_encoded(parsed_secret) {
-        Ok(s) => s,
+/// Stores a secret and its meta date as encrypted file on disk.
+///
+/// # Arguments
+///
+/// - `bytes`:                     the POSTed bytes from the form
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn store_secret(
+    bytes: Bytes,
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    debug!("store_secret()");
+    let max_form_bytes = max_tell_secret_bytes(
+        application_configuration
+            .configuration_file
+            .max_attachment_bytes,
+    );
+    let parsed_form_data = match parse_secret(bytes, max_form_bytes) {
+        Ok(parsed_form_data) => parsed_form_data,
         Err(e) => {
-            warn!("error decoding secret, assuming input too long: {}", &e);
-            return MAX_FORM_INPUT_LEN + 1;
+            return HttpResponse::err_text_response(e.to_string());
         }
     };
-    if decoded_secret.len() > MAX_FORM_INPUT_LEN {
-        warn!("secret is too large: {} bytes!", &decoded_secret.len());
+    match tell_secret(parsed_form_data, &application_configuration).await {
+        Ok(_) => HttpResponse::ok_text_response("OK"),
+        Err(e) => HttpResponse::err_text_response(e.to_string()),
     }
-    decoded_secret.len()
 }
 
-/// Loads a stored secret and decrypts it
+/// Stores a secret sent by automation through the api
+/// and returns its id as json.
 ///
 /// # Arguments
 ///
-/// - encrypted_percent_encoded_url_payload: tail of the url
-/// - `application_configuration`:           application configuration
-/// - `user`:                                authenticated user calling this function
+/// - `req`:                       the request with the api token, see `validate_api_token()`
+/// - `bytes`:                     the POSTed json data
+/// - `application_configuration`: application configuration
 ///
 /// # Returns
 ///
 /// - `HttpResponse`
-pub async fn reveal_secret(
-    encrypted_percent_encoded_url_payload: web::Path<String>,
+pub async fn api_store_secret(
+    req: HttpRequest,
+    bytes: Bytes,
     application_configuration: web::Data<ApplicationConfiguration>,
-    user: AuthenticatedUser,
 ) -> HttpResponse {
-    debug!(
-        "reveal_secret(), encrypted_percent_encoded_url_payload {}",
-        &encrypted_percent_encoded_url_payload
-    );
-    let encrypted_url_payload =
-        percent_decode_str(&encrypted_percent_encoded_url_payload).decode_utf8_lossy();
-    // rsa decrypt all data
-    let url_payload = match application_configuration
-        .rsa_keys
+    debug!("api_store_secret()");
+    if application_configuration
+        .rsa_password
         .read()
         .unwrap()
-        .decrypt_str(&encrypted_url_payload)
+        .rsa_private_key_password
+        .is_none()
     {
+        return HttpResponse::err_text_response("ERROR: server is not ready!");
+    }
+    let parsed_form_data = match parse_secret(bytes, MAX_SECRET_FORM_BYTES_LEN) {
+        Ok(parsed_form_data) => parsed_form_data,
         Err(e) => {
-            return HttpResponse::err_text_response(format!("ERROR: {}", &e));
+            return HttpResponse::err_text_response(e.to_string());
         }
-        Ok(url_payload) => url_payload,
     };
-    debug!("url_payload = {}", &url_payload);
-    // get details from the payload
-    let mut split_iter = url_payload.split(';');
-    let uuid = split_iter.next().unwrap_or("uuid");
-    let iv_base64 = split_iter.next().unwrap_or("iv");
-    let key_base64 = split_iter.next().unwrap_or("key");
-    // load data from file
-    let path = Path::new(
-        &application_configuration
-            .configuration_file
-            .secret_directory,
+    let sender_allowed = match req.extensions().get::<ApiToken>() {
+        Some(api_token) => {
+            let sender_allowed = api_token.is_allowed_sender(&parsed_form_data.from_email);
+            if !sender_allowed {
+                warn!(
+                    "api token {} must not send secrets as {}",
+                    &api_token.name, &parsed_form_data.from_email
+                );
+            }
+            sender_allowed
+        }
+        None => false,
+    };
+    if !sender_allowed {
+        return HttpResponse::err_text_response("ERROR: sender not allowed for this api token!");
+    }
+    match tell_secret(parsed_form_data, &application_configuration).await {
+        Ok(secret_id) => {
+            let json_response = match serde_json::to_string(&SecretId { secret_id }) {
+                Ok(json_response) => json_response,
+                Err(e) => {
+                    return HttpResponse::err_text_response(format!("ERROR: {}", &e));
+                }
+            };
+            HttpResponse::ok_json_response(json_response)
+        }
+        Err(e) => HttpResponse::err_text_response(e.to_string()),
+    }
+}
+
+/// Id of a stored secret, returned by the api
+#[derive(Serialize)]
+struct SecretId {
+    #[serde(rename = "SecretId")]
+    secret_id: String,
+}
+
+/// One row of an uploaded csv file with secrets
+#[derive(Deserialize)]
+pub struct BulkSecretRow {
+    pub receiver: String,
+    pub context: String,
+    pub secret: String,
+    #[serde(default)]
+    pub valid_for_hours: Option<i64>,
+}
+
+/// Result of one row of an uploaded csv file with secrets
+#[derive(Serialize)]
+pub struct BulkSecretResult {
+    #[serde(rename = "Row")]
+    pub row: usize,
+    #[serde(rename = "ToEmail")]
+    pub to_email: String,
+    #[serde(rename = "Context")]
+    pub context: String,
+    #[serde(rename = "Status")]
+    pub status: String,
+    #[serde(rename = "Message")]
+    pub message: String,
+}
+
+impl BulkSecretResult {
+    /// Builds the result of a row that cannot be parsed.
+    ///
+    /// # Arguments
+    ///
+    /// - `row`: number of the row, starting with 1 after the header
+    /// - `e`:   the error of the csv parser
+    pub fn invalid_row(row: usize, e: &csv::Error) -> BulkSecretResult {
+        BulkSecretResult {
+            row,
+            to_email: String::new(),
+            context: String::new(),
+            status: "invalid".to_string(),
+            message: e.to_string(),
+        }
+    }
+
+    /// Builds the result of a row that has been passed to `tell_secret()`.
+    ///
+    /// # Arguments
+    ///
+    /// - `row`:         number of the row, starting with 1 after the header
+    /// - `csv_row`:     the parsed row
+    /// - `tell_result`: result of storing the secret
+    pub fn from_tell_result(
+        row: usize,
+        csv_row: BulkSecretRow,
+        tell_result: Result<String, TellSecretError>,
+    ) -> BulkSecretResult {
+        let (status, message) = match tell_result {
+            Ok(_) => ("stored", String::new()),
+            Err(e) => {
+                let status = match e {
+                    TellSecretError::InvalidInput(_) => "invalid",
+                    TellSecretError::UnknownReceiver(_) => "unknown receiver",
+                    TellSecretError::MailFailed(_) => "mail failed",
+                    TellSecretError::Failed(_) => "failed",
+                };
+                // the report must not be mistaken for an error response
+                (status, e.to_string().replacen("ERROR: ", "", 1))
+            }
+        };
+        BulkSecretResult {
+            row,
+            to_email: csv_row.receiver,
+            context: csv_row.context,
+            status: status.to_string(),
+            message,
+        }
+    }
+}
+
+/// Parses an uploaded csv file with the columns `receiver`, `context`,
+/// `secret` and the optional `valid_for_hours`.
+///
+/// # Arguments
+///
+/// - `csv_data`: the csv file including the header
+///
+/// # Returns
+///
+/// - `Vec<Result<BulkSecretRow, csv::Error>>` - one entry per row
+pub fn parse_bulk_secret_rows(csv_data: &[u8]) -> Vec<Result<BulkSecretRow, csv::Error>> {
+    // rows without `valid_for_hours` may omit the last column
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(csv_data)
+        .deserialize::<BulkSecretRow>()
+        .collect()
+}
+
+/// Stores one secret per row of an uploaded csv file with the
+/// columns `receiver`, `context`, `secret` and the optional
+/// `valid_for_hours`. The administrator is the sender of all secrets.
+///
+/// # Arguments
+///
+/// - `admin`:                     authenticated administrator, sender of the secrets
+/// - `bytes`:                     the POSTed csv file
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse` - json report with the result of every row
+pub async fn bulk_store_secrets(
+    admin: AuthenticatedAdministrator,
+    bytes: Bytes,
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    debug!("bulk_store_secrets()");
+    if application_configuration
+        .rsa_password
+        .read()
+        .unwrap()
+        .rsa_private_key_password
+        .is_none()
+    {
+        return HttpResponse::err_text_response("ERROR: server is not ready!");
+    }
+    if bytes.len() > MAX_BULK_CSV_BYTES_LEN {
+        warn!("csv data exceeds {} bytes!", MAX_BULK_CSV_BYTES_LEN);
+        return HttpResponse::err_text_response(format!(
+            "ERROR: more than {} bytes of data sent",
+            &MAX_BULK_CSV_BYTES_LEN
+        ));
+    }
+    let sender = &admin.0;
+    info!("{} sends secrets from a csv file", &sender.user_name);
+    let mut results: Vec<BulkSecretResult> = Vec::new();
+    for (index, csv_row) in parse_bulk_secret_rows(bytes.as_ref())
+        .into_iter()
+        .enumerate()
+    {
+        let mut csv_row = match csv_row {
+            Ok(csv_row) => csv_row,
+            Err(e) => {
+                results.push(BulkSecretResult::invalid_row(index + 1, &e));
+                continue;
+            }
+        };
+        let secret = Secret {
+            version: SECRET_FORMAT_VERSION,
+            from_email: sender.mail.clone(),
+            from_display_name: format!("{} {}", &sender.first_name, &sender.last_name),
+            to_email: csv_row.receiver.clone(),
+            to_display_name: String::new(),
+            context: csv_row.context.clone(),
+            secret: csv_row.secret.to_base64_encoded(),
+            valid_for_hours: csv_row.valid_for_hours,
+            expires_at: None,
+            receivers: Vec::new(),
+            passphrase: None,
+            passphrase_salt: None,
+            data_key: None,
+            key_id: None,
+            attachment: None,
+        };
+        csv_row.secret.zeroize();
+        let tell_result = tell_secret(secret, &application_configuration).await;
+        results.push(BulkSecretResult::from_tell_result(
+            index + 1,
+            csv_row,
+            tell_result,
+        ));
+    }
+    match serde_json::to_string(&results) {
+        Ok(json_response) => HttpResponse::ok_json_response(json_response),
+        Err(e) => HttpResponse::err_text_response(format!("ERROR: {}", &e)),
+    }
+}
+
+/// Errors that may occur while telling a secret, used to
+/// report the result of every row in a bulk upload.
+#[derive(Debug)]
+pub enum TellSecretError {
+    /// the posted data is invalid
+    InvalidInput(String),
+    /// the receiver cannot be found
+    UnknownReceiver(String),
+    /// the secret has been stored but the mail could not be sent
+    MailFailed(String),
+    /// the secret could not be encrypted or stored
+    Failed(String),
+}
+
+impl fmt::Display for TellSecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TellSecretError::InvalidInput(e)
+            | TellSecretError::UnknownReceiver(e)
+            | TellSecretError::MailFailed(e)
+            | TellSecretError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Returns the max length of the POSTed form data of a new secret,
+/// including an attached file of up to `max_attachment_bytes`.
+///
+/// # Arguments
+///
+/// - `max_attachment_bytes`: max size of an attached file, see configuration
+///
+/// # Returns
+///
+/// - `usize`
+pub fn max_tell_secret_bytes(max_attachment_bytes: Option<usize>) -> usize {
+    match max_attachment_bytes {
+        // the attachment is base64 encoded, file name and content type
+        // are limited just like other form fields
+        Some(max_attachment_bytes) => {
+            MAX_SECRET_FORM_BYTES_LEN
+                + max_attachment_bytes.div_ceil(3) * 4
+                + 2 * MAX_FORM_INPUT_LEN
+        }
+        None => MAX_SECRET_FORM_BYTES_LEN,
+    }
+}
+
+/// One comma separated receiver of a secret as entered in the form.
+#[derive(Debug, PartialEq)]
+pub enum ReceiverEntry {
+    /// a single email address
+    Email(String),
+    /// a group like `cn=netops`, expanded to the email addresses of its members
+    Group(String),
+}
+
+/// Splits the comma separated receivers of a secret into
+/// groups (`cn=` prefix) and single email addresses.
+///
+/// # Arguments
+///
+/// - `to_email`: comma separated receivers and groups
+///
+/// # Returns
+///
+/// - `Result<Vec<ReceiverEntry>, TellSecretError>`
+pub fn split_receivers(to_email: &str) -> Result<Vec<ReceiverEntry>, TellSecretError> {
+    let mut receiver_entries: Vec<ReceiverEntry> = Vec::new();
+    for to_email in to_email.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        if to_email.len() > MAX_FORM_INPUT_LEN {
+            return Err(TellSecretError::InvalidInput(format!(
+                "ERROR: to email > {} chars",
+                MAX_FORM_INPUT_LEN
+            )));
+        }
+        match to_email.get(..GROUP_PREFIX.len()) {
+            Some(prefix) if prefix.eq_ignore_ascii_case(GROUP_PREFIX) => {
+                receiver_entries.push(ReceiverEntry::Group(
+                    to_email[GROUP_PREFIX.len()..].to_string(),
+                ));
+            }
+            _ => receiver_entries.push(ReceiverEntry::Email(to_email.to_string())),
+        }
+    }
+    Ok(receiver_entries)
+}
+
+/// Removes duplicate email addresses of receivers, ignoring case,
+/// and checks the number of receivers after groups have been expanded.
+///
+/// # Arguments
+///
+/// - `to_emails`: email addresses of all receivers and group members
+///
+/// # Returns
+///
+/// - `Result<Vec<String>, TellSecretError>`
+pub fn unique_receiver_emails(to_emails: Vec<String>) -> Result<Vec<String>, TellSecretError> {
+    let mut receiver_emails: Vec<String> = Vec::new();
+    for to_email in to_emails {
+        if !receiver_emails
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(&to_email))
+        {
+            receiver_emails.push(to_email);
+        }
+    }
+    if receiver_emails.is_empty() {
+        return Err(TellSecretError::InvalidInput(
+            "ERROR: no receiver given".to_string(),
+        ));
+    }
+    if receiver_emails.len() > MAX_RECEIVERS {
+        return Err(TellSecretError::InvalidInput(format!(
+            "ERROR: more than {} receivers",
+            MAX_RECEIVERS
+        )));
+    }
+    Ok(receiver_emails)
+}
+
+/// Parses the POSTed json data of a secret.
+///
+/// # Arguments
+///
+/// - `bytes`:          the POSTed json data
+/// - `max_form_bytes`: max length of the POSTed json data
+///
+/// # Returns
+///
+/// - `Result<Secret, TellSecretError>`
+fn parse_secret(bytes: Bytes, max_form_bytes: usize) -> Result<Secret, TellSecretError> {
+    let bytes_vec = bytes.to_vec();
+    let form_data = match String::from_utf8(bytes_vec) {
+        Ok(form_data) => form_data,
+        Err(_) => {
+            return Err(TellSecretError::InvalidInput(
+                "ERROR: could not parse form data".to_string(),
+            ));
+        }
+    };
+    if form_data.len() > max_form_bytes {
+        warn!("form data exceeds {} bytes!", max_form_bytes);
+        return Err(TellSecretError::InvalidInput(format!(
+            "ERROR: more than {} bytes of data sent",
+            &max_form_bytes
+        )));
+    }
+    let parsed_form_data = match serde_json::from_str(&form_data) as Result<Secret, _> {
+        Ok(parsed_form_data) => parsed_form_data,
+        Err(_) => {
+            return Err(TellSecretError::InvalidInput(
+                "ERROR: could not parse json form data".to_string(),
+            ));
+        }
+    };
+    debug!("parsed_form_data={:?}", &parsed_form_data);
+    Ok(parsed_form_data)
+}
+
+/// Validates and stores a secret as encrypted file on disk,
+/// then the receiver is notified via email.
+///
+/// # Arguments
+///
+/// - `parsed_form_data`:          the secret, the secret itself must be base64 encoded
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `Result<String, TellSecretError>` - either the id of the stored secret or an error
+async fn tell_secret(
+    mut parsed_form_data: Secret,
+    application_configuration: &web::Data<ApplicationConfiguration>,
+) -> Result<String, TellSecretError> {
+    if parsed_form_data.from_email.len() > MAX_FORM_INPUT_LEN {
+        return Err(TellSecretError::InvalidInput(format!(
+            "ERROR: from email > {} chars",
+            MAX_FORM_INPUT_LEN
+        )));
+    }
+    // the secret may be told to several comma separated receivers
+    // and groups like `cn=netops` at once
+    let mut to_emails: Vec<String> = Vec::new();
+    for receiver_entry in split_receivers(&parsed_form_data.to_email)? {
+        match receiver_entry {
+            ReceiverEntry::Email(to_email) => to_emails.push(to_email),
+            ReceiverEntry::Group(group) => {
+                match <UserDataImpl as GetUserData>::get_group_members(
+                    &group,
+                    application_configuration,
+                )
+                .await
+                {
+                    Ok(members) if !members.is_empty() => {
+                        info!("group {} has the members {}", &group, members.join(", "));
+                        to_emails.extend(members);
+                    }
+                    Ok(_) => {
+                        return Err(TellSecretError::UnknownReceiver(format!(
+                            "ERROR: group {} has no members",
+                            &group
+                        )));
+                    }
+                    Err(e) => {
+                        info!("cannot find group {}, error: {}", &group, &e);
+                        return Err(TellSecretError::UnknownReceiver(format!(
+                            "ERROR: cannot find group {}",
+                            &group
+                        )));
+                    }
+                }
+            }
+        }
+    }
+    let receiver_emails = unique_receiver_emails(to_emails)?;
+    if parsed_form_data.context.len() > MAX_FORM_INPUT_LEN {
+        return Err(TellSecretError::InvalidInput(format!(
+            "ERROR: context > {} chars",
+            MAX_FORM_INPUT_LEN
+        )));
+    }
+    let secret_length = get_base64_encoded_secret_len(&parsed_form_data.secret);
+    if secret_length > MAX_SECRET_LEN {
+        return Err(TellSecretError::InvalidInput(format!(
+            "ERROR: secret > {} bytes!",
+            MAX_SECRET_LEN
+        )));
+    }
+    if let Some(attachment) = parsed_form_data.attachment.as_mut() {
+        validate_attachment(
+            attachment,
+            application_configuration
+                .configuration_file
+                .max_attachment_bytes,
+        )?;
+    }
+    // the sender may choose one of the configured lifetimes,
+    // otherwise the secret lives as long as any secret may live.
+    let expires_at = match parsed_form_data.valid_for_hours {
+        Some(valid_for_hours) => {
+            if !application_configuration
+                .configuration_file
+                .secret_valid_for_hours
+                .contains(&valid_for_hours)
+            {
+                return Err(TellSecretError::InvalidInput(format!(
+                    "ERROR: invalid lifetime of {} hours",
+                    valid_for_hours
+                )));
+            }
+            Utc::now() + Duration::hours(valid_for_hours)
+        }
+        None => {
+            Utc::now()
+                + Duration::seconds(
+                    application_configuration
+                        .configuration_file
+                        .max_secret_age_seconds,
+                )
+        }
+    };
+    parsed_form_data.valid_for_hours = None;
+    parsed_form_data.expires_at = Some(expires_at.to_rfc3339());
+    let email_configuration = &application_configuration
+        .configuration_file
+        .email_configuration;
+    let mut receivers: Vec<Receiver> = Vec::new();
+    let mut receiver_languages: Vec<Option<String>> = Vec::new();
+    let mut one_time_codes: Vec<Option<String>> = Vec::new();
+    for to_email in receiver_emails {
+        // external receivers are not in the directory, they get
+        // a one-time code to reveal the secret without login.
+        if application_configuration
+            .configuration_file
+            .is_external_receiver(&to_email)
+        {
+            if email_configuration.external_receiver_link.is_none()
+                || email_configuration.external_receiver_code.is_none()
+            {
+                return Err(TellSecretError::InvalidInput(
+                    "ERROR: external receivers are not configured!".to_string(),
+                ));
+            }
+            let one_time_code = match create_one_time_code() {
+                Ok(one_time_code) => one_time_code,
+                Err(e) => {
+                    return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+                }
+            };
+            info!("{} is an external receiver", &to_email);
+            receivers.push(Receiver {
+                to_display_name: to_email.clone(),
+                to_email,
+                revealed: false,
+                code_hash: Some(hash_one_time_code(&one_time_code)),
+                failed_attempts: 0,
+            });
+            receiver_languages.push(None);
+            one_time_codes.push(Some(one_time_code));
+            continue;
+        }
+        let receiver_details = match <UserDataImpl as GetUserData>::get_receiver_details(
+            &to_email,
+            application_configuration,
+        )
+        .await
+        {
+            Ok(receiver_details) => receiver_details,
+            Err(e) => {
+                info!("cannot find mail address {}, error: {}", &to_email, &e);
+                return Err(TellSecretError::UnknownReceiver(format!(
+                    "ERROR: cannot find mail address {}",
+                    &to_email
+                )));
+            }
+        };
+        receivers.push(Receiver {
+            to_email,
+            to_display_name: receiver_details.display_name,
+            revealed: false,
+            code_hash: None,
+            failed_attempts: 0,
+        });
+        receiver_languages.push(receiver_details.language);
+        one_time_codes.push(None);
+    }
+    // the first receiver is also stored the way secrets were
+    // stored before multiple receivers were supported.
+    parsed_form_data.to_email = receivers[0].to_email.clone();
+    parsed_form_data.to_display_name = receivers[0].to_display_name.clone();
+    parsed_form_data.receivers = receivers;
+    // aes encrypt the secret before rsa encryption
+    let aes_encryption_result = match parsed_form_data.secret.to_aes_enrypted_b64() {
+        Ok(aes_encryption_result) => aes_encryption_result,
+        Err(e) => {
+            return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+        }
+    };
+    // store aes encrypted secret instead of plaintext secret
+    parsed_form_data.secret = aes_encryption_result.encrypted_data.clone();
+    // the attachment is encrypted with the same key as the secret
+    if let Some(attachment) = parsed_form_data.attachment.as_mut() {
+        if let Err(e) = attachment.encrypt_content(&aes_encryption_result.encryption_key) {
+            return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+        }
+    }
+    // the key in the url payload is wrapped with the optional passphrase,
+    // so that the link alone is not enough to reveal the secret.
+    let url_payload_key = match parsed_form_data.passphrase.take() {
+        Some(mut passphrase) if !passphrase.is_empty() => {
+            if passphrase.len() > MAX_FORM_INPUT_LEN {
+                passphrase.zeroize();
+                return Err(TellSecretError::InvalidInput(format!(
+                    "ERROR: passphrase > {} chars",
+                    MAX_FORM_INPUT_LEN
+                )));
+            }
+            let passphrase_salt = match create_passphrase_salt() {
+                Ok(passphrase_salt) => passphrase_salt,
+                Err(e) => {
+                    passphrase.zeroize();
+                    return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+                }
+            };
+            // deriving the key takes a while, the worker thread must not be blocked
+            let mut encryption_key = aes_encryption_result.encryption_key.clone();
+            let salt = passphrase_salt.clone();
+            let wrapped_key = web::block(move || {
+                let wrapped_key = wrap_key_with_passphrase(&encryption_key, &passphrase, &salt)
+                    .map_err(|e| e.to_string());
+                encryption_key.zeroize();
+                passphrase.zeroize();
+                wrapped_key
+            })
+            .await;
+            let wrapped_key = match wrapped_key {
+                Ok(Ok(wrapped_key)) => wrapped_key,
+                Ok(Err(e)) => {
+                    return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+                }
+                Err(e) => {
+                    return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+                }
+            };
+            parsed_form_data.passphrase_salt = Some(passphrase_salt);
+            wrapped_key
+        }
+        _ => aes_encryption_result.encryption_key.clone(),
+    };
+    // encrypt all data with the master key
+    let encrypted_form_data = match parsed_form_data.to_encrypted(
+        application_configuration
+            .master_key
+            .read()
+            .unwrap()
+            .as_ref(),
+    ) {
+        Ok(encrypted_form_data) => encrypted_form_data,
+        Err(e) => {
+            return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+        }
+    };
+
+    // write data to disk
+    let uuid = application_configuration
+        .shared_secret
+        .write()
+        .unwrap()
+        .create_uuid();
+
+    let path = Path::new(
+        &application_configuration
+            .configuration_file
+            .secret_directory,
+    )
+    .join(uuid.to_string());
+    info!("writing secret to file {}", &path.display());
+    if let Err(e) = encrypted_form_data.write_to_disk(&path).await {
+        warn!("{}", &e);
+        return Err(TellSecretError::Failed(format!(
+            "ERROR: could not write secret {} to disk!",
+            &path.display()
+        )));
+    };
+
+    info!("success, file {} written", &path.display());
+    // every receiver gets a mail with an own url payload
+    let mut failed_receivers: Vec<String> = Vec::new();
+    for (receiver_index, receiver_language) in receiver_languages.iter().enumerate() {
+        let receiver = &parsed_form_data.receivers[receiver_index];
+        let to_email = receiver.to_email.clone();
+        let to_display_name = receiver.to_display_name.clone();
+        // build url payload for email
+        let url_payload = format!(
+            "{};{};{};{};{}",
+            &uuid.to_string(),
+            &aes_encryption_result.encryption_iv,
+            &url_payload_key,
+            &expires_at.to_rfc3339(),
+            receiver_index
+        );
+        debug!("url_payload = {}", &url_payload);
+        // rsa encrypt url payload
+        let encrypted_url_payload = match application_configuration
+            .master_key
+            .read()
+            .unwrap()
+            .encrypt_str(&url_payload)
+        {
+            Ok(encrypted_url_payload) => encrypted_url_payload,
+            Err(e) => {
+                return Err(TellSecretError::Failed(format!("ERROR: {}", &e)));
+            }
+        };
+        let encrypted_percent_encoded_url_payload =
+            utf8_percent_encode(&encrypted_url_payload, FRAGMENT).to_string();
+        debug!(
+            "encrypted_percent_encoded_url_payload = {}",
+            &encrypted_percent_encoded_url_payload
+        );
+        // send email to receiver
+        parsed_form_data.to_email = to_email;
+        parsed_form_data.to_display_name = to_display_name;
+        info!(
+            "sending email to {} for secret {}",
+            &parsed_form_data.to_email,
+            &uuid.to_string()
+        );
+        let notify_result = match (
+            &one_time_codes[receiver_index],
+            &email_configuration.external_receiver_link,
+            &email_configuration.external_receiver_code,
+        ) {
+            // link and one-time code are sent in separate mails
+            (Some(one_time_code), Some(link_template), Some(code_template)) => notify_receiver(
+                &parsed_form_data,
+                link_template,
+                &encrypted_percent_encoded_url_payload,
+                None,
+                email_configuration,
+            )
+            .and_then(|_| {
+                notify_receiver(
+                    &parsed_form_data,
+                    code_template,
+                    "",
+                    Some(one_time_code),
+                    email_configuration,
+                )
+            }),
+            _ => notify_receiver(
+                &parsed_form_data,
+                &email_configuration.mail_template_for_language(receiver_language.as_deref()),
+                &encrypted_percent_encoded_url_payload,
+                None,
+                email_configuration,
+            ),
+        };
+        if let Err(e) = notify_result {
+            warn!(
+                "error sending email to {} for secret {}: {}",
+                &parsed_form_data.to_email,
+                &uuid.to_string(),
+                &e
+            );
+            failed_receivers.push(parsed_form_data.to_email.clone());
+        }
+    }
+    if !failed_receivers.is_empty() {
+        return Err(TellSecretError::MailFailed(format!(
+            "ERROR: cannot send email to {}!",
+            failed_receivers.join(", ")
+        )));
+    }
+    Ok(uuid.to_string())
+}
+
+/// Sends the mail with a link to its receiver, which is taken
+/// from `to_email` and `to_display_name` of the secret.
+///
+/// # Arguments
+///
+/// - `secret`:              the secret, only the meta data is used
+/// - `mail_template`:       subject and templates of the mail
+/// - `url_payload`:         encrypted and percent encoded url payload
+/// - `one_time_code`:       one-time code of an external receiver, replaces `{Code}`
+/// - `email_configuration`: mail server to use
+///
+/// # Returns
+///
+/// - `Result<(), Box<dyn Error>>`
+fn notify_receiver(
+    secret: &Secret,
+    mail_template: &MailTemplateConfiguration,
+    url_payload: &str,
+    one_time_code: Option<&str>,
+    email_configuration: &SendEMailConfiguration,
+) -> Result<(), Box<dyn Error>> {
+    let one_time_code = one_time_code.unwrap_or_default();
+    let mail_body_template = mail_template
+        .load_mail_template()?
+        .replace("{Code}", one_time_code);
+    let mail_body = secret.build_mail_body(&mail_body_template, url_payload);
+    let mail_body_html = mail_template
+        .load_mail_template_html()?
+        .map(|mail_body_template_html| {
+            secret.build_mail_body_html(
+                &mail_body_template_html.replace("{Code}", one_time_code),
+                url_payload,
+            )
+        });
+    let mail_subject = secret
+        .build_context(&mail_template.mail_subject)
+        .replace("{FromDisplayName}", &secret.from_display_name);
+    email_configuration.send_multipart_mail(
+        &secret.to_email,
+        &mail_subject,
+        &mail_body,
+        mail_body_html.as_deref(),
+    )
+}
+
+/// Request to send a secret, POSTed by the requester
+#[derive(Deserialize, Debug)]
+struct SecretRequest {
+    /// the user that is asked to send the secret
+    #[serde(rename = "SenderEmail")]
+    sender_email: String,
+    #[serde(rename = "Context")]
+    context: String,
+}
+
+/// Details of a secret request, used to pre-fill the form
+/// of the user that has been asked to send the secret
+#[derive(Serialize)]
+struct SecretRequestDetails {
+    #[serde(rename = "ToEmail")]
+    to_email: String,
+    #[serde(rename = "Context")]
+    context: String,
+}
+
+/// Payload of the link in a secret request. Requester, expiry and
+/// context are encrypted as one value with a random data key, only
+/// the data key is encrypted with the master key. So the parts of
+/// different links cannot be combined and the master key never
+/// encrypts text chosen by the requester.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct SecretRequestPayload {
+    /// the user that asked for the secret and will receive it
+    #[serde(rename = "Requester")]
+    pub requester: String,
+    /// rfc3339 timestamp after which the link is no longer valid
+    #[serde(rename = "ExpiresAt")]
+    pub expires_at: String,
+    #[serde(rename = "Context")]
+    pub context: String,
+}
+
+impl SecretRequestPayload {
+    /// Encrypts the payload for the link of a secret request.
+    ///
+    /// # Arguments
+    ///
+    /// - `master_key`: master key that encrypts the data key
+    ///
+    /// # Returns
+    ///
+    /// - `Result<String, Box<dyn Error>>` - the encrypted data key and payload, separated by `;`
+    pub fn to_encrypted_url_payload(
+        &self,
+        master_key: &dyn MasterKey,
+    ) -> Result<String, Box<dyn Error>> {
+        let json_payload = serde_json::to_string(self)?;
+        let mut data_key = create_data_key()?;
+        let encrypted_payload = encrypt_with_data_key(&data_key, &json_payload);
+        let mut data_key_base64 = data_key.to_base64_urlsafe_encoded();
+        data_key.zeroize();
+        let encrypted_data_key = master_key.encrypt_str(&data_key_base64);
+        data_key_base64.zeroize();
+        Ok(format!("{};{}", encrypted_data_key?, encrypted_payload?))
+    }
+
+    /// Decrypts the payload of a link that has been built
+    /// by `to_encrypted_url_payload()`.
+    ///
+    /// # Arguments
+    ///
+    /// - `encrypted_url_payload`: the encrypted data key and payload, separated by `;`
+    /// - `master_key`:            master key that decrypts the data key
+    ///
+    /// # Returns
+    ///
+    /// - `Result<SecretRequestPayload, Box<dyn Error>>`
+    pub fn from_encrypted_url_payload(
+        encrypted_url_payload: &str,
+        master_key: &dyn MasterKey,
+    ) -> Result<SecretRequestPayload, Box<dyn Error>> {
+        let (encrypted_data_key, encrypted_payload) = encrypted_url_payload
+            .split_once(';')
+            .ok_or("invalid secret request payload")?;
+        let mut data_key_base64 = master_key.decrypt_str(encrypted_data_key)?;
+        let data_key = Vec::from_base64_urlsafe_encoded(&data_key_base64);
+        data_key_base64.zeroize();
+        let mut data_key = data_key?;
+        let json_payload = decrypt_with_data_key(&data_key, encrypted_payload);
+        data_key.zeroize();
+        Ok(serde_json::from_str(&json_payload?)?)
+    }
+
+    /// Returns `true` if the secret request has expired.
+    pub fn is_expired(&self) -> bool {
+        is_expired(&self.expires_at)
+    }
+}
+
+/// Asks a user to send a secret to the authenticated user. The user
+/// gets an email with a link to a pre-filled form to tell the secret.
+///
+/// The link contains the encrypted mail address of the requester,
+/// the expiry timestamp of the request and the context, see
+/// `SecretRequestPayload`. Nothing is stored on disk.
+///
+/// # Arguments
+///
+/// - `user`:                      authenticated user requesting the secret
+/// - `bytes`:                     the POSTed json data
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn request_secret(
+    user: AuthenticatedUser,
+    bytes: Bytes,
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    debug!("request_secret()");
+    let email_configuration = &application_configuration
+        .configuration_file
+        .email_configuration;
+    let secret_request_template = match &email_configuration.secret_request {
+        Some(secret_request_template) => secret_request_template,
+        None => {
+            return HttpResponse::err_text_response("ERROR: secret requests are not configured!");
+        }
+    };
+    if bytes.len() > MAX_FORM_BYTES_LEN {
+        warn!("form data exceeds {} bytes!", MAX_FORM_BYTES_LEN);
+        return HttpResponse::err_text_response(format!(
+            "ERROR: more than {} bytes of data sent",
+            &MAX_FORM_BYTES_LEN
+        ));
+    }
+    let secret_request = match serde_json::from_slice(&bytes) as Result<SecretRequest, _> {
+        Ok(secret_request) => secret_request,
+        Err(_) => {
+            return HttpResponse::err_text_response("ERROR: could not parse json form data");
+        }
+    };
+    debug!("secret_request={:?}", &secret_request);
+    if secret_request.sender_email.len() > MAX_FORM_INPUT_LEN {
+        return HttpResponse::err_text_response(format!(
+            "ERROR: sender email > {} chars",
+            MAX_FORM_INPUT_LEN
+        ));
+    }
+    if secret_request.context.len() > MAX_FORM_INPUT_LEN {
+        return HttpResponse::err_text_response(format!(
+            "ERROR: context > {} chars",
+            MAX_FORM_INPUT_LEN
+        ));
+    }
+    let sender_details = match <UserDataImpl as GetUserData>::get_receiver_details(
+        &secret_request.sender_email,
+        &application_configuration,
+    )
+    .await
+    {
+        Ok(sender_details) => sender_details,
+        Err(e) => {
+            info!(
+                "cannot find mail address {}, error: {}",
+                &secret_request.sender_email, &e
+            );
+            return HttpResponse::err_text_response(format!(
+                "ERROR: cannot find mail address {}",
+                &secret_request.sender_email
+            ));
+        }
+    };
+    let expires_at = Utc::now()
+        + Duration::seconds(
+            application_configuration
+                .configuration_file
+                .max_secret_age_seconds,
+        );
+    let secret_request_payload = SecretRequestPayload {
+        requester: user.mail.clone(),
+        expires_at: expires_at.to_rfc3339(),
+        context: secret_request.context.clone(),
+    };
+    let rsa_read_lock = application_configuration.master_key.read().unwrap();
+    let encrypted_url_payload =
+        match secret_request_payload.to_encrypted_url_payload(rsa_read_lock.as_ref()) {
+            Ok(encrypted_url_payload) => encrypted_url_payload,
+            Err(e) => {
+                return HttpResponse::err_text_response(format!("ERROR: {}", &e));
+            }
+        };
+    drop(rsa_read_lock);
+    let encrypted_percent_encoded_url_payload =
+        utf8_percent_encode(&encrypted_url_payload, FRAGMENT).to_string();
+    // the requester is the receiver of the secret, but
+    // the asked user is the receiver of this mail.
+    let secret = Secret {
+        version: SECRET_FORMAT_VERSION,
+        from_email: user.mail.clone(),
+        from_display_name: format!("{} {}", &user.first_name, &user.last_name),
+        to_email: secret_request.sender_email,
+        to_display_name: sender_details.display_name,
+        context: secret_request.context,
+        secret: String::new(),
+        valid_for_hours: None,
+        expires_at: None,
+        receivers: Vec::new(),
+        passphrase: None,
+        passphrase_salt: None,
+        data_key: None,
+        key_id: None,
+        attachment: None,
+    };
+    info!(
+        "{} asks {} for a secret",
+        &secret.from_email, &secret.to_email
+    );
+    match notify_receiver(
+        &secret,
+        secret_request_template,
+        &encrypted_percent_encoded_url_payload,
+        None,
+        email_configuration,
+    ) {
+        Ok(_) => HttpResponse::ok_text_response("OK"),
+        Err(e) => {
+            warn!(
+                "error sending secret request to {}: {}",
+                &secret.to_email, &e
+            );
+            HttpResponse::err_text_response("ERROR: cannot send email!")
+        }
+    }
+}
+
+/// Decrypts the link of a secret request, so that the asked
+/// user gets a form that is pre-filled with the requester
+/// as receiver and the context.
+///
+/// # Arguments
+///
+/// - `encrypted_percent_encoded_url_payload`: tail of the url
+/// - `application_configuration`:             application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn get_secret_request(
+    encrypted_percent_encoded_url_payload: web::Path<String>,
+    application_configuration: web::Data<ApplicationConfiguration>,
+    _user: AuthenticatedUser,
+) -> HttpResponse {
+    debug!(
+        "get_secret_request(), encrypted_percent_encoded_url_payload {}",
+        &encrypted_percent_encoded_url_payload
+    );
+    let encrypted_url_payload =
+        percent_decode_str(&encrypted_percent_encoded_url_payload).decode_utf8_lossy();
+    let rsa_read_lock = application_configuration.master_key.read().unwrap();
+    let secret_request_payload = SecretRequestPayload::from_encrypted_url_payload(
+        &encrypted_url_payload,
+        rsa_read_lock.as_ref(),
+    );
+    drop(rsa_read_lock);
+    // the same error for every failure, the padding must not be revealed
+    let secret_request_payload = match secret_request_payload {
+        Ok(secret_request_payload) => secret_request_payload,
+        Err(_) => {
+            return HttpResponse::err_text_response("ERROR: invalid secret request!");
+        }
+    };
+    if secret_request_payload.is_expired() {
+        return HttpResponse::err_text_response("ERROR: secret request expired!");
+    }
+    let secret_request_details = SecretRequestDetails {
+        to_email: secret_request_payload.requester,
+        context: secret_request_payload.context,
+    };
+    match serde_json::to_string(&secret_request_details) {
+        Ok(json_response) => HttpResponse::ok_json_response(json_response),
+        Err(e) => HttpResponse::err_text_response(format!("ERROR: {}", &e)),
+    }
+}
+
+/// Validates a file attached to a new secret. Only the base name of
+/// the file is kept and an empty content type is replaced by
+/// `application/octet-stream`.
+///
+/// # Arguments
+///
+/// - `attachment`:           the attached file, content must be base64 encoded
+/// - `max_attachment_bytes`: max size of the file, `None` if attachments are not allowed
+///
+/// # Returns
+///
+/// - `Result<(), TellSecretError>`
+fn validate_attachment(
+    attachment: &mut Attachment,
+    max_attachment_bytes: Option<usize>,
+) -> Result<(), TellSecretError> {
+    let max_attachment_bytes = match max_attachment_bytes {
+        Some(max_attachment_bytes) => max_attachment_bytes,
+        None => {
+            return Err(TellSecretError::InvalidInput(
+                "ERROR: attachments are not allowed!".to_string(),
+            ));
+        }
+    };
+    attachment.file_name = attachment
+        .file_name
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    if attachment.file_name.is_empty() || attachment.file_name.len() > MAX_FORM_INPUT_LEN {
+        return Err(TellSecretError::InvalidInput(format!(
+            "ERROR: file name must have 1 to {} chars",
+            MAX_FORM_INPUT_LEN
+        )));
+    }
+    if attachment.content_type.is_empty() {
+        attachment.content_type = "application/octet-stream".to_string();
+    }
+    if attachment.content_type.len() > MAX_FORM_INPUT_LEN
+        || !attachment
+            .content_type
+            .chars()
+            .all(|c| c.is_ascii_graphic())
+    {
+        return Err(TellSecretError::InvalidInput(
+            "ERROR: invalid content type of attachment".to_string(),
+        ));
+    }
+    let attachment_length = match Vec::from_base64_encoded(&attachment.content) {
+        Ok(content) => content.len(),
+        Err(_) => {
+            return Err(TellSecretError::InvalidInput(
+                "ERROR: could not decode attachment".to_string(),
+            ));
+        }
+    };
+    if attachment_length > max_attachment_bytes {
+        warn!("attachment is too large: {} bytes!", &attachment_length);
+        return Err(TellSecretError::InvalidInput(format!(
+            "ERROR: attachment > {} bytes!",
+            max_attachment_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the length of a base64 decoded secret. If it cannot be decoded
+/// at all, MAX_SECRET_LEN + 1 will be returned as length.
+fn get_base64_encoded_secret_len(parsed_secret: &str) -> usize {
+    let decoded_secret = match Vec::from_base64_encoded(parsed_secret) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("error decoding secret, assuming input too long: {}", &e);
+            return MAX_SECRET_LEN + 1;
+        }
+    };
+    if decoded_secret.len() > MAX_SECRET_LEN {
+        warn!("secret is too large: {} bytes!", &decoded_secret.len());
+    }
+    decoded_secret.len()
+}
+
+/// Loads a stored secret and decrypts it
+///
+/// # Arguments
+///
+/// - encrypted_percent_encoded_url_payload: tail of the url
+/// - `application_configuration`:           application configuration
+/// - `user`:                                authenticated user calling this function
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn reveal_secret(
+    encrypted_percent_encoded_url_payload: web::Path<String>,
+    application_configuration: web::Data<ApplicationConfiguration>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    debug!(
+        "reveal_secret(), encrypted_percent_encoded_url_payload {}",
+        &encrypted_percent_encoded_url_payload
+    );
+    reveal(
+        &encrypted_percent_encoded_url_payload,
+        &application_configuration,
+        RevealEntitlement::User(&user),
+        None,
+    )
+    .await
+}
+
+/// Passphrase POSTed by the receiver of a protected secret
+#[derive(Deserialize)]
+struct Passphrase {
+    #[serde(rename = "Passphrase")]
+    passphrase: String,
+}
+
+/// Loads a stored secret that is protected by a passphrase and decrypts it
+///
+/// # Arguments
+///
+/// - encrypted_percent_encoded_url_payload: tail of the url
+/// - `bytes`:                               the POSTed json data with the passphrase
+/// - `application_configuration`:           application configuration
+/// - `user`:                                authenticated user calling this function
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn reveal_secret_with_passphrase(
+    encrypted_percent_encoded_url_payload: web::Path<String>,
+    bytes: Bytes,
+    application_configuration: web::Data<ApplicationConfiguration>,
+    user: AuthenticatedUser,
+) -> HttpResponse {
+    debug!(
+        "reveal_secret_with_passphrase(), encrypted_percent_encoded_url_payload {}",
+        &encrypted_percent_encoded_url_payload
+    );
+    if bytes.len() > MAX_FORM_BYTES_LEN {
+        warn!("form data exceeds {} bytes!", MAX_FORM_BYTES_LEN);
+        return HttpResponse::err_text_response(format!(
+            "ERROR: more than {} bytes of data sent",
+            &MAX_FORM_BYTES_LEN
+        ));
+    }
+    let mut passphrase = match serde_json::from_slice(&bytes) as Result<Passphrase, _> {
+        Ok(passphrase) => passphrase,
+        Err(_) => {
+            return HttpResponse::err_text_response("ERROR: could not parse json form data");
+        }
+    };
+    let http_response = reveal(
+        &encrypted_percent_encoded_url_payload,
+        &application_configuration,
+        RevealEntitlement::User(&user),
+        Some(&passphrase.passphrase),
+    )
+    .await;
+    passphrase.passphrase.zeroize();
+    http_response
+}
+
+/// One-time code POSTed by an external receiver, together
+/// with the passphrase of a protected secret
+#[derive(Deserialize)]
+struct OneTimeCode {
+    #[serde(rename = "Code")]
+    code: String,
+    #[serde(rename = "Passphrase", default)]
+    passphrase: Option<String>,
+}
+
+/// Loads a stored secret of an external receiver and decrypts it.
+/// Instead of a login the one-time code sent to the receiver
+/// in a separate mail is needed.
+///
+/// # Arguments
+///
+/// - encrypted_percent_encoded_url_payload: tail of the url
+/// - `bytes`:                               the POSTed json data with the one-time code
+/// - `application_configuration`:           application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+pub async fn reveal_external_secret(
+    encrypted_percent_encoded_url_payload: web::Path<String>,
+    bytes: Bytes,
+    application_configuration: web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    debug!(
+        "reveal_external_secret(), encrypted_percent_encoded_url_payload {}",
+        &encrypted_percent_encoded_url_payload
+    );
+    if bytes.len() > MAX_FORM_BYTES_LEN {
+        warn!("form data exceeds {} bytes!", MAX_FORM_BYTES_LEN);
+        return HttpResponse::err_text_response(format!(
+            "ERROR: more than {} bytes of data sent",
+            &MAX_FORM_BYTES_LEN
+        ));
+    }
+    let mut one_time_code = match serde_json::from_slice(&bytes) as Result<OneTimeCode, _> {
+        Ok(one_time_code) => one_time_code,
+        Err(_) => {
+            return HttpResponse::err_text_response("ERROR: could not parse json form data");
+        }
+    };
+    let http_response = reveal(
+        &encrypted_percent_encoded_url_payload,
+        &application_configuration,
+        RevealEntitlement::Code(&one_time_code.code),
+        one_time_code.passphrase.as_deref(),
+    )
+    .await;
+    if let Some(passphrase) = one_time_code.passphrase.as_mut() {
+        passphrase.zeroize();
+    }
+    http_response
+}
+
+/// Proves that the caller may reveal a secret
+enum RevealEntitlement<'a> {
+    /// a user of the directory that has logged in
+    User(&'a AuthenticatedUser),
+    /// the one-time code of an external receiver
+    Code(&'a str),
+}
+
+/// Loads a stored secret and decrypts it if the caller is
+/// entitled to reveal it.
+///
+/// # Arguments
+///
+/// - encrypted_percent_encoded_url_payload: tail of the url
+/// - `application_configuration`:           application configuration
+/// - `entitlement`:                         login or one-time code of the caller
+/// - `passphrase`:                          optional passphrase entered by the caller
+///
+/// # Returns
+///
+/// - `HttpResponse`
+async fn reveal(
+    encrypted_percent_encoded_url_payload: &str,
+    application_configuration: &web::Data<ApplicationConfiguration>,
+    entitlement: RevealEntitlement<'_>,
+    passphrase: Option<&str>,
+) -> HttpResponse {
+    let encrypted_url_payload =
+        percent_decode_str(encrypted_percent_encoded_url_payload).decode_utf8_lossy();
+    // links mailed before OAEP padding was introduced are only accepted
+    // from logged in users until `accept_pkcs1_links_until`
+    let accept_pkcs1_links = matches!(entitlement, RevealEntitlement::User(_))
+        && application_configuration
+            .configuration_file
+            .accepts_pkcs1_links();
+    let url_payload = {
+        let rsa_read_lock = application_configuration.master_key.read().unwrap();
+        if accept_pkcs1_links {
+            rsa_read_lock.decrypt_str_with_legacy_fallback(&encrypted_url_payload)
+        } else {
+            rsa_read_lock.decrypt_str(&encrypted_url_payload)
+        }
+    };
+    let url_payload = match url_payload {
+        Err(_) => {
+            return HttpResponse::err_text_response(INVALID_LINK_ERROR);
+        }
+        Ok(url_payload) => url_payload,
+    };
+    debug!("url_payload = {}", &url_payload);
+    // get details from the payload
+    let mut split_iter = url_payload.split(';');
+    let uuid = split_iter.next().unwrap_or("uuid");
+    if uuid::Uuid::parse_str(uuid).is_err() {
+        return HttpResponse::err_text_response(INVALID_LINK_ERROR);
+    }
+    let iv_base64 = split_iter.next().unwrap_or("iv");
+    let key_base64 = split_iter.next().unwrap_or("key");
+    // links created before secrets could expire have no expiry timestamp
+    let expires_at = split_iter.next();
+    // links created before multiple receivers were supported have no receiver index
+    let receiver_index = match split_iter.next().map(|i| i.parse::<usize>()).transpose() {
+        Ok(receiver_index) => receiver_index,
+        Err(_) => {
+            return HttpResponse::err_text_response(INVALID_LINK_ERROR);
+        }
+    };
+    // load data from file
+    let path = Path::new(
+        &application_configuration
+            .configuration_file
+            .secret_directory,
     )
     .join(uuid);
     info!("reading secret from file {}", &path.display());
@@ -504,6 +1958,9 @@ pub async fn reveal_secret(
         Ok(encrypted_secret) => encrypted_secret,
         Err(e) => {
             warn!("secret file {} cannot be read: {}", &path.display(), e);
+            if expires_at.map(is_expired).unwrap_or(false) {
+                return HttpResponse::err_text_response("ERROR: secret expired!");
+            }
             return HttpResponse::err_text_response(
                 "ERROR: Secret cannot be read! Already revealed?",
             );
@@ -511,8 +1968,14 @@ pub async fn reveal_secret(
     };
     info!("success, file {} read", &path.display());
     // rsa decrypt the stored values
-    let rsa_read_lock = application_configuration.rsa_keys.read().unwrap();
-    let mut aes_encrypted = match encrypted_secret.to_decrypted(&rsa_read_lock) {
+    // the lock must not be held while the passphrase is checked
+    let mut aes_encrypted = match encrypted_secret.to_decrypted(
+        application_configuration
+            .master_key
+            .read()
+            .unwrap()
+            .as_ref(),
+    ) {
         Err(e) => {
             return HttpResponse::err_text_response(format!("ERROR: {}", &e));
         }
@@ -520,15 +1983,107 @@ pub async fn reveal_secret(
     };
     debug!("aes_encrypted = {}", &aes_encrypted.secret);
 
-    // check if user is entitled to reveal this secret
-    if aes_encrypted.to_email != user.mail {
-        warn!(
-            "user{} (mail = {}) wants to access secret {} (to_email = {})",
-            &user.user_name, &user.mail, &uuid, &aes_encrypted.to_email
-        );
-        return HttpResponse::err_text_response("ERROR: access to secret not permitted!");
+    // every receiver reveals the secret with the own url payload
+    let mut is_external_receiver = false;
+    if let Some(receiver_index) = receiver_index {
+        let receiver = match aes_encrypted.receivers.get(receiver_index) {
+            Some(receiver) => receiver,
+            None => {
+                return HttpResponse::err_text_response("ERROR: invalid receiver of secret!");
+            }
+        };
+        if receiver.revealed {
+            return HttpResponse::err_text_response(
+                "ERROR: Secret cannot be read! Already revealed?",
+            );
+        }
+        is_external_receiver = receiver.code_hash.is_some();
+        aes_encrypted.to_email = receiver.to_email.clone();
+        aes_encrypted.to_display_name = receiver.to_display_name.clone();
+    }
+    // check if the caller is entitled to reveal this secret
+    match (entitlement, receiver_index) {
+        (RevealEntitlement::User(user), _) => {
+            if aes_encrypted.to_email != user.mail || is_external_receiver {
+                warn!(
+                    "user{} (mail = {}) wants to access secret {} (to_email = {})",
+                    &user.user_name, &user.mail, &uuid, &aes_encrypted.to_email
+                );
+                return HttpResponse::err_text_response("ERROR: access to secret not permitted!");
+            }
+        }
+        (RevealEntitlement::Code(code), Some(receiver_index)) if is_external_receiver => {
+            if !aes_encrypted.receivers[receiver_index].code_matches(code) {
+                return reject_failed_attempt(
+                    &path,
+                    Some(receiver_index),
+                    uuid,
+                    &aes_encrypted.to_email,
+                    "code",
+                    application_configuration,
+                );
+            }
+        }
+        (RevealEntitlement::Code(_), _) => {
+            warn!("secret {} has no external receiver", &uuid);
+            return HttpResponse::err_text_response("ERROR: access to secret not permitted!");
+        }
+    }
+    // other receivers are none of the business of this receiver
+    aes_encrypted.receivers.clear();
+    if aes_encrypted.is_expired() {
+        match remove_file(&path) {
+            Err(e) => {
+                warn!(
+                    "expired secret {} cannot be deleted: {}",
+                    &path.display(),
+                    &e
+                );
+            }
+            Ok(_) => {
+                info!("expired secret {} has been removed on reveal", &uuid);
+            }
+        }
+        return HttpResponse::err_text_response("ERROR: secret expired!");
     }
-    let decrypted_secret = match aes_encrypted.secret.decrypt_b64_aes(key_base64, iv_base64) {
+    // secrets protected by a passphrase need it to unwrap the aes key
+    let key_base64 = match (&aes_encrypted.passphrase_salt, passphrase) {
+        (None, _) => key_base64.to_string(),
+        (Some(_), None) => {
+            return HttpResponse::ok_json_response(r#"{"PassphraseRequired":true}"#);
+        }
+        (Some(passphrase_salt), Some(passphrase)) => {
+            // deriving the key takes a while, the worker thread must not be blocked
+            let wrapped_key = key_base64.to_string();
+            let mut passphrase = passphrase.to_string();
+            let salt = passphrase_salt.clone();
+            let unwrapped_key = web::block(move || {
+                let unwrapped_key = unwrap_key_with_passphrase(&wrapped_key, &passphrase, &salt)
+                    .map_err(|e| e.to_string());
+                passphrase.zeroize();
+                unwrapped_key
+            })
+            .await;
+            match unwrapped_key {
+                Ok(Ok(key_base64)) => key_base64,
+                Err(e) => {
+                    return HttpResponse::err_text_response(format!("ERROR: {}", &e));
+                }
+                Ok(Err(_)) => {
+                    return reject_failed_attempt(
+                        &path,
+                        receiver_index,
+                        uuid,
+                        &aes_encrypted.to_email,
+                        "passphrase",
+                        application_configuration,
+                    );
+                }
+            }
+        }
+    };
+    aes_encrypted.passphrase_salt = None;
+    let decrypted_secret = match aes_encrypted.decrypt_aes_secret(&key_base64, iv_base64) {
         Ok(decrypted_secret) => decrypted_secret,
         Err(e) => {
             return HttpResponse::err_text_response(format!("ERROR: {}", &e));
@@ -536,6 +2091,12 @@ pub async fn reveal_secret(
     };
     // put the plaintext secret into the struct
     aes_encrypted.secret = decrypted_secret;
+    if let Some(attachment) = aes_encrypted.attachment.as_mut() {
+        if let Err(e) = attachment.decrypt_content(&key_base64) {
+            warn!("cannot decrypt attachment of secret {}: {}", &uuid, &e);
+            return HttpResponse::err_text_response(format!("ERROR: {}", &e));
+        }
+    }
     let json_response = match serde_json::to_string(&aes_encrypted) {
         Err(e) => {
             return HttpResponse::err_text_response(format!("ERROR: {}", &e));
@@ -543,19 +2104,105 @@ pub async fn reveal_secret(
         Ok(json_response) => json_response,
     };
     debug!("json_response = {}", &json_response);
-    // remove the secret file before revealing data.
-    match remove_file(&path) {
+    // remove the secret file or mark the receiver as revealed before
+    // revealing data, the file is removed after the last receiver
+    // has revealed the secret.
+    let revealed_result = match receiver_index {
+        Some(receiver_index) => {
+            // prevent concurrent updates of the secret file by other receivers
+            let _shared_secret_write_lock =
+                application_configuration.shared_secret.write().unwrap();
+            mark_receiver_revealed(&path, receiver_index).map(|removed| {
+                if !removed {
+                    info!("secret {} waits for other receivers", &uuid);
+                }
+            })
+        }
+        None => remove_file(&path).map_err(|e| e.into()),
+    };
+    match revealed_result {
         Err(e) => {
             warn!("secret {} cannot be deleted: {}", &path.display(), &e);
             HttpResponse::err_text_response("ERROR: secret cannot be deleted from server")
         }
         Ok(_) => {
-            info!("revealing secret with id {}", &uuid);
+            info!(
+                "revealing secret with id {} to {}",
+                &uuid, &aes_encrypted.to_email
+            );
+            if let Some(reveal_receipt) = &application_configuration
+                .configuration_file
+                .email_configuration
+                .reveal_receipt
+            {
+                if let Err(e) = aes_encrypted.notify_sender(
+                    reveal_receipt,
+                    &application_configuration
+                        .configuration_file
+                        .email_configuration,
+                ) {
+                    warn!("cannot send reveal receipt for secret {}: {}", &uuid, &e);
+                }
+            }
             HttpResponse::ok_json_response(json_response)
         }
     }
 }
 
+/// Counts a wrong one-time code or passphrase of a receiver. After
+/// `MAX_FAILED_ATTEMPTS` the receiver cannot reveal the secret anymore.
+///
+/// # Arguments
+///
+/// - `path`:                      path of the secret file
+/// - `receiver_index`:            index of the receiver from the url payload
+/// - `uuid`:                      id of the secret, used for logging
+/// - `to_email`:                  mail address of the receiver, used for logging
+/// - `what`:                      what was wrong, e.g. `code`
+/// - `application_configuration`: application configuration
+///
+/// # Returns
+///
+/// - `HttpResponse`
+fn reject_failed_attempt(
+    path: &Path,
+    receiver_index: Option<usize>,
+    uuid: &str,
+    to_email: &str,
+    what: &str,
+    application_configuration: &web::Data<ApplicationConfiguration>,
+) -> HttpResponse {
+    warn!(
+        "{} entered a wrong {} for secret {}",
+        &to_email, &what, &uuid
+    );
+    let receiver_index = match receiver_index {
+        Some(receiver_index) => receiver_index,
+        None => {
+            return HttpResponse::err_text_response(format!("ERROR: wrong {}!", &what));
+        }
+    };
+    // prevent concurrent updates of the secret file by other receivers
+    let _shared_secret_write_lock = application_configuration.shared_secret.write().unwrap();
+    match record_failed_attempt(path, receiver_index, MAX_FAILED_ATTEMPTS) {
+        Ok(true) => {
+            warn!(
+                "{} entered too many wrong values for secret {}",
+                &to_email, &uuid
+            );
+            HttpResponse::err_text_response(format!(
+                "ERROR: wrong {}, the secret cannot be revealed anymore!",
+                &what
+            ))
+        }
+        Ok(false) => HttpResponse::err_text_response(format!("ERROR: wrong {}!", &what)),
+        Err(e) => {
+            warn!("cannot count wrong {} for secret {}: {}", &what, &uuid, &e);
+            HttpResponse::err_text_response(format!("ERROR: wrong {}!", &what))
+        }
+    }
+}
+
 /// Details about the authenticated user
 #[derive(Serialize, Deserialize)]
 struct UserDetails {
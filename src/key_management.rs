@@ -0,0 +1,150 @@
+use crate::configuration::ConfigurationFile;
+use crate::rsa_functions::{key_id, MIN_RSA_MODULUS_SIZE};
+use crate::unsecure_string::SecureStringToUnsecureString;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::symm::Cipher;
+use secstr::SecStr;
+use std::error::Error;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+use zeroize::Zeroize;
+
+/// min length of the password for the private key, also checked by the sysop page
+pub const MIN_PASSPHRASE_LENGTH: usize = 14;
+/// plaintext to check that the unlocked master key can decrypt its own data
+const VERIFY_PLAINTEXT: &str = "lmtyas verify-config";
+
+/// Writes a file that is only readable by its owner, an existing
+/// file is not overwritten.
+fn write_private_file<P: AsRef<Path>>(path: P, data: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut open_options = OpenOptions::new();
+    open_options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open_options.mode(0o600);
+    }
+    let mut file = open_options
+        .open(&path)
+        .map_err(|e| format!("cannot create {}: {}", path.as_ref().display(), e))?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+/// Encrypts the private key as PKCS#8 PEM with the passphrase.
+fn private_key_to_encrypted_pem(
+    private_key: &PKey<openssl::pkey::Private>,
+    secure_passphrase: &SecStr,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if secure_passphrase.unsecure().len() < MIN_PASSPHRASE_LENGTH {
+        return Err(format!(
+            "passphrase must be at least {} characters long",
+            MIN_PASSPHRASE_LENGTH
+        )
+        .into());
+    }
+    let mut unsecure_passphrase = secure_passphrase.to_unsecure_string();
+    let encrypted_pem = private_key
+        .private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), unsecure_passphrase.as_bytes());
+    unsecure_passphrase.zeroize();
+    Ok(encrypted_pem?)
+}
+
+/// Generates a new RSA key pair for the web service and writes the
+/// passphrase protected private key and the public key as PEM files.
+///
+/// # Arguments
+///
+/// - `rsa_private_key_path`: must not exist yet
+/// - `rsa_public_key_path`:  must not exist yet
+/// - `rsa_key_bits`:         at least `MIN_RSA_MODULUS_SIZE` * 8 bits
+/// - `secure_passphrase`:    at least `MIN_PASSPHRASE_LENGTH` characters
+///
+/// # Returns
+///
+/// - `Result<String, Box<dyn Error>>`: id of the new key
+pub fn generate_rsa_key_files<P: AsRef<Path>>(
+    rsa_private_key_path: P,
+    rsa_public_key_path: P,
+    rsa_key_bits: u32,
+    secure_passphrase: &SecStr,
+) -> Result<String, Box<dyn Error>> {
+    if rsa_key_bits < MIN_RSA_MODULUS_SIZE * 8 {
+        return Err(format!(
+            "rsa keys must have at least {} bits",
+            MIN_RSA_MODULUS_SIZE * 8
+        )
+        .into());
+    }
+    for path in [&rsa_private_key_path, &rsa_public_key_path] {
+        if path.as_ref().exists() {
+            return Err(format!("{} already exists", path.as_ref().display()).into());
+        }
+    }
+    let rsa_private_key = Rsa::generate(rsa_key_bits)?;
+    let rsa_key_id = key_id(&rsa_private_key)?;
+    let rsa_public_key_pem = rsa_private_key.public_key_to_pem()?;
+    let private_key = PKey::from_rsa(rsa_private_key)?;
+    let rsa_private_key_pem = private_key_to_encrypted_pem(&private_key, secure_passphrase)?;
+    write_private_file(&rsa_private_key_path, &rsa_private_key_pem)?;
+    std::fs::write(&rsa_public_key_path, rsa_public_key_pem)?;
+    Ok(rsa_key_id)
+}
+
+/// Encrypts the private key file with a new passphrase. The file is
+/// replaced only after the new PEM has been written completely.
+///
+/// # Arguments
+///
+/// - `private_key_path`:      RSA or EC private key
+/// - `old_secure_passphrase`: current passphrase
+/// - `new_secure_passphrase`: at least `MIN_PASSPHRASE_LENGTH` characters
+///
+/// # Returns
+///
+/// - `Result<(), Box<dyn Error>>`
+pub fn change_private_key_passphrase<P: AsRef<Path>>(
+    private_key_path: P,
+    old_secure_passphrase: &SecStr,
+    new_secure_passphrase: &SecStr,
+) -> Result<(), Box<dyn Error>> {
+    let private_key_file = std::fs::read(&private_key_path)?;
+    let mut unsecure_passphrase = old_secure_passphrase.to_unsecure_string();
+    let private_key =
+        PKey::private_key_from_pem_passphrase(&private_key_file, unsecure_passphrase.as_bytes());
+    unsecure_passphrase.zeroize();
+    let private_key = private_key.map_err(|_| "cannot decrypt private key with old passphrase")?;
+    let private_key_pem = private_key_to_encrypted_pem(&private_key, new_secure_passphrase)?;
+    let mut new_private_key_path = private_key_path.as_ref().as_os_str().to_owned();
+    new_private_key_path.push(".new");
+    write_private_file(&new_private_key_path, &private_key_pem)?;
+    std::fs::rename(&new_private_key_path, &private_key_path)?;
+    Ok(())
+}
+
+/// Unlocks the master key of the configuration and checks
+/// that it decrypts the data it encrypted.
+///
+/// # Arguments
+///
+/// - `configuration_file`:       the loaded configuration
+/// - `rsa_private_key_password`: password of the private key or pin of the token
+///
+/// # Returns
+///
+/// - `Result<String, Box<dyn Error>>`: id of the primary key
+pub fn verify_master_key(
+    configuration_file: &ConfigurationFile,
+    rsa_private_key_password: &SecStr,
+) -> Result<String, Box<dyn Error>> {
+    let master_key = configuration_file.read_master_key(rsa_private_key_password)?;
+    let encrypted = master_key.encrypt_str(VERIFY_PLAINTEXT)?;
+    if master_key.decrypt_str(&encrypted)? != VERIFY_PLAINTEXT {
+        return Err("master key cannot decrypt its own data".into());
+    }
+    master_key
+        .key_id()
+        .ok_or_else(|| "master key has no key id".into())
+}
@@ -7,6 +7,7 @@ use ldap3::{ldap_escape, LdapConnAsync, Scope, SearchEntry};
 use log::debug;
 use secstr::SecStr;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::error::Error;
 use zeroize::Zeroize;
 
@@ -20,6 +21,8 @@ pub struct LdapCommonConfiguration {
     pub bind_dn: String,
     pub user_filter: String,
     pub mail_filter: String,
+    pub language_attribute: Option<String>,
+    pub group_filter: Option<String>,
     #[cfg(feature = "ldap-auth")]
     pub authentication: LdapAuthConfiguration,
 }
@@ -40,6 +43,33 @@ impl LdapCommonConfiguration {
         filter: &str,
         attributes: Vec<S>,
     ) -> Result<String, Box<dyn Error>> {
+        let mut result = String::new();
+        for attrs in self.ldap_search_entries(filter, attributes).await? {
+            // build a string containing the whole result not unlike json.
+            // Not 100% happy with this solution but for now it seems the
+            // most generic approach.
+            String::push_str(&mut result, &format!("{:?}", attrs));
+        }
+        debug!("result = {}", &result);
+        Ok(result)
+    }
+
+    /// Performs a generic ldap search and returns the attributes
+    /// of every found entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`:         filter expression to use for the search-
+    /// * `attributes`:     a vector of attributes that should be delivered as search result.
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Vec<HashMap<String, Vec<String>>>, Box<dyn Error>>` - either the attributes of the found entries or an error
+    async fn ldap_search_entries<S: AsRef<str> + std::marker::Sync + std::marker::Send>(
+        &self,
+        filter: &str,
+        attributes: Vec<S>,
+    ) -> Result<Vec<HashMap<String, Vec<String>>>, Box<dyn Error>> {
         let (conn, mut ldap) = LdapConnAsync::new(&self.url).await?;
         ldap3::drive!(conn);
         debug!("Connected to {}", &&self.url);
@@ -57,16 +87,11 @@ impl LdapCommonConfiguration {
             .search(&self.base_ou, Scope::Subtree, filter, attributes)
             .await?
             .success()?;
-        let mut result = String::new();
-        for entry in rs {
-            let search_entry = SearchEntry::construct(entry);
-            // build a string containing the whole result not unlike json.
-            // Not 100% happy with this solution but for now it seems the
-            // most generic approach.
-            String::push_str(&mut result, &format!("{:?}", search_entry.attrs));
-        }
+        let result = rs
+            .into_iter()
+            .map(|entry| SearchEntry::construct(entry).attrs)
+            .collect();
         ldap.unbind().await?;
-        debug!("result = {}", &result);
         debug!("ldap.unbind() -> OK");
         Ok(result)
     }
@@ -98,7 +123,7 @@ impl LdapCommonConfiguration {
     }
 
     /// Search uid in Ldap for basic user information attributes, such as
-    /// uid, cn, givenName, sn
+    /// uid, cn, givenName, sn and the optional `language_attribute`
     ///
     /// # Arguments
     ///
@@ -119,8 +144,41 @@ impl LdapCommonConfiguration {
             None => &self.mail_filter,
         };
         let filterstring = &ldap_filter.replace("{0}", &ldap_escape(mail));
-        self.ldap_search(filterstring, vec!["uid", "givenName", "sn", "mail"])
-            .await
+        let mut attributes = vec!["uid", "givenName", "sn", "mail"];
+        if let Some(language_attribute) = &self.language_attribute {
+            attributes.push(language_attribute);
+        }
+        self.ldap_search(filterstring, attributes).await
+    }
+
+    /// Search the members of a group in Ldap with help of the `group_filter`
+    ///
+    /// # Arguments
+    ///
+    /// - `group`: name of the group, replaces `{0}` in `group_filter`
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Vec<String>, Box<dyn Error>>` - either the mail addresses of all members or an error
+    pub async fn ldap_search_group_members(
+        &self,
+        group: &str,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let group_filter = match &self.group_filter {
+            Some(group_filter) => group_filter,
+            None => return Err("no group_filter configured".into()),
+        };
+        let filterstring = &group_filter.replace("{0}", &ldap_escape(group));
+        let mut members: Vec<String> = self
+            .ldap_search_entries(filterstring, vec!["mail"])
+            .await?
+            .into_iter()
+            .filter_map(|mut attrs| attrs.remove("mail"))
+            .filter_map(|mails| mails.into_iter().next())
+            .collect();
+        members.sort();
+        members.dedup();
+        Ok(members)
     }
 }
 
@@ -1,6 +1,7 @@
 // tell the rust compiler which modules we have in extra files
 pub mod aes_functions;
 pub mod authenticated_user;
+pub mod authentication_api_token;
 pub mod authentication_functions;
 #[cfg(feature = "ldap-auth")]
 pub mod authentication_ldap;
@@ -12,23 +13,37 @@ pub mod cleanup_timer;
 pub mod cli_parser;
 pub mod configuration;
 pub mod cookie_functions;
+pub mod ec_functions;
 #[cfg(feature = "get-userdata-ldap")]
 pub mod get_userdata_ldap;
 pub mod get_userdata_trait;
 pub mod handler_functions;
 pub mod header_value_trait;
 pub mod http_traits;
+pub mod key_management;
 #[cfg(feature = "ldap-common")]
 pub mod ldap_common;
 pub mod log_functions;
 pub mod login_user_trait;
+#[cfg(feature = "mail-auth-starttls")]
+pub mod mail_auth_starttls;
+#[cfg(feature = "mail-auth-tls")]
+pub mod mail_auth_tls;
 pub mod mail_configuration;
+#[cfg(feature = "mail-file")]
+pub mod mail_file;
 #[cfg(feature = "mail-noauth-notls")]
 pub mod mail_noauth_notls;
+#[cfg(feature = "mail-sendmail")]
+pub mod mail_sendmail;
+pub mod master_key_trait;
 #[cfg(feature = "oidc-ldap")]
 pub mod oidc_ldap;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11_functions;
 pub mod rsa_functions;
 pub mod secret_functions;
+pub mod shamir_functions;
 pub mod unsecure_string;
 
 pub const PROGRAM_NAME: &str = env!("CARGO_PKG_NAME");
@@ -52,6 +67,6 @@ pub mod authentication_url {
 }
 
 #[cfg(feature = "oidc-auth-ldap")]
-pub const TIMER_VEC_CAPACITY: usize = 3;
+pub const TIMER_VEC_CAPACITY: usize = 4;
 #[cfg(not(feature = "oidc-auth-ldap"))]
-pub const TIMER_VEC_CAPACITY: usize = 2;
+pub const TIMER_VEC_CAPACITY: usize = 3;
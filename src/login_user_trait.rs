@@ -1,7 +1,7 @@
 extern crate env_logger;
 use crate::configuration::ApplicationConfiguration;
 use crate::http_traits::CustomHttpResponse;
-pub use crate::mail_noauth_notls::SendEMail;
+pub use crate::mail_configuration::SendEMail;
 use actix_web::web::Bytes;
 use actix_web::{web, HttpResponse, HttpRequest};
 use async_trait::async_trait;
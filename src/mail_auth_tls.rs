@@ -0,0 +1,35 @@
+pub use crate::mail_configuration::{SendEMail, SendEMailConfiguration};
+use lettre::transport::smtp::client::Tls;
+use lettre::{SmtpTransport, Transport};
+use std::error::Error;
+
+impl SendEMail for SendEMailConfiguration {
+    /// The `SendEMailConfiguration` trait is a contract
+    /// for implementing different flavors of `send_multipart_mail()`.
+    /// This implementation sends credentials over an implicit TLS connection
+    /// (e.g. port 465).
+    ///
+    /// # Arguments
+    ///
+    /// - `mail_to`:          mail address of the receiver of the secret.
+    /// - `mail_subject`:     subject of the mail
+    /// - `mail_body`:        body of the mail
+    /// - `mail_body_html`:   optional html body of the mail
+    fn send_multipart_mail(
+        &self,
+        mail_to: &str,
+        mail_subject: &str,
+        mail_body: &str,
+        mail_body_html: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let email = self.build_message(mail_to, mail_subject, mail_body, mail_body_html)?;
+
+        let smtp_transport = SmtpTransport::builder_dangerous(&self.mail_server_address)
+            .port(self.mail_server_port)
+            .tls(Tls::Wrapper(self.build_tls_parameters()?))
+            .credentials(self.build_credentials()?)
+            .build();
+        smtp_transport.send(&email)?;
+        Ok(())
+    }
+}
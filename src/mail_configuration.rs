@@ -1,4 +1,12 @@
+use crate::PROGRAM_NAME;
+#[cfg(any(feature = "mail-auth-starttls", feature = "mail-auth-tls"))]
+use lettre::transport::smtp::{
+    authentication::Credentials,
+    client::{Certificate, TlsParameters},
+};
+use lettre::{message::header::ContentType, message::Mailbox, message::MultiPart, Message};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
 use std::path::Path;
@@ -13,7 +21,18 @@ pub struct SendEMailConfiguration {
     pub mail_from: String,
     pub mail_subject: String,
     pub mail_template_file: Box<Path>,
+    pub mail_template_html_file: Option<Box<Path>>,
     pub mail_credentails: Option<EMailCredentials>,
+    pub mail_ca_certificate_files: Option<Vec<Box<Path>>>,
+    pub mail_sendmail_command: Option<String>,
+    pub mail_file_directory: Option<Box<Path>>,
+    pub localized_mail_templates: Option<HashMap<String, MailTemplateConfiguration>>,
+    pub fallback_language: Option<String>,
+    pub reveal_receipt: Option<MailTemplateConfiguration>,
+    pub expiry_notice: Option<MailTemplateConfiguration>,
+    pub secret_request: Option<MailTemplateConfiguration>,
+    pub external_receiver_link: Option<MailTemplateConfiguration>,
+    pub external_receiver_code: Option<MailTemplateConfiguration>,
 }
 
 impl SendEMailConfiguration {
@@ -22,6 +41,204 @@ impl SendEMailConfiguration {
         let mail_body_template = fs::read_to_string(&self.mail_template_file)?;
         Ok(mail_body_template)
     }
+
+    /// loads the optional html mail template file
+    pub fn load_mail_template_html(&self) -> Result<Option<String>, Box<dyn Error>> {
+        load_optional_mail_template(&self.mail_template_html_file)
+    }
+
+    /// Selects subject and templates of the mail to the receiver of a
+    /// secret. The preferred language of the receiver is looked up in
+    /// `localized_mail_templates`, e.g. `de-DE` matches `de-DE` or `de`.
+    /// If there is no match, the `fallback_language` is tried before
+    /// `mail_subject` and `mail_template_file` are used.
+    ///
+    /// # Arguments
+    ///
+    /// - `language`: optional preferred language of the receiver
+    ///
+    /// # Returns
+    ///
+    /// - `MailTemplateConfiguration`
+    pub fn mail_template_for_language(&self, language: Option<&str>) -> MailTemplateConfiguration {
+        if let Some(localized_mail_templates) = &self.localized_mail_templates {
+            let mut candidates = language.map(language_candidates).unwrap_or_default();
+            if let Some(fallback_language) = &self.fallback_language {
+                candidates.push(fallback_language.clone());
+            }
+            for candidate in candidates {
+                if let Some((_, mail_template)) = localized_mail_templates
+                    .iter()
+                    .find(|(key, _)| key.eq_ignore_ascii_case(&candidate))
+                {
+                    return mail_template.clone();
+                }
+            }
+        }
+        MailTemplateConfiguration {
+            mail_subject: self.mail_subject.clone(),
+            mail_template_file: self.mail_template_file.clone(),
+            mail_template_html_file: self.mail_template_html_file.clone(),
+        }
+    }
+
+    /// Builds the mail message that is handed over to the
+    /// transport of the selected mail feature.
+    ///
+    /// # Arguments
+    ///
+    /// - `mail_to`:          mail address of the receiver of the secret.
+    /// - `mail_subject`:     subject of the mail
+    /// - `mail_body`:        body of the mail
+    /// - `mail_body_html`:   optional html body, sent as `multipart/alternative`
+    pub fn build_message(
+        &self,
+        mail_to: &str,
+        mail_subject: &str,
+        mail_body: &str,
+        mail_body_html: Option<&str>,
+    ) -> Result<Message, Box<dyn Error>> {
+        let parsed_mail_from = Mailbox::parse_with_context_on_error(
+            &self.mail_from,
+            ParseMailAddressErrorContext::FromAddress,
+        )?;
+        let parsed_mail_to =
+            Mailbox::parse_with_context_on_error(mail_to, ParseMailAddressErrorContext::ToAddress)?;
+        let message_builder = Message::builder()
+            .from(parsed_mail_from)
+            .to(parsed_mail_to)
+            .subject(mail_subject)
+            .user_agent(PROGRAM_NAME.to_string());
+        let message = match mail_body_html {
+            Some(mail_body_html) => message_builder.multipart(MultiPart::alternative_plain_html(
+                String::from(mail_body),
+                String::from(mail_body_html),
+            )),
+            None => message_builder
+                .header(ContentType::TEXT_PLAIN)
+                .body(String::from(mail_body)),
+        };
+        match message {
+            Ok(m) => Ok(m),
+            Err(e) => Err(Box::<dyn Error + Send + Sync>::from(format!(
+                "Error building email message: {}",
+                e,
+            ))),
+        }
+    }
+
+    /// Builds the credentials to authenticate at the mail server.
+    /// The credentials are mandatory for authenticated mail features.
+    #[cfg(any(feature = "mail-auth-starttls", feature = "mail-auth-tls"))]
+    pub fn build_credentials(&self) -> Result<Credentials, Box<dyn Error>> {
+        match &self.mail_credentails {
+            Some(mail_credentials) => Ok(Credentials::new(
+                mail_credentials.mail_user.clone(),
+                mail_credentials.mail_password.clone(),
+            )),
+            None => Err(Box::<dyn Error + Send + Sync>::from(
+                "mail_credentails are missing in the email_configuration",
+            )),
+        }
+    }
+
+    /// Builds the TLS parameters to connect to the mail server.
+    /// Custom CA certificates (pem format) are added to the
+    /// trusted root certificates of the system.
+    #[cfg(any(feature = "mail-auth-starttls", feature = "mail-auth-tls"))]
+    pub fn build_tls_parameters(&self) -> Result<TlsParameters, Box<dyn Error>> {
+        let mut tls_parameters_builder = TlsParameters::builder(self.mail_server_address.clone());
+        if let Some(ca_certificate_files) = &self.mail_ca_certificate_files {
+            for ca_certificate_file in ca_certificate_files {
+                let pem = fs::read(ca_certificate_file)?;
+                tls_parameters_builder =
+                    tls_parameters_builder.add_root_certificate(Certificate::from_pem(&pem)?);
+            }
+        }
+        Ok(tls_parameters_builder.build()?)
+    }
+}
+
+/// Holds subject and templates of a mail, e.g.
+/// a localized mail to the receiver of a secret
+/// or a notification of the sender.
+#[derive(Clone, Deserialize, Debug)]
+pub struct MailTemplateConfiguration {
+    pub mail_subject: String,
+    pub mail_template_file: Box<Path>,
+    pub mail_template_html_file: Option<Box<Path>>,
+}
+
+impl MailTemplateConfiguration {
+    /// loads the mail template file
+    pub fn load_mail_template(&self) -> Result<String, Box<dyn Error>> {
+        let mail_body_template = fs::read_to_string(&self.mail_template_file)?;
+        Ok(mail_body_template)
+    }
+
+    /// loads the optional html mail template file
+    pub fn load_mail_template_html(&self) -> Result<Option<String>, Box<dyn Error>> {
+        load_optional_mail_template(&self.mail_template_html_file)
+    }
+}
+
+/// Builds the languages to look up from a language attribute,
+/// e.g. `de_DE, en;q=0.8` results in `de-DE` and `de`.
+fn language_candidates(language: &str) -> Vec<String> {
+    let mut candidates = Vec::new();
+    let preferred_language = language
+        .split([',', ';'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .replace('_', "-");
+    if preferred_language.is_empty() {
+        return candidates;
+    }
+    if let Some((primary_language, _)) = preferred_language.split_once('-') {
+        let primary_language = primary_language.to_string();
+        candidates.push(preferred_language);
+        candidates.push(primary_language);
+    } else {
+        candidates.push(preferred_language);
+    }
+    candidates
+}
+
+/// loads an optional mail template file
+fn load_optional_mail_template(
+    mail_template_file: &Option<Box<Path>>,
+) -> Result<Option<String>, Box<dyn Error>> {
+    match mail_template_file {
+        Some(mail_template_file) => Ok(Some(fs::read_to_string(mail_template_file)?)),
+        None => Ok(None),
+    }
+}
+
+/// Escapes the characters `&`, `<`, `>`, `"` and `'`, so that
+/// user input can be put into a html mail template without
+/// injecting markup.
+///
+/// # Arguments
+///
+/// - `text`: text that should be escaped
+///
+/// # Returns
+///
+/// - `String`
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 /// Holds optional credentials tp
@@ -78,16 +295,40 @@ impl ParseMailboxWithContext for lettre::message::Mailbox{
 }
 
 /// This trait is a contract for implementing different
-/// flavors of `send_mail()`.
+/// flavors of `send_multipart_mail()`.
 /// The default implementation does not send credentials
 /// and does not use TLS.
 pub trait SendEMail {
-    /// sends an email to the receiver of a secret
+    /// sends a plain text email to the receiver of a secret
+    ///
+    /// # Arguments
+    ///
+    /// - `mail_to`:          mail address of the receiver of the secret.
+    /// - `mail_subject`:     subject of the mail
+    /// - `mail_body`:        body of the mail
+    fn send_mail(
+        &self,
+        mail_to: &str,
+        mail_subject: &str,
+        mail_body: &str,
+    ) -> Result<(), Box<dyn Error>> {
+        self.send_multipart_mail(mail_to, mail_subject, mail_body, None)
+    }
+
+    /// sends an email to the receiver of a secret, the optional
+    /// html body is sent as alternative to the plain text body.
     ///
     /// # Arguments
     ///
     /// - `mail_to`:          mail address of the receiver of the secret.
     /// - `mail_subject`:     subject of the mail
     /// - `mail_body`:        body of the mail
-    fn send_mail(&self, mail_to: &str, mail_subject: &str, mail_body: &str) -> Result<(), Box<dyn Error>>;
+    /// - `mail_body_html`:   optional html body of the mail
+    fn send_multipart_mail(
+        &self,
+        mail_to: &str,
+        mail_subject: &str,
+        mail_body: &str,
+        mail_body_html: Option<&str>,
+    ) -> Result<(), Box<dyn Error>>;
 }
@@ -0,0 +1,41 @@
+pub use crate::mail_configuration::{SendEMail, SendEMailConfiguration};
+use lettre::{FileTransport, Transport};
+use std::error::Error;
+use std::fs::create_dir_all;
+
+impl SendEMail for SendEMailConfiguration {
+    /// The `SendEMailConfiguration` trait is a contract
+    /// for implementing different flavors of `send_multipart_mail()`.
+    /// This implementation does not send the mail at all but writes
+    /// it into `mail_file_directory`, one `<message id>.eml` file per mail.
+    /// Meant for test and staging systems.
+    ///
+    /// # Arguments
+    ///
+    /// - `mail_to`:          mail address of the receiver of the secret.
+    /// - `mail_subject`:     subject of the mail
+    /// - `mail_body`:        body of the mail
+    /// - `mail_body_html`:   optional html body of the mail
+    fn send_multipart_mail(
+        &self,
+        mail_to: &str,
+        mail_subject: &str,
+        mail_body: &str,
+        mail_body_html: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let email = self.build_message(mail_to, mail_subject, mail_body, mail_body_html)?;
+
+        let mail_file_directory = match &self.mail_file_directory {
+            Some(mail_file_directory) => mail_file_directory,
+            None => {
+                return Err(Box::<dyn Error + Send + Sync>::from(
+                    "mail_file_directory is missing in the email_configuration",
+                ));
+            }
+        };
+        create_dir_all(mail_file_directory)?;
+        let file_transport = FileTransport::new(mail_file_directory);
+        file_transport.send(&email)?;
+        Ok(())
+    }
+}
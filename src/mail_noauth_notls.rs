@@ -1,13 +1,12 @@
 pub use crate::mail_configuration::{
     ParseMailAddressErrorContext, ParseMailboxWithContext, SendEMail, SendEMailConfiguration,
 };
-use crate::PROGRAM_NAME;
-use lettre::{message::header::ContentType, message::Mailbox, Message, SmtpTransport, Transport};
+use lettre::{SmtpTransport, Transport};
 use std::error::Error;
 
 impl SendEMail for SendEMailConfiguration {
     /// The `SendEMailConfiguration` trait is a contract
-    /// for implementing different flavors of `send_mail()`.
+    /// for implementing different flavors of `send_multipart_mail()`.
     /// This is the default implementation (as in default feature)
     /// that does not send credentials and does not use TLS.
     ///
@@ -16,34 +15,15 @@ impl SendEMail for SendEMailConfiguration {
     /// - `mail_to`:          mail address of the receiver of the secret.
     /// - `mail_subject`:     subject of the mail
     /// - `mail_body`:        body of the mail
-    fn send_mail(
+    /// - `mail_body_html`:   optional html body of the mail
+    fn send_multipart_mail(
         &self,
         mail_to: &str,
         mail_subject: &str,
         mail_body: &str,
+        mail_body_html: Option<&str>,
     ) -> Result<(), Box<dyn Error>> {
-        let parsed_mail_from = Mailbox::parse_with_context_on_error(
-            &self.mail_from,
-            ParseMailAddressErrorContext::FromAddress,
-        )?;
-        let parsed_mail_to =
-            Mailbox::parse_with_context_on_error(mail_to, ParseMailAddressErrorContext::ToAddress)?;
-        let email = match Message::builder()
-            .from(parsed_mail_from)
-            .to(parsed_mail_to)
-            .subject(mail_subject)
-            .header(ContentType::TEXT_PLAIN)
-            .user_agent(PROGRAM_NAME.to_string())
-            .body(String::from(mail_body))
-        {
-            Ok(m) => m,
-            Err(e) => {
-                return Err(Box::<dyn Error + Send + Sync>::from(format!(
-                    "Error building email message: {}",
-                    e,
-                )));
-            }
-        };
+        let email = self.build_message(mail_to, mail_subject, mail_body, mail_body_html)?;
 
         let tp = SmtpTransport::builder_dangerous(&self.mail_server_address);
         let tp_with_port = tp.port(self.mail_server_port);
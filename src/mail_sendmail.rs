@@ -0,0 +1,34 @@
+pub use crate::mail_configuration::{SendEMail, SendEMailConfiguration};
+use lettre::{SendmailTransport, Transport};
+use std::error::Error;
+
+impl SendEMail for SendEMailConfiguration {
+    /// The `SendEMailConfiguration` trait is a contract
+    /// for implementing different flavors of `send_multipart_mail()`.
+    /// This implementation pipes the mail to a local `sendmail`
+    /// binary, `mail_sendmail_command` may point to another binary
+    /// than the one found in the search path.
+    ///
+    /// # Arguments
+    ///
+    /// - `mail_to`:          mail address of the receiver of the secret.
+    /// - `mail_subject`:     subject of the mail
+    /// - `mail_body`:        body of the mail
+    /// - `mail_body_html`:   optional html body of the mail
+    fn send_multipart_mail(
+        &self,
+        mail_to: &str,
+        mail_subject: &str,
+        mail_body: &str,
+        mail_body_html: Option<&str>,
+    ) -> Result<(), Box<dyn Error>> {
+        let email = self.build_message(mail_to, mail_subject, mail_body, mail_body_html)?;
+
+        let sendmail_transport = match &self.mail_sendmail_command {
+            Some(command) => SendmailTransport::new_with_command(command),
+            None => SendmailTransport::new(),
+        };
+        sendmail_transport.send(&email)?;
+        Ok(())
+    }
+}
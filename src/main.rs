@@ -1,5 +1,7 @@
 use actix_files::Files;
 use actix_web::{guard, middleware, web, App, HttpResponse, HttpServer};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use lmtyas::authentication_api_token::validate_api_token;
 #[cfg(feature = "ldap-auth")]
 use lmtyas::authentication_ldap::LdapCommonConfiguration;
 use lmtyas::authentication_middleware::CheckAuthentication;
@@ -7,14 +9,24 @@ use lmtyas::authentication_middleware::CheckAuthentication;
 use lmtyas::authentication_oidc::OidcConfiguration;
 use lmtyas::authentication_url;
 use lmtyas::cleanup_timer::build_cleaup_timers;
-use lmtyas::cli_parser::{parse_cli_parameters, ARG_CONFIG_FILE};
-use lmtyas::configuration::ApplicationConfiguration;
+use lmtyas::cli_parser::{
+    parse_cli_parameters, ARG_CONFIG_FILE, ARG_KEY_BITS, ARG_PRIVATE_KEY_FILE, ARG_PUBLIC_KEY_FILE,
+    ARG_SPLIT_RSA_PASSWORD, CMD_CHANGE_PASSPHRASE, CMD_KEYGEN, CMD_VERIFY_CONFIG,
+};
+use lmtyas::configuration::{ApplicationConfiguration, ConfigurationFile};
 use lmtyas::handler_functions::*;
+use lmtyas::key_management::{
+    change_private_key_passphrase, generate_rsa_key_files, verify_master_key,
+};
 use lmtyas::log_functions::extract_request_path;
 use lmtyas::login_user_trait::Login;
-use log::info;
+use lmtyas::rsa_functions::RsaPrivateKeyPassword;
+use lmtyas::shamir_functions::split_secret;
+use log::{info, warn};
+use secstr::SecStr;
 use std::io::Write;
 use std::path::Path;
+use zeroize::Zeroize;
 
 #[cfg(feature = "ldap-auth")]
 type AuthConfiguration = LdapCommonConfiguration;
@@ -34,6 +46,21 @@ async fn main() -> std::io::Result<()> {
 
     // parse cli parameters and load the configuration
     let clap_arg_matches = parse_cli_parameters();
+    if let Some(mut split_arguments) = clap_arg_matches.get_many::<u8>(ARG_SPLIT_RSA_PASSWORD) {
+        let threshold = *split_arguments.next().unwrap();
+        let share_count = *split_arguments.next().unwrap();
+        return split_rsa_password(threshold, share_count);
+    }
+    match clap_arg_matches.subcommand() {
+        Some((CMD_KEYGEN, keygen_arg_matches)) => return keygen(keygen_arg_matches),
+        Some((CMD_CHANGE_PASSPHRASE, change_passphrase_arg_matches)) => {
+            return change_passphrase(change_passphrase_arg_matches)
+        }
+        Some((CMD_VERIFY_CONFIG, verify_config_arg_matches)) => {
+            return verify_config(verify_config_arg_matches)
+        }
+        _ => {}
+    }
     let config_file: String = clap_arg_matches
         .get_one::<String>(ARG_CONFIG_FILE)
         .unwrap()
@@ -46,9 +73,22 @@ async fn main() -> std::io::Result<()> {
         .configuration_file
         .web_bind_address
         .clone();
+    // unlock the rsa private key if a password file or systemd credential exists
+    match application_configuration.load_master_key_with_password_file() {
+        Ok(true) => info!("rsa keys have been loaded from password file"),
+        Ok(false) => info!("waiting for the administrator to enter the rsa password"),
+        Err(e) => warn!("cannot load rsa keys with password file: {}", e),
+    }
     // load ssl keys
     let ssl_acceptor_builder = application_configuration.get_ssl_acceptor_builder();
 
+    // max size of a new secret including an attached file
+    let max_tell_secret_bytes = max_tell_secret_bytes(
+        application_configuration
+            .configuration_file
+            .max_attachment_bytes,
+    );
+
     // build cleanup timers and store references to keep them running
     let _timer_guards = build_cleaup_timers(&application_configuration);
 
@@ -74,7 +114,9 @@ async fn main() -> std::io::Result<()> {
                 middleware::Logger::new("%a %{CUSTOM_REQUEST}xi %s %b %{User-Agent}i %T")
                     // exclude the password from appearing in the log
                     .exclude_regex("/authenticated/sysop/set_password_for_rsa_rivate_key")
+                    .exclude_regex("/authenticated/sysop/add_rsa_password_share")
                     .exclude_regex("/authenticated/secret/reveal")
+                    .exclude_regex("/external/secret/reveal")
                     .custom_request_replace("CUSTOM_REQUEST", |req| {
                         extract_request_path(format!("{} {}", &req.method(), &req.uri()).as_str())
                     }),
@@ -103,9 +145,28 @@ async fn main() -> std::io::Result<()> {
                     .route("/is_server_ready", web::get().to(is_server_ready))
                     .route("/get/login-hint", web::get().to(get_login_hint))
                     .route("/get/mail-hint", web::get().to(get_mail_hint))
-                    .route("/get/imprint-link", web::get().to(get_imprint_link)),
+                    .route("/get/imprint-link", web::get().to(get_imprint_link))
+                    .route(
+                        "/get/secret-valid-for-hours",
+                        web::get().to(get_secret_valid_for_hours),
+                    )
+                    .route(
+                        "/get/max-attachment-bytes",
+                        web::get().to(get_max_attachment_bytes),
+                    ),
             )
             .service(web::resource("/").route(web::get().to(redirect_to_index)))
+            // routes for automation, authenticated with api tokens
+            .service(
+                web::scope("/api/v1")
+                    .wrap(HttpAuthentication::bearer(validate_api_token))
+                    .route("/secret", web::post().to(api_store_secret)),
+            )
+            // route for external receivers, authenticated with a one-time code
+            .service(web::scope("/external").route(
+                "/secret/reveal/{encrypted_percent_encoded_url_payload}",
+                web::post().to(reveal_external_secret),
+            ))
             // routes for authenticated administrators only
             .service(
                 web::scope("authenticated/sysop")
@@ -114,9 +175,20 @@ async fn main() -> std::io::Result<()> {
                         "/set_password_for_rsa_rivate_key/{password}",
                         web::post().to(set_password_for_rsa_rivate_key),
                     )
+                    .route(
+                        "/add_rsa_password_share/{share}",
+                        web::post().to(add_rsa_password_share),
+                    )
+                    .route(
+                        "/get/rsa_password_shares",
+                        web::get().to(get_rsa_password_shares),
+                    )
                     // serve files to admins only
                     // for just two files dedicated functions are fine
                     // with more to come a more generic approach must be used
+                    .route("/reload_rsa_keys", web::post().to(reload_rsa_keys))
+                    .route("/seal", web::post().to(seal_server))
+                    .route("/bulk_send_secrets", web::post().to(bulk_store_secrets))
                     .route("/sysop.html", web::get().to(get_sysop_html))
                     .route("/js/sysop.js", web::get().to(get_sysop_js)),
             )
@@ -124,10 +196,24 @@ async fn main() -> std::io::Result<()> {
             .service(
                 web::scope("authenticated/secret")
                     .wrap(CheckAuthentication)
-                    .route("/tell", web::post().to(store_secret))
+                    // secrets may come with an attached file
+                    .service(
+                        web::resource("/tell")
+                            .app_data(web::PayloadConfig::new(max_tell_secret_bytes))
+                            .route(web::post().to(store_secret)),
+                    )
+                    .route("/request", web::post().to(request_secret))
+                    .route(
+                        "/request/{encrypted_percent_encoded_url_payload}",
+                        web::get().to(get_secret_request),
+                    )
                     .route(
                         "/reveal/{encrypted_percent_encoded_url_payload}",
                         web::get().to(reveal_secret),
+                    )
+                    .route(
+                        "/reveal/{encrypted_percent_encoded_url_payload}",
+                        web::post().to(reveal_secret_with_passphrase),
                     ),
             )
             .service(
@@ -197,3 +283,100 @@ async fn main() -> std::io::Result<()> {
     .run()
     .await
 }
+
+/// Converts the errors of the library for the return value of `main()`.
+fn to_io_error(e: Box<dyn std::error::Error>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string())
+}
+
+/// Reads one line from stdin without the line break, e.g. a passphrase.
+/// The prompt is written to stderr, so that the subcommands can be
+/// used in scripts.
+fn read_secure_line_from_stdin(prompt: &str) -> std::io::Result<SecStr> {
+    eprint!("{}: ", prompt);
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    let secure_line = SecStr::from(line.trim_end_matches(['\r', '\n']));
+    line.zeroize();
+    Ok(secure_line)
+}
+
+/// Reads a new passphrase and its confirmation from stdin.
+fn read_new_passphrase_from_stdin() -> std::io::Result<SecStr> {
+    let passphrase = read_secure_line_from_stdin("new passphrase")?;
+    if passphrase != read_secure_line_from_stdin("repeat new passphrase")? {
+        return Err(to_io_error("passphrases do not match".into()));
+    }
+    Ok(passphrase)
+}
+
+/// Reads the password for the rsa private key from stdin and prints
+/// one share per line. Every administrator gets one of the shares.
+fn split_rsa_password(threshold: u8, share_count: u8) -> std::io::Result<()> {
+    let rsa_password = read_secure_line_from_stdin("password for rsa private key")?;
+    let shares =
+        split_secret(rsa_password.unsecure(), threshold, share_count).map_err(to_io_error)?;
+    for share in shares {
+        println!("{}", share);
+    }
+    Ok(())
+}
+
+/// Creates a new rsa key pair, see subcommand `keygen`.
+fn keygen(arg_matches: &clap::ArgMatches) -> std::io::Result<()> {
+    let passphrase = read_new_passphrase_from_stdin()?;
+    let rsa_key_id = generate_rsa_key_files(
+        arg_matches.get_one::<String>(ARG_PRIVATE_KEY_FILE).unwrap(),
+        arg_matches.get_one::<String>(ARG_PUBLIC_KEY_FILE).unwrap(),
+        *arg_matches.get_one::<u32>(ARG_KEY_BITS).unwrap(),
+        &passphrase,
+    )
+    .map_err(to_io_error)?;
+    println!("created rsa key pair with key id {}", rsa_key_id);
+    Ok(())
+}
+
+/// Encrypts the private key with a new passphrase, see subcommand `change-passphrase`.
+fn change_passphrase(arg_matches: &clap::ArgMatches) -> std::io::Result<()> {
+    let private_key_file = arg_matches.get_one::<String>(ARG_PRIVATE_KEY_FILE).unwrap();
+    let old_passphrase = read_secure_line_from_stdin("old passphrase")?;
+    let new_passphrase = read_new_passphrase_from_stdin()?;
+    change_private_key_passphrase(private_key_file, &old_passphrase, &new_passphrase)
+        .map_err(to_io_error)?;
+    println!("passphrase of {} has been changed", private_key_file);
+    Ok(())
+}
+
+/// Checks the configuration file and unlocks the keys, see subcommand `verify-config`.
+fn verify_config(arg_matches: &clap::ArgMatches) -> std::io::Result<()> {
+    let config_file = arg_matches.get_one::<String>(ARG_CONFIG_FILE).unwrap();
+    let configuration_file = ConfigurationFile::read_from_file(config_file).map_err(to_io_error)?;
+    println!("{} has been loaded", config_file);
+    let mut problems = configuration_file.verify();
+    let rsa_private_key_password = match configuration_file.get_rsa_private_key_password_file() {
+        Some(rsa_private_key_password_file) => {
+            RsaPrivateKeyPassword::read_from_file(rsa_private_key_password_file)
+                .map_err(to_io_error)?
+                .rsa_private_key_password
+                .unwrap()
+        }
+        None => read_secure_line_from_stdin("password for rsa private key")?,
+    };
+    if rsa_private_key_password.unsecure().is_empty() {
+        println!("no password, keys have not been unlocked");
+    } else {
+        match verify_master_key(&configuration_file, &rsa_private_key_password) {
+            Ok(key_id) => println!("keys have been unlocked, key id {}", key_id),
+            Err(e) => problems.push(format!("cannot unlock keys: {}", e)),
+        }
+    }
+    for problem in &problems {
+        println!("ERROR: {}", problem);
+    }
+    if !problems.is_empty() {
+        return Err(to_io_error(
+            format!("{} problems found in {}", problems.len(), config_file).into(),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use crate::ec_functions::EcKeys;
+use crate::rsa_functions::RsaKeys;
+use openssl::pkey::{Id, PKey};
+use openssl::sha::sha256;
+use secstr::SecStr;
+use std::error::Error;
+use std::path::Path;
+
+/// number of bytes of the public key hash used as key id
+const KEY_ID_LENGTH: usize = 8;
+
+/// This trait hides the algorithm of the master key that encrypts
+/// the stored secrets, the url payload of the links and the cookies.
+/// The implementation is chosen by the type of the configured key.
+pub trait MasterKey: Send + Sync {
+    /// Returns the id of the primary key, it is stored with the
+    /// data it encrypted, see `key_id_from_public_key_der()`.
+    fn key_id(&self) -> Option<String>;
+
+    /// Encrypt a String slice with the primary public key
+    /// and return it as base64 encoded String.
+    ///
+    /// # Arguments
+    ///
+    /// - `plaintext_data`: a String slice with data to encrypt
+    fn encrypt_str(&self, plaintext_data: &str) -> Result<String, Box<dyn Error>>;
+
+    /// Decrypt a base64 encoded String slice with the private key
+    /// that has the given key id.
+    ///
+    /// # Arguments
+    ///
+    /// - `encrypted_data`: a String slice with data to decrypt
+    /// - `key_id`:         id of the key, every key is tried if `None`
+    fn decrypt_str_with_key_id(
+        &self,
+        encrypted_data: &str,
+        key_id: Option<&str>,
+    ) -> Result<String, Box<dyn Error>>;
+
+    /// Decrypt a base64 encoded String slice with the private keys
+    /// and return it as plaintext String.
+    ///
+    /// # Arguments
+    ///
+    /// - `encrypted_data`: a String slice with data to decrypt
+    fn decrypt_str(&self, encrypted_data: &str) -> Result<String, Box<dyn Error>> {
+        self.decrypt_str_with_key_id(encrypted_data, None)
+    }
+
+    /// Decrypt a base64 encoded String slice that has been encrypted
+    /// by older versions of the web service. Only RSA keys have been
+    /// used back then.
+    ///
+    /// # Arguments
+    ///
+    /// - `encrypted_data`: a String slice with data to decrypt
+    fn decrypt_str_legacy(&self, _encrypted_data: &str) -> Result<String, Box<dyn Error>> {
+        Err("legacy data can only be decrypted with rsa keys".into())
+    }
+
+    /// Decrypt a base64 encoded String slice that may have been encrypted
+    /// by older versions of the web service, e.g. links in mails that have
+    /// been sent before OAEP padding was introduced.
+    ///
+    /// # Arguments
+    ///
+    /// - `encrypted_data`: a String slice with data to decrypt
+    fn decrypt_str_with_legacy_fallback(
+        &self,
+        encrypted_data: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        match self.decrypt_str(encrypted_data) {
+            Ok(decrypted_data) => Ok(decrypted_data),
+            Err(_) => self.decrypt_str_legacy(encrypted_data),
+        }
+    }
+}
+
+/// Builds the id of a key from the hash of its public key,
+/// so that it can be stored with the data it encrypted.
+///
+/// # Arguments
+///
+/// - `public_key_der`: DER encoded public key
+///
+/// # Returns
+///
+/// - `String`: hex encoded key id
+pub fn key_id_from_public_key_der(public_key_der: &[u8]) -> String {
+    let public_key_hash = sha256(public_key_der);
+    public_key_hash[..KEY_ID_LENGTH]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Loads the master key and the optional key ring. RSA keys or
+/// elliptic curve keys (NIST curves or X25519) are used depending
+/// on the type of the public key.
+///
+/// # Arguments
+///
+/// - private_key_path:   Path
+/// - public_key_path:    Path
+/// - key_ring_directory: optional directory with older private keys
+/// - secure_passphrase:  SecStr
+///
+/// # Returns
+///
+/// - `Result<Box<dyn MasterKey>, Box<dyn Error>>`
+pub fn read_master_key_from_files<P: AsRef<Path>>(
+    private_key_path: P,
+    public_key_path: P,
+    key_ring_directory: Option<P>,
+    secure_passphrase: &SecStr,
+) -> Result<Box<dyn MasterKey>, Box<dyn Error>> {
+    let public_key_file = std::fs::read_to_string(&public_key_path)?;
+    let public_key = PKey::public_key_from_pem(public_key_file.as_bytes())?;
+    match public_key.id() {
+        Id::RSA => {
+            let mut rsa_keys = RsaKeys::new();
+            rsa_keys.read_from_files(private_key_path, public_key_path, secure_passphrase)?;
+            if let Some(key_ring_directory) = key_ring_directory {
+                rsa_keys.read_key_ring_from_directory(key_ring_directory, secure_passphrase)?;
+            }
+            Ok(Box::new(rsa_keys))
+        }
+        Id::EC | Id::X25519 => {
+            let mut ec_keys = EcKeys::new();
+            ec_keys.read_from_files(private_key_path, public_key_path, secure_passphrase)?;
+            if let Some(key_ring_directory) = key_ring_directory {
+                ec_keys.read_key_ring_from_directory(key_ring_directory, secure_passphrase)?;
+            }
+            Ok(Box::new(ec_keys))
+        }
+        _ => Err("unsupported type of master key, use RSA, EC or X25519 keys".into()),
+    }
+}
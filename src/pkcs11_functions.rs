@@ -0,0 +1,250 @@
+use crate::base64_trait::Base64VecU8Conversions;
+use crate::master_key_trait::MasterKey;
+use crate::rsa_functions::{key_id, RsaKeys};
+use crate::unsecure_string::SecureStringToUnsecureString;
+use cryptoki::context::{CInitializeArgs, CInitializeFlags, Pkcs11};
+use cryptoki::error::{Error as CryptokiError, RvError};
+use cryptoki::mechanism::rsa::{PkcsMgfType, PkcsOaepParams, PkcsOaepSource};
+use cryptoki::mechanism::{Mechanism, MechanismType};
+use cryptoki::object::{Attribute, KeyType, ObjectClass, ObjectHandle};
+use cryptoki::session::{Session, UserType};
+use cryptoki::types::AuthPin;
+use log::{debug, info, warn};
+use openssl::pkey::Public;
+use openssl::rsa::Rsa;
+use secstr::SecStr;
+use serde::Deserialize;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// plaintext to check that the private key on the token
+/// belongs to the configured public key
+const ROUND_TRIP_PLAINTEXT: &str = "lmtyas pkcs11 round trip";
+/// modules that have been loaded, they are never unloaded because
+/// `C_Finalize` would end the sessions of reloaded keys as well.
+static PKCS11_MODULES: Mutex<Vec<(String, Pkcs11)>> = Mutex::new(Vec::new());
+
+/// Holds the configuration of the PKCS#11 token
+/// that stores the RSA private key.
+#[derive(Clone, Deserialize, Debug)]
+pub struct Pkcs11Configuration {
+    /// path of the PKCS#11 module, e.g. `/usr/lib/softhsm/libsofthsm2.so`
+    pub module_path: String,
+    /// label of the token that holds the private key
+    pub token_label: String,
+    /// label of the RSA private key on the token
+    pub key_label: String,
+}
+
+/// Returns the initialized context of the PKCS#11 module, it is
+/// loaded and initialized only once.
+fn load_module(module_path: &str) -> Result<Pkcs11, Box<dyn Error>> {
+    let mut pkcs11_modules = PKCS11_MODULES.lock().unwrap();
+    if let Some((_, pkcs11)) = pkcs11_modules.iter().find(|(path, _)| path == module_path) {
+        return Ok(pkcs11.clone());
+    }
+    let pkcs11 = Pkcs11::new(module_path).map_err(|e| {
+        warn!("cannot load PKCS#11 module {}: {}", module_path, &e);
+        format!("Cannot load PKCS#11 module {}: {}", module_path, e)
+    })?;
+    match pkcs11.initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK)) {
+        Ok(_) | Err(CryptokiError::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => (),
+        Err(e) => return Err(format!("PKCS#11 C_Initialize failed: {}", e).into()),
+    }
+    pkcs11_modules.push((module_path.to_string(), pkcs11.clone()));
+    Ok(pkcs11)
+}
+
+/// Session with a PKCS#11 token, the RSA private key never
+/// leaves the token, it is only referenced by its handle.
+struct Pkcs11Session {
+    session: Session,
+    private_key: ObjectHandle,
+}
+
+impl Pkcs11Session {
+    /// Loads the module, opens a session with the token and
+    /// logs in with the pin.
+    fn open(
+        pkcs11_configuration: &Pkcs11Configuration,
+        secure_pin: &SecStr,
+    ) -> Result<Pkcs11Session, Box<dyn Error>> {
+        let pkcs11 = load_module(&pkcs11_configuration.module_path)?;
+        let slot = pkcs11
+            .get_slots_with_token()?
+            .into_iter()
+            .find(|slot| match pkcs11.get_token_info(*slot) {
+                Ok(token_info) => {
+                    debug!("found PKCS#11 token {} in {}", token_info.label(), slot);
+                    token_info.label().trim_end() == pkcs11_configuration.token_label
+                }
+                Err(_) => false,
+            })
+            .ok_or_else(|| {
+                warn!(
+                    "PKCS#11 token {} not found",
+                    &pkcs11_configuration.token_label
+                );
+                format!(
+                    "PKCS#11 token {} not found",
+                    &pkcs11_configuration.token_label
+                )
+            })?;
+        let session = pkcs11.open_ro_session(slot)?;
+        let auth_pin = AuthPin::from(secure_pin.to_unsecure_string());
+        match session.login(UserType::User, Some(&auth_pin)) {
+            Ok(_) | Err(CryptokiError::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => (),
+            Err(e) => {
+                warn!("PKCS#11 C_Login failed: {}", &e);
+                return Err(format!("PKCS#11 C_Login failed: {}", e).into());
+            }
+        }
+        let private_key = find_private_key(&session, &pkcs11_configuration.key_label)?;
+        Ok(Pkcs11Session {
+            session,
+            private_key,
+        })
+    }
+
+    /// Decrypts data with the private key on the token.
+    fn decrypt(
+        &self,
+        encrypted_data: &[u8],
+        mechanism: &Mechanism,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(self
+            .session
+            .decrypt(mechanism, self.private_key, encrypted_data)?)
+    }
+}
+
+/// Finds the handle of the RSA private key with the given label.
+fn find_private_key(session: &Session, key_label: &str) -> Result<ObjectHandle, Box<dyn Error>> {
+    let template = [
+        Attribute::Class(ObjectClass::PRIVATE_KEY),
+        Attribute::KeyType(KeyType::RSA),
+        Attribute::Label(key_label.as_bytes().to_vec()),
+    ];
+    match session.find_objects(&template)?.first() {
+        Some(private_key) => Ok(*private_key),
+        None => {
+            warn!("rsa private key {} not found on token", key_label);
+            Err(format!("RSA private key {} not found on token", key_label).into())
+        }
+    }
+}
+
+/// OAEP padding with SHA-1, just like `openssl::rsa::Padding::PKCS1_OAEP`
+fn oaep_mechanism() -> Mechanism<'static> {
+    Mechanism::RsaPkcsOaep(PkcsOaepParams::new(
+        MechanismType::SHA1,
+        PkcsMgfType::MGF1_SHA1,
+        PkcsOaepSource::empty(),
+    ))
+}
+
+/// Holds the RSA public key for encryption and the session with
+/// the PKCS#11 token that decrypts data with the private key.
+pub struct Pkcs11Keys {
+    pub rsa_public_key: Rsa<Public>,
+    /// id of the key pair, see `rsa_functions::key_id()`
+    pub rsa_key_id: String,
+    session: Mutex<Pkcs11Session>,
+}
+
+impl Pkcs11Keys {
+    /// Logs in to the PKCS#11 token with the pin and loads the
+    /// RSA public key from the given path. The private key on the
+    /// token must decrypt data encrypted with the public key,
+    /// otherwise `key_label` refers to the wrong key.
+    ///
+    /// # Arguments
+    ///
+    /// - pkcs11_configuration: module, token and key label
+    /// - rsa_public_key_path:  Path
+    /// - secure_pin:           SecStr
+    ///
+    /// # Returns
+    ///
+    /// - Result<Pkcs11Keys, Box<dyn Error>>
+    pub fn open<P: AsRef<Path>>(
+        pkcs11_configuration: &Pkcs11Configuration,
+        rsa_public_key_path: P,
+        secure_pin: &SecStr,
+    ) -> Result<Pkcs11Keys, Box<dyn Error>> {
+        let rsa_public_key_file = std::fs::read_to_string(rsa_public_key_path)?;
+        let rsa_public_key = Rsa::public_key_from_pem(rsa_public_key_file.as_bytes())?;
+        let session = Pkcs11Session::open(pkcs11_configuration, secure_pin)?;
+        let rsa_key_id = key_id(&rsa_public_key)?;
+        let pkcs11_keys = Pkcs11Keys {
+            rsa_public_key,
+            rsa_key_id,
+            session: Mutex::new(session),
+        };
+        let encrypted = pkcs11_keys.encrypt_str(ROUND_TRIP_PLAINTEXT)?;
+        match pkcs11_keys.decrypt_str(&encrypted) {
+            Ok(decrypted) if decrypted == ROUND_TRIP_PLAINTEXT => (),
+            _ => {
+                warn!(
+                    "rsa private key {} on PKCS#11 token does not match the public key",
+                    &pkcs11_configuration.key_label
+                );
+                return Err(format!(
+                    "RSA private key {} on PKCS#11 token does not match the public key",
+                    &pkcs11_configuration.key_label
+                )
+                .into());
+            }
+        }
+        info!(
+            "rsa private key {} with id {} is used from PKCS#11 token {}",
+            &pkcs11_configuration.key_label,
+            &pkcs11_keys.rsa_key_id,
+            &pkcs11_configuration.token_label
+        );
+        Ok(pkcs11_keys)
+    }
+
+    /// Decrypts a base64 encoded String slice on the token.
+    fn decrypt_str_with_mechanism(
+        &self,
+        encrypted_data: &str,
+        mechanism: &Mechanism,
+    ) -> Result<String, Box<dyn Error>> {
+        let raw_data = Vec::from_base64_encoded(encrypted_data)?;
+        let decrypted_buf = self.session.lock().unwrap().decrypt(&raw_data, mechanism)?;
+        Ok(String::from_utf8(decrypted_buf)?
+            .trim_matches(char::from(0))
+            .to_string())
+    }
+}
+
+impl MasterKey for Pkcs11Keys {
+    fn key_id(&self) -> Option<String> {
+        Some(self.rsa_key_id.clone())
+    }
+
+    fn encrypt_str(&self, plaintext_data: &str) -> Result<String, Box<dyn Error>> {
+        let rsa_keys = RsaKeys {
+            rsa_public_key: Some(self.rsa_public_key.clone()),
+            ..RsaKeys::new()
+        };
+        rsa_keys.encrypt_str(plaintext_data)
+    }
+
+    fn decrypt_str_with_key_id(
+        &self,
+        encrypted_data: &str,
+        key_id: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        if key_id.is_some() && key_id != Some(self.rsa_key_id.as_str()) {
+            return Err("data has been encrypted with another key".into());
+        }
+        self.decrypt_str_with_mechanism(encrypted_data, &oaep_mechanism())
+    }
+
+    fn decrypt_str_legacy(&self, encrypted_data: &str) -> Result<String, Box<dyn Error>> {
+        self.decrypt_str_with_mechanism(encrypted_data, &Mechanism::RsaPkcs)
+    }
+}
@@ -1,4 +1,5 @@
 use crate::base64_trait::{Base64StringConversions, Base64VecU8Conversions};
+use crate::master_key_trait::{key_id_from_public_key_der, MasterKey};
 use crate::unsecure_string::SecureStringToUnsecureString;
 use log::{debug, warn , info};
 use openssl::rsa::{Padding, Rsa};
@@ -9,7 +10,9 @@ use std::path::Path;
 use zeroize::Zeroize;
 
 // min bit size of the modulus (modulus * 8 = rsa key bits)
-const MIN_RSA_MODULUS_SIZE: u32 = 256;
+pub const MIN_RSA_MODULUS_SIZE: u32 = 256;
+/// file extension of the private keys in the key ring directory
+const KEY_RING_FILE_EXTENSION: &str = "key";
 /// Holds the RSA private and public for
 /// encryption and decryption
 pub struct RsaKeys {
@@ -17,6 +20,11 @@ pub struct RsaKeys {
     pub rsa_private_key: Option<Rsa<openssl::pkey::Private>>,
     // Option<> to construct empty values
     pub rsa_public_key: Option<Rsa<openssl::pkey::Public>>,
+    /// id of the primary key pair, see `key_id()`
+    pub rsa_key_id: Option<String>,
+    /// older private keys that are only used for decryption,
+    /// together with their key ids
+    pub previous_rsa_private_keys: Vec<(String, Rsa<openssl::pkey::Private>)>,
 }
 
 impl Default for RsaKeys {
@@ -32,11 +40,13 @@ impl RsaKeys {
     ///
     /// # Returns
     ///
-    /// - RsaKeys{rsa_private_key: None, rsa_public_key: None,}
+    /// - RsaKeys{rsa_private_key: None, rsa_public_key: None, ...}
     pub fn new() -> RsaKeys {
         RsaKeys {
             rsa_private_key: None,
             rsa_public_key: None,
+            rsa_key_id: None,
+            previous_rsa_private_keys: Vec::new(),
         }
     }
     /// Loads RSA private and public key from the given paths.
@@ -82,12 +92,73 @@ impl RsaKeys {
             let boxed_error = Box::<dyn Error + Send + Sync>::from(RSA_MIN_MODULUS_ERR);
             return Err(boxed_error);
         }
+        self.rsa_key_id = Some(key_id(&rsa_public_key)?);
         self.rsa_private_key = Some(rsa_private_key);
         self.rsa_public_key = Some(rsa_public_key);
         Ok(())
     }
 
-    /// Encrypt a String slice with stored RSA public key
+    /// Loads the older RSA private keys of the key ring, every file
+    /// ending with `.key` in the given directory. They are only used
+    /// to decrypt secrets, links and cookies created before the
+    /// primary key has been replaced, so all of them must be protected
+    /// by the same passphrase as the primary key.
+    ///
+    /// # Arguments
+    ///
+    /// - key_ring_directory: Path
+    /// - secure_passphrase:  SecStr
+    ///
+    /// # Returns
+    ///
+    /// - Result<(), Box<dyn Error>>
+    pub fn read_key_ring_from_directory<P: AsRef<Path>>(
+        &mut self,
+        key_ring_directory: P,
+        secure_passphrase: &SecStr,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut rsa_private_key_files = Vec::new();
+        for dir_entry in std::fs::read_dir(key_ring_directory)? {
+            let path = dir_entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some(KEY_RING_FILE_EXTENSION) {
+                let rsa_private_key_file = std::fs::read_to_string(&path)?;
+                rsa_private_key_files.push((path, rsa_private_key_file));
+            }
+        }
+        let mut unsecure_passphrase = secure_passphrase.to_unsecure_string();
+        let mut rsa_private_keys = Vec::new();
+        for (path, rsa_private_key_file) in rsa_private_key_files {
+            match Rsa::private_key_from_pem_passphrase(
+                rsa_private_key_file.as_bytes(),
+                unsecure_passphrase.as_bytes(),
+            ) {
+                Ok(rsa_private_key) => rsa_private_keys.push((path, rsa_private_key)),
+                Err(e) => {
+                    unsecure_passphrase.zeroize();
+                    warn!("cannot load rsa private key {}: {}", path.display(), e);
+                    return Err(format!("Cannot load rsa key {}!", path.display()).into());
+                }
+            };
+        }
+        unsecure_passphrase.zeroize();
+        let mut previous_rsa_private_keys = Vec::new();
+        for (path, rsa_private_key) in rsa_private_keys {
+            let rsa_key_id = key_id(&rsa_private_key)?;
+            if Some(&rsa_key_id) == self.rsa_key_id.as_ref() {
+                continue;
+            }
+            info!(
+                "rsa key {} with id {} is used for decryption",
+                path.display(),
+                &rsa_key_id
+            );
+            previous_rsa_private_keys.push((rsa_key_id, rsa_private_key));
+        }
+        self.previous_rsa_private_keys = previous_rsa_private_keys;
+        Ok(())
+    }
+
+    /// Encrypt a String slice with stored RSA public key (OAEP padding)
     /// and return it as base64 encoded String.
     ///
     /// # Arguments
@@ -100,7 +171,7 @@ impl RsaKeys {
         }
         let public_key = self.rsa_public_key.as_ref().unwrap();
         let mut buf: Vec<u8> = vec![0; public_key.size() as usize];
-        match public_key.public_encrypt(plaintext_data.as_bytes(), &mut buf, Padding::PKCS1) {
+        match public_key.public_encrypt(plaintext_data.as_bytes(), &mut buf, Padding::PKCS1_OAEP) {
             Err(e) => {
                 println!("Could not rsa encrypt given value: {}", &e);
                 info!("Could not rsa encrypt given value: {}", &e);
@@ -116,12 +187,71 @@ impl RsaKeys {
     }
 
     /// Decrypt a base64 encoded String slice with stored RSA private key
-    /// and return it as plaintext String.
+    /// (OAEP padding) and return it as plaintext String. The older keys
+    /// of the key ring are tried if the primary key does not fit.
     ///
     /// # Arguments
     ///
     /// - `encrypted_data`: a String slice with data to decrypt
     pub fn decrypt_str(&self, encrypted_data: &str) -> Result<String, Box<dyn Error>> {
+        self.decrypt_str_with_padding(encrypted_data, Padding::PKCS1_OAEP, None)
+    }
+
+    /// Decrypt a base64 encoded String slice (OAEP padding) with the
+    /// private key that has the given key id.
+    ///
+    /// # Arguments
+    ///
+    /// - `encrypted_data`: a String slice with data to decrypt
+    /// - `key_id`:         id of the key, every key is tried if `None`
+    pub fn decrypt_str_with_key_id(
+        &self,
+        encrypted_data: &str,
+        key_id: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        self.decrypt_str_with_padding(encrypted_data, Padding::PKCS1_OAEP, key_id)
+    }
+
+    /// Decrypt a base64 encoded String slice that has been encrypted
+    /// with PKCS#1 v1.5 padding by older versions of the web service.
+    /// Only used to read secrets that have been stored before OAEP
+    /// padding was introduced.
+    ///
+    /// # Arguments
+    ///
+    /// - `encrypted_data`: a String slice with data to decrypt
+    pub fn decrypt_str_pkcs1(&self, encrypted_data: &str) -> Result<String, Box<dyn Error>> {
+        self.decrypt_str_with_padding(encrypted_data, Padding::PKCS1, None)
+    }
+
+    /// Returns the private keys that may decrypt data, the primary
+    /// key first. If a key id is given, only the key with this
+    /// id is returned.
+    fn decryption_keys(&self, key_id: Option<&str>) -> Vec<&Rsa<openssl::pkey::Private>> {
+        let primary_key = self
+            .rsa_key_id
+            .as_deref()
+            .zip(self.rsa_private_key.as_ref());
+        primary_key
+            .into_iter()
+            .chain(
+                self.previous_rsa_private_keys
+                    .iter()
+                    .map(|(id, key)| (id.as_str(), key)),
+            )
+            .filter(|(id, _)| key_id.is_none() || key_id == Some(id))
+            .map(|(_, key)| key)
+            .collect()
+    }
+
+    /// Decrypt a base64 encoded String slice with the RSA private keys
+    /// of the key ring and the given padding.
+    fn decrypt_str_with_padding(
+        &self,
+        encrypted_data: &str,
+        padding: Padding,
+        key_id: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
         if self.rsa_private_key.is_none() {
             let box_err: Box<dyn Error> = "RSA private key is not set!".to_string().into();
             return Err(box_err);
@@ -140,37 +270,117 @@ impl RsaKeys {
             }
         };
 
-        let private_key = self.rsa_private_key.as_ref().unwrap();
-        let mut buf: Vec<u8> = vec![0; private_key.size() as usize];
-        match private_key.private_decrypt(&raw_data, &mut buf, Padding::PKCS1) {
-            Err(e) => {
-                println!("Could not rsa decrypt given value: {}", &e);
-                info!("Could not rsa decrypt given value: {}", &e);
-                let box_err: Box<dyn Error> =
-                    "Could not rsa decrypt given value".to_string().into();
-                Err(box_err)
-            }
-            Ok(_) => {
-                let decrypted_data = match String::from_utf8(buf) {
-                    Ok(s) => s,
+        // with PKCS#1 v1.5 padding a wrong key may decrypt garbage without
+        // error, so only a result that is valid utf8 is accepted.
+        let decrypted_data = self
+            .decryption_keys(key_id)
+            .into_iter()
+            .find_map(|private_key| {
+                let mut buf: Vec<u8> = vec![0; private_key.size() as usize];
+                let length = match private_key.private_decrypt(&raw_data, &mut buf, padding) {
+                    Ok(length) => length,
                     Err(e) => {
-                        println!("Could not convert decrypted data to utf8: {}", &e);
-                        info!("Could not convert decrypted data to utf8: {}", &e);
-                        let box_err: Box<dyn Error> = "Could not convert decrypted data to utf8"
-                            .to_string()
-                            .into();
-                        return Err(box_err);
+                        debug!("Could not rsa decrypt given value: {}", &e);
+                        return None;
                     }
                 };
-                Ok(decrypted_data.trim_matches(char::from(0)).to_string())
+                buf.truncate(length);
+                match String::from_utf8(buf) {
+                    Ok(s) => Some(s),
+                    Err(e) => {
+                        debug!("Could not convert decrypted data to utf8: {}", &e);
+                        None
+                    }
+                }
+            });
+        match decrypted_data {
+            None => {
+                info!("Could not rsa decrypt given value with any key");
+                let box_err: Box<dyn Error> =
+                    "Could not rsa decrypt given value".to_string().into();
+                Err(box_err)
             }
+            Some(decrypted_data) => Ok(decrypted_data.trim_matches(char::from(0)).to_string()),
         }
     }
 }
 
+impl MasterKey for RsaKeys {
+    fn key_id(&self) -> Option<String> {
+        self.rsa_key_id.clone()
+    }
+
+    fn encrypt_str(&self, plaintext_data: &str) -> Result<String, Box<dyn Error>> {
+        RsaKeys::encrypt_str(self, plaintext_data)
+    }
+
+    fn decrypt_str_with_key_id(
+        &self,
+        encrypted_data: &str,
+        key_id: Option<&str>,
+    ) -> Result<String, Box<dyn Error>> {
+        RsaKeys::decrypt_str_with_key_id(self, encrypted_data, key_id)
+    }
+
+    fn decrypt_str_legacy(&self, encrypted_data: &str) -> Result<String, Box<dyn Error>> {
+        self.decrypt_str_pkcs1(encrypted_data)
+    }
+}
+
+/// Builds the id of a RSA key from the hash of its public key,
+/// so that it can be stored with the data it encrypted.
+///
+/// # Arguments
+///
+/// - `rsa_key`: public or private key
+///
+/// # Returns
+///
+/// - `Result<String, Box<dyn Error>>`: hex encoded key id
+pub fn key_id<T: openssl::pkey::HasPublic>(rsa_key: &Rsa<T>) -> Result<String, Box<dyn Error>> {
+    Ok(key_id_from_public_key_der(&rsa_key.public_key_to_der()?))
+}
+
 /// Holds the password for the RSA private key
 /// that encrypts secrets and links.
 #[derive(Clone, Deserialize, Debug)]
 pub struct RsaPrivateKeyPassword {
     pub rsa_private_key_password: Option<SecStr>,
 }
+
+impl RsaPrivateKeyPassword {
+    /// Reads the password for the RSA private key from a file, e.g.
+    /// a systemd credential. A trailing line break is removed.
+    ///
+    /// # Arguments
+    ///
+    /// - `path`: file with the password
+    ///
+    /// # Returns
+    ///
+    /// - `Result<RsaPrivateKeyPassword, Box<dyn Error>>`
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            if mode & 0o077 != 0 {
+                warn!(
+                    "password file {} is readable by other users",
+                    path.as_ref().display()
+                );
+            }
+        }
+        let mut password = std::fs::read_to_string(&path)?;
+        let password_length = password.trim_end_matches(['\r', '\n']).len();
+        if password_length == 0 {
+            password.zeroize();
+            return Err(format!("password file {} is empty", path.as_ref().display()).into());
+        }
+        let rsa_private_key_password = SecStr::from(&password[..password_length]);
+        password.zeroize();
+        Ok(RsaPrivateKeyPassword {
+            rsa_private_key_password: Some(rsa_private_key_password),
+        })
+    }
+}
@@ -1,14 +1,40 @@
-use crate::rsa_functions::RsaKeys;
-use chrono::Utc;
+use crate::aes_functions::{
+    create_data_key, decrypt_with_data_key, encrypt_with_data_key, DecryptAes,
+};
+use crate::base64_trait::Base64StringConversions;
+use crate::base64_trait::Base64VecU8Conversions;
+use crate::mail_configuration::{
+    escape_html, MailTemplateConfiguration, SendEMail, SendEMailConfiguration,
+};
+use crate::master_key_trait::MasterKey;
+use chrono::{DateTime, Duration, Utc};
+use log::{info, warn};
+use openssl::rand::rand_bytes;
+use openssl::{memcmp, sha::sha256};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use std::fs::File;
+use std::fmt;
+use std::fs::{read_dir, remove_file, File};
 use std::path::Path;
+use std::sync::{Arc, RwLock};
 use uuid::v1::{Context, Timestamp};
 use uuid::Uuid;
+use zeroize::Zeroize;
 
 /// Used to build unique uuids, created with `openssl rand -hex 6`
 const SECRET_ID: &[u8; 6] = &[0x99, 0xa8, 0xdb, 0x5c, 0x43, 0x85];
+/// characters of a one-time code for external receivers
+const ONE_TIME_CODE_CHARS: &[u8; 32] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+/// length of a one-time code for external receivers
+const ONE_TIME_CODE_LEN: usize = 10;
+/// format of secrets stored with rsa PKCS#1 v1.5 padding and AES-CBC,
+/// files without a version field have been written in this format.
+pub const SECRET_FORMAT_LEGACY: u8 = 1;
+/// format of secrets stored with rsa OAEP padding and AES-GCM
+pub const SECRET_FORMAT_RSA_OAEP: u8 = 2;
+/// format of secrets whose fields are encrypted by one random data key,
+/// only the data key is encrypted with the master key (RSA or EC)
+pub const SECRET_FORMAT_VERSION: u8 = 3;
 
 pub struct SharedSecretData {
     /// used by the uuid crate to build unique uuids across threads
@@ -53,8 +79,11 @@ impl SharedSecretData {
 }
 
 /// Holds the a secret and its meta data
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize)]
 pub struct Secret {
+    /// format of the stored secret, see `SECRET_FORMAT_VERSION`
+    #[serde(rename = "Version", default = "legacy_format_version")]
+    pub version: u8,
     #[serde(rename = "FromEmail")]
     pub from_email: String,
     #[serde(rename = "FromDisplayName")]
@@ -67,6 +96,305 @@ pub struct Secret {
     pub context: String,
     #[serde(rename = "Secret")]
     pub secret: String,
+    /// lifetime of the secret in hours as chosen by the sender,
+    /// only used when a new secret is posted.
+    #[serde(
+        rename = "ValidForHours",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub valid_for_hours: Option<i64>,
+    /// rfc3339 formatted point in time when the secret expires
+    #[serde(rename = "ExpiresAt", default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// every receiver of the secret, each one reveals the secret on
+    /// its own. Secrets stored before multiple receivers were supported
+    /// only have `to_email` and `to_display_name`.
+    #[serde(rename = "Receivers", default, skip_serializing_if = "Vec::is_empty")]
+    pub receivers: Vec<Receiver>,
+    /// optional passphrase chosen by the sender, only used when
+    /// a new secret is posted. It is never stored.
+    #[serde(
+        rename = "Passphrase",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub passphrase: Option<String>,
+    /// salt to derive the key from the passphrase, only
+    /// set if the secret is protected by a passphrase.
+    #[serde(
+        rename = "PassphraseSalt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub passphrase_salt: Option<String>,
+    /// data key that encrypts all other fields, encrypted with the master key,
+    /// see `SECRET_FORMAT_VERSION`
+    #[serde(rename = "DataKey", default, skip_serializing_if = "Option::is_none")]
+    pub data_key: Option<String>,
+    /// id of the master key that encrypted the data key,
+    /// see `master_key_trait::key_id_from_public_key_der`
+    #[serde(rename = "KeyId", default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+    /// optional file that is told together with the secret
+    #[serde(
+        rename = "Attachment",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub attachment: Option<Attachment>,
+}
+
+/// neither the secret nor the passphrase must appear in the log,
+/// the key of the passphrase would unlock the secret
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Secret")
+            .field("version", &self.version)
+            .field("from_email", &self.from_email)
+            .field("from_display_name", &self.from_display_name)
+            .field("to_email", &self.to_email)
+            .field("to_display_name", &self.to_display_name)
+            .field("context", &self.context)
+            .field("secret", &format!("<{} bytes>", self.secret.len()))
+            .field("valid_for_hours", &self.valid_for_hours)
+            .field("expires_at", &self.expires_at)
+            .field("receivers", &self.receivers)
+            .field(
+                "passphrase",
+                &self.passphrase.as_ref().map(|_| "<redacted>"),
+            )
+            .field("passphrase_salt", &self.passphrase_salt)
+            .field("data_key", &self.data_key)
+            .field("key_id", &self.key_id)
+            .field("attachment", &self.attachment)
+            .finish()
+    }
+}
+
+/// Holds a file attached to a secret
+#[derive(Deserialize, Serialize)]
+pub struct Attachment {
+    #[serde(rename = "FileName")]
+    pub file_name: String,
+    #[serde(rename = "ContentType")]
+    pub content_type: String,
+    /// base64 encoded content of the file, it is encrypted with
+    /// the key of the secret before it is stored, just like the secret.
+    #[serde(rename = "Content")]
+    pub content: String,
+}
+
+/// the content of the file must not appear in the log
+impl fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attachment")
+            .field("file_name", &self.file_name)
+            .field("content_type", &self.content_type)
+            .field("content", &format!("<{} bytes>", self.content.len()))
+            .finish()
+    }
+}
+
+/// Holds one receiver of a secret
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Receiver {
+    #[serde(rename = "ToEmail")]
+    pub to_email: String,
+    #[serde(rename = "ToDisplayName")]
+    pub to_display_name: String,
+    /// set as soon as the receiver has revealed the secret,
+    /// stored unencrypted so that it can be updated without
+    /// access to the rsa keys.
+    #[serde(rename = "Revealed", default)]
+    pub revealed: bool,
+    /// hash of the one-time code of an external receiver
+    /// that reveals the secret without login
+    #[serde(rename = "CodeHash", default, skip_serializing_if = "Option::is_none")]
+    pub code_hash: Option<String>,
+    /// number of wrong one-time codes entered, stored unencrypted
+    /// just like `revealed`
+    #[serde(rename = "FailedAttempts", default)]
+    pub failed_attempts: u8,
+}
+
+impl Receiver {
+    /// Checks the one-time code of an external receiver. Both codes
+    /// are hashed, so that the comparison takes constant time.
+    ///
+    /// # Arguments
+    ///
+    /// - `code`: one-time code entered by the receiver
+    ///
+    /// # Returns
+    ///
+    /// - `bool`: false if the receiver has no one-time code
+    pub fn code_matches(&self, code: &str) -> bool {
+        match &self.code_hash {
+            Some(code_hash) => memcmp::eq(
+                code_hash.as_bytes(),
+                hash_one_time_code(&code.trim().to_uppercase()).as_bytes(),
+            ),
+            None => false,
+        }
+    }
+
+    /// Creates a new instance of `Receiver` with
+    /// encrypted data.
+    fn to_encrypted(&self, field_cipher: &FieldCipher) -> Result<Receiver, Box<dyn Error>> {
+        Ok(Receiver {
+            to_email: field_cipher.encrypt(&self.to_email)?,
+            to_display_name: field_cipher.encrypt(&self.to_display_name)?,
+            revealed: self.revealed,
+            code_hash: self
+                .code_hash
+                .as_ref()
+                .map(|c| field_cipher.encrypt(c))
+                .transpose()?,
+            failed_attempts: self.failed_attempts,
+        })
+    }
+
+    /// Creates a new instance of `Receiver` with
+    /// decrypted data.
+    fn to_decrypted(&self, field_cipher: &FieldCipher) -> Result<Receiver, Box<dyn Error>> {
+        Ok(Receiver {
+            to_email: field_cipher.decrypt(&self.to_email)?,
+            to_display_name: field_cipher.decrypt(&self.to_display_name)?,
+            revealed: self.revealed,
+            code_hash: self
+                .code_hash
+                .as_ref()
+                .map(|c| field_cipher.decrypt(c))
+                .transpose()?,
+            failed_attempts: self.failed_attempts,
+        })
+    }
+}
+
+impl Attachment {
+    /// AES encrypts the content with the key of the secret. The
+    /// key is only part of the url payload, so the attachment
+    /// cannot be read from the stored file alone.
+    ///
+    /// # Arguments
+    ///
+    /// - `key_base64`: base64 encoded key of the secret
+    pub fn encrypt_content(&mut self, key_base64: &str) -> Result<(), Box<dyn Error>> {
+        let mut key = Vec::from_base64_urlsafe_encoded(key_base64)?;
+        let encrypted_content = encrypt_with_data_key(&key, &self.content);
+        key.zeroize();
+        self.content = encrypted_content?;
+        Ok(())
+    }
+
+    /// Decrypts the content that has been encrypted with `encrypt_content()`.
+    ///
+    /// # Arguments
+    ///
+    /// - `key_base64`: base64 encoded key of the secret
+    pub fn decrypt_content(&mut self, key_base64: &str) -> Result<(), Box<dyn Error>> {
+        let mut key = Vec::from_base64_urlsafe_encoded(key_base64)?;
+        let decrypted_content = decrypt_with_data_key(&key, &self.content);
+        key.zeroize();
+        self.content = decrypted_content?;
+        Ok(())
+    }
+
+    /// Creates a new instance of `Attachment` with
+    /// encrypted data.
+    fn to_encrypted(&self, field_cipher: &FieldCipher) -> Result<Attachment, Box<dyn Error>> {
+        Ok(Attachment {
+            file_name: field_cipher.encrypt(&self.file_name)?,
+            content_type: field_cipher.encrypt(&self.content_type)?,
+            content: field_cipher.encrypt(&self.content)?,
+        })
+    }
+
+    /// Creates a new instance of `Attachment` with
+    /// decrypted data.
+    fn to_decrypted(&self, field_cipher: &FieldCipher) -> Result<Attachment, Box<dyn Error>> {
+        Ok(Attachment {
+            file_name: field_cipher.decrypt(&self.file_name)?,
+            content_type: field_cipher.decrypt(&self.content_type)?,
+            content: field_cipher.decrypt(&self.content)?,
+        })
+    }
+}
+
+/// Encrypts and decrypts the fields of a stored secret
+/// according to its format version.
+struct FieldCipher<'a> {
+    master_key: &'a dyn MasterKey,
+    version: u8,
+    /// data key of secrets stored with `SECRET_FORMAT_VERSION`
+    data_key: Vec<u8>,
+}
+
+impl<'a> FieldCipher<'a> {
+    /// Creates a new random data key to encrypt a secret.
+    ///
+    /// # Returns
+    ///
+    /// - `(FieldCipher, String)`: the cipher and the encrypted data key
+    fn new(master_key: &'a dyn MasterKey) -> Result<(FieldCipher<'a>, String), Box<dyn Error>> {
+        let field_cipher = FieldCipher {
+            master_key,
+            version: SECRET_FORMAT_VERSION,
+            data_key: create_data_key()?,
+        };
+        let mut data_key_base64 = field_cipher.data_key.to_base64_urlsafe_encoded();
+        let encrypted_data_key = master_key.encrypt_str(&data_key_base64);
+        data_key_base64.zeroize();
+        Ok((field_cipher, encrypted_data_key?))
+    }
+
+    /// Builds the cipher to decrypt a stored secret, the data key
+    /// is decrypted with the private key of the given key id.
+    fn for_stored_secret(
+        master_key: &'a dyn MasterKey,
+        version: u8,
+        encrypted_data_key: Option<&str>,
+        key_id: Option<&str>,
+    ) -> Result<FieldCipher<'a>, Box<dyn Error>> {
+        let data_key = match (version, encrypted_data_key) {
+            (SECRET_FORMAT_LEGACY | SECRET_FORMAT_RSA_OAEP, _) => Vec::new(),
+            (SECRET_FORMAT_VERSION, Some(encrypted_data_key)) => {
+                let mut data_key_base64 =
+                    master_key.decrypt_str_with_key_id(encrypted_data_key, key_id)?;
+                let data_key = Vec::from_base64_urlsafe_encoded(&data_key_base64);
+                data_key_base64.zeroize();
+                data_key?
+            }
+            (SECRET_FORMAT_VERSION, None) => return Err("secret has no data key".into()),
+            _ => return Err(format!("unknown secret format version {}", version).into()),
+        };
+        Ok(FieldCipher {
+            master_key,
+            version,
+            data_key,
+        })
+    }
+
+    /// Encrypts a field with the data key.
+    fn encrypt(&self, plaintext: &str) -> Result<String, Box<dyn Error>> {
+        encrypt_with_data_key(&self.data_key, plaintext)
+    }
+
+    /// Decrypts a field depending on the format version.
+    fn decrypt(&self, encrypted_data: &str) -> Result<String, Box<dyn Error>> {
+        match self.version {
+            SECRET_FORMAT_LEGACY => self.master_key.decrypt_str_legacy(encrypted_data),
+            SECRET_FORMAT_RSA_OAEP => self.master_key.decrypt_str(encrypted_data),
+            _ => decrypt_with_data_key(&self.data_key, encrypted_data),
+        }
+    }
+}
+
+impl Drop for FieldCipher<'_> {
+    fn drop(&mut self) {
+        self.data_key.zeroize();
+    }
 }
 
 impl Secret {
@@ -103,45 +431,150 @@ impl Secret {
     }
 
     /// Creates a new instance of `Secret` with
-    /// encrypted data.
-    pub fn to_encrypted(&self, rsa_keys: &RsaKeys) -> Result<Secret, Box<dyn Error>> {
-        let encrypted_from_email = rsa_keys.encrypt_str(&self.from_email)?;
-        let encrypted_from_display_name = rsa_keys.encrypt_str(&self.from_display_name)?;
-        let encrypted_to_email = rsa_keys.encrypt_str(&self.to_email)?;
-        let encrypted_to_display_name = rsa_keys.encrypt_str(&self.to_display_name)?;
-        let encrypted_context = rsa_keys.encrypt_str(&self.context)?;
-        let encrypted_secret = rsa_keys.encrypt_str(&self.secret)?;
+    /// encrypted data. All fields are encrypted with a new
+    /// random data key, only the data key is encrypted with
+    /// the master key, so that the size of the fields is not
+    /// limited by the rsa key size.
+    pub fn to_encrypted(&self, master_key: &dyn MasterKey) -> Result<Secret, Box<dyn Error>> {
+        let (field_cipher, encrypted_data_key) = FieldCipher::new(master_key)?;
+        let encrypted_from_email = field_cipher.encrypt(&self.from_email)?;
+        let encrypted_from_display_name = field_cipher.encrypt(&self.from_display_name)?;
+        let encrypted_to_email = field_cipher.encrypt(&self.to_email)?;
+        let encrypted_to_display_name = field_cipher.encrypt(&self.to_display_name)?;
+        let encrypted_context = field_cipher.encrypt(&self.context)?;
+        let encrypted_secret = field_cipher.encrypt(&self.secret)?;
+        let encrypted_expires_at = self
+            .expires_at
+            .as_ref()
+            .map(|e| field_cipher.encrypt(e))
+            .transpose()?;
+        let encrypted_passphrase_salt = self
+            .passphrase_salt
+            .as_ref()
+            .map(|s| field_cipher.encrypt(s))
+            .transpose()?;
+        let encrypted_receivers = self
+            .receivers
+            .iter()
+            .map(|r| r.to_encrypted(&field_cipher))
+            .collect::<Result<Vec<Receiver>, Box<dyn Error>>>()?;
+        let encrypted_attachment = self
+            .attachment
+            .as_ref()
+            .map(|a| a.to_encrypted(&field_cipher))
+            .transpose()?;
         let secret = Secret {
+            version: SECRET_FORMAT_VERSION,
             from_email: encrypted_from_email,
             from_display_name: encrypted_from_display_name,
             to_email: encrypted_to_email,
             to_display_name: encrypted_to_display_name,
             context: encrypted_context,
             secret: encrypted_secret,
+            valid_for_hours: None,
+            expires_at: encrypted_expires_at,
+            receivers: encrypted_receivers,
+            passphrase: None,
+            passphrase_salt: encrypted_passphrase_salt,
+            data_key: Some(encrypted_data_key),
+            key_id: master_key.key_id(),
+            attachment: encrypted_attachment,
         };
         Ok(secret)
     }
 
     /// Creates a new instance of `Secret` with
     /// decrypted data.
-    pub fn to_decrypted(&self, rsa_keys: &RsaKeys) -> Result<Secret, Box<dyn Error>> {
-        let decrypted_from_email = rsa_keys.decrypt_str(&self.from_email)?;
-        let decrypted_from_display_name = rsa_keys.decrypt_str(&self.from_display_name)?;
-        let decrypted_to_email = rsa_keys.decrypt_str(&self.to_email)?;
-        let decrypted_to_display_name = rsa_keys.decrypt_str(&self.to_display_name)?;
-        let decrypted_context = rsa_keys.decrypt_str(&self.context)?;
-        let decrypted_secret = rsa_keys.decrypt_str(&self.secret)?;
+    pub fn to_decrypted(&self, master_key: &dyn MasterKey) -> Result<Secret, Box<dyn Error>> {
+        let field_cipher = FieldCipher::for_stored_secret(
+            master_key,
+            self.version,
+            self.data_key.as_deref(),
+            self.key_id.as_deref(),
+        )?;
+        let decrypted_from_email = field_cipher.decrypt(&self.from_email)?;
+        let decrypted_from_display_name = field_cipher.decrypt(&self.from_display_name)?;
+        let decrypted_to_email = field_cipher.decrypt(&self.to_email)?;
+        let decrypted_to_display_name = field_cipher.decrypt(&self.to_display_name)?;
+        let decrypted_context = field_cipher.decrypt(&self.context)?;
+        let decrypted_secret = field_cipher.decrypt(&self.secret)?;
+        let decrypted_expires_at = self
+            .expires_at
+            .as_ref()
+            .map(|e| field_cipher.decrypt(e))
+            .transpose()?;
+        let decrypted_passphrase_salt = self
+            .passphrase_salt
+            .as_ref()
+            .map(|s| field_cipher.decrypt(s))
+            .transpose()?;
+        let decrypted_receivers = self
+            .receivers
+            .iter()
+            .map(|r| r.to_decrypted(&field_cipher))
+            .collect::<Result<Vec<Receiver>, Box<dyn Error>>>()?;
+        let decrypted_attachment = self
+            .attachment
+            .as_ref()
+            .map(|a| a.to_decrypted(&field_cipher))
+            .transpose()?;
         let secret = Secret {
+            version: self.version,
             from_email: decrypted_from_email,
             from_display_name: decrypted_from_display_name,
             to_email: decrypted_to_email,
             to_display_name: decrypted_to_display_name,
             context: decrypted_context,
             secret: decrypted_secret,
+            valid_for_hours: None,
+            expires_at: decrypted_expires_at,
+            receivers: decrypted_receivers,
+            passphrase: None,
+            passphrase_salt: decrypted_passphrase_salt,
+            data_key: None,
+            key_id: None,
+            attachment: decrypted_attachment,
         };
         Ok(secret)
     }
 
+    /// Decrypts the AES encrypted secret of a (rsa decrypted) secret
+    /// with the cipher of its format version.
+    ///
+    /// # Arguments
+    ///
+    /// - `key_base64`: base64 encoded key from the url payload
+    /// - `iv_base64`:  base64 encoded iv from the url payload
+    ///
+    /// # Returns
+    ///
+    /// - `Result<String, Box<dyn Error>>`: the plaintext secret
+    pub fn decrypt_aes_secret(
+        &self,
+        key_base64: &str,
+        iv_base64: &str,
+    ) -> Result<String, Box<dyn Error>> {
+        match self.version {
+            SECRET_FORMAT_LEGACY => self.secret.decrypt_b64_aes_cbc(key_base64, iv_base64),
+            _ => self.secret.decrypt_b64_aes(key_base64, iv_base64),
+        }
+    }
+
+    /// Checks if the (decrypted) secret has expired.
+    ///
+    /// Secrets without expiry timestamp never expire on their own,
+    /// they are removed after `max_secret_age_seconds` by the cleanup job.
+    ///
+    /// # Returns
+    ///
+    /// - `bool`: true if the secret has expired or the timestamp cannot be parsed
+    pub fn is_expired(&self) -> bool {
+        match &self.expires_at {
+            Some(expires_at) => is_expired(expires_at),
+            None => false,
+        }
+    }
+
     /// replaces the placeholders in a mail template:
     ///
     /// {ToDisplayName}   -> `&self.to_display_name`
@@ -167,6 +600,61 @@ impl Secret {
             .replace("{UrlPayload}", url_payload)
     }
 
+    /// replaces the placeholders in a html mail template, just like
+    /// `build_mail_body()` but all values are html escaped, so that
+    /// e.g. the context cannot inject markup into the mail.
+    ///
+    /// # Agruments
+    ///
+    /// - `mail_body_template`: html mail template
+    /// - `url_payload`:        url payload that will replace {UrlPayload}
+    ///
+    /// # Returns
+    ///
+    /// - `String`
+    pub fn build_mail_body_html(&self, mail_body_template: &str, url_payload: &str) -> String {
+        mail_body_template
+            .replace("{ToDisplayName}", &escape_html(&self.to_display_name))
+            .replace("{FromDisplayName}", &escape_html(&self.from_display_name))
+            .replace("{Context}", &escape_html(&self.context))
+            .replace("{UrlPayload}", &escape_html(url_payload))
+    }
+
+    /// Sends a notification about this (decrypted) secret to its sender,
+    /// e.g. when the secret has been revealed or has expired.
+    ///
+    /// The placeholders `{ToDisplayName}`, `{FromDisplayName}`
+    /// and `{Context}` are replaced in the template and subject.
+    ///
+    /// # Arguments
+    ///
+    /// - `notification`:        subject and template of the notification
+    /// - `email_configuration`: mail server to use
+    ///
+    /// # Returns
+    ///
+    /// - `Result<(), Box<dyn Error>>`
+    pub fn notify_sender(
+        &self,
+        notification: &MailTemplateConfiguration,
+        email_configuration: &SendEMailConfiguration,
+    ) -> Result<(), Box<dyn Error>> {
+        let mail_body_template = notification.load_mail_template()?;
+        let mail_body = self.build_mail_body(&mail_body_template, "");
+        let mail_body_html = notification
+            .load_mail_template_html()?
+            .map(|mail_body_template_html| self.build_mail_body_html(&mail_body_template_html, ""));
+        let mail_subject = self
+            .build_context(&notification.mail_subject)
+            .replace("{ToDisplayName}", &self.to_display_name);
+        email_configuration.send_multipart_mail(
+            &self.from_email,
+            &mail_subject,
+            &mail_body,
+            mail_body_html.as_deref(),
+        )
+    }
+
     /// replaces the placeholder in a mail subject:
     ///
     /// {Context}         -> `&self.context`
@@ -182,3 +670,336 @@ impl Secret {
         subject.replace("{Context}", &self.context)
     }
 }
+
+/// Format version of secret files without a version field.
+fn legacy_format_version() -> u8 {
+    SECRET_FORMAT_LEGACY
+}
+
+/// Checks if a rfc3339 formatted timestamp lies in the past.
+/// Timestamps that cannot be parsed are handled as expired.
+///
+/// # Arguments
+///
+/// - `expires_at`: rfc3339 formatted timestamp
+///
+/// # Returns
+///
+/// - `bool`
+pub fn is_expired(expires_at: &str) -> bool {
+    match DateTime::parse_from_rfc3339(expires_at) {
+        Ok(expires_at) => expires_at < Utc::now(),
+        Err(e) => {
+            warn!("cannot parse expiry timestamp {}: {}", &expires_at, &e);
+            true
+        }
+    }
+}
+
+/// Reads a secret that will be removed unrevealed, so that the optional
+/// expiry notice can be sent to its sender. The secret itself stays
+/// aes encrypted, only the meta data is needed for the mail.
+fn read_expiry_notice_from_disk<P: AsRef<Path>>(
+    path: P,
+    master_key: &dyn MasterKey,
+) -> Result<Secret, Box<dyn Error>> {
+    let content = std::fs::read_to_string(&path)?;
+    let encrypted_secret: Secret = serde_json::from_str(&content)?;
+    let mut secret = encrypted_secret.to_decrypted(master_key)?;
+    // only name the receivers that did not reveal the secret
+    if !secret.receivers.is_empty() {
+        secret.to_display_name = secret
+            .receivers
+            .iter()
+            .filter(|r| !r.revealed)
+            .map(|r| r.to_display_name.as_str())
+            .collect::<Vec<&str>>()
+            .join(", ");
+    }
+    Ok(secret)
+}
+
+/// Reads the expiry timestamp of a stored secret file.
+fn read_expiry_from_disk<P: AsRef<Path>>(
+    path: P,
+    master_key: &dyn MasterKey,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let content = std::fs::read_to_string(&path)?;
+    let secret: Secret = serde_json::from_str(&content)?;
+    match secret.expires_at {
+        Some(expires_at) => {
+            let field_cipher = FieldCipher::for_stored_secret(
+                master_key,
+                secret.version,
+                secret.data_key.as_deref(),
+                secret.key_id.as_deref(),
+            )?;
+            Ok(Some(field_cipher.decrypt(&expires_at)?))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Marks one receiver of a stored secret as revealed. The secret file
+/// is removed when all receivers have revealed the secret.
+///
+/// The caller must make sure that no other thread updates the same
+/// file at the same time.
+///
+/// # Arguments
+///
+/// - `path`:           Path and filename in the form of /path/<uuid>
+/// - `receiver_index`: index of the receiver in the stored secret
+///
+/// # Returns
+///
+/// - `Result<bool, Box<dyn Error>>`: true if the secret file has been removed
+pub fn mark_receiver_revealed<P: AsRef<Path>>(
+    path: P,
+    receiver_index: usize,
+) -> Result<bool, Box<dyn Error>> {
+    update_receiver(path, receiver_index, |receiver| {
+        if receiver.revealed {
+            return Err("secret has already been revealed".into());
+        }
+        receiver.revealed = true;
+        Ok(())
+    })
+}
+
+/// Counts a wrong one-time code or passphrase entered by a receiver.
+/// After `max_attempts` the receiver cannot reveal the secret
+/// anymore and is handled as if the secret had been revealed.
+///
+/// The caller must make sure that no other thread updates the same
+/// file at the same time.
+///
+/// # Arguments
+///
+/// - `path`:           Path and filename in the form of /path/<uuid>
+/// - `receiver_index`: index of the receiver in the stored secret
+/// - `max_attempts`:   number of wrong values that may be entered
+///
+/// # Returns
+///
+/// - `Result<bool, Box<dyn Error>>`: true if the receiver is locked out
+pub fn record_failed_attempt<P: AsRef<Path>>(
+    path: P,
+    receiver_index: usize,
+    max_attempts: u8,
+) -> Result<bool, Box<dyn Error>> {
+    let mut locked_out = false;
+    update_receiver(path, receiver_index, |receiver| {
+        receiver.failed_attempts = receiver.failed_attempts.saturating_add(1);
+        if receiver.failed_attempts >= max_attempts {
+            receiver.revealed = true;
+            locked_out = true;
+        }
+        Ok(())
+    })?;
+    Ok(locked_out)
+}
+
+/// Updates one receiver of a stored secret. The secret file is removed
+/// when all receivers have revealed the secret, otherwise it is written
+/// back with its original modification time, so that the secret does
+/// not live longer than `max_secret_age_seconds`. The new content is
+/// written to a temporary file in the same directory that replaces the
+/// secret file, so a crash cannot leave a truncated secret behind.
+fn update_receiver<P, F>(path: P, receiver_index: usize, update: F) -> Result<bool, Box<dyn Error>>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&mut Receiver) -> Result<(), Box<dyn Error>>,
+{
+    let content = std::fs::read_to_string(&path)?;
+    let mut secret: Secret = serde_json::from_str(&content)?;
+    match secret.receivers.get_mut(receiver_index) {
+        Some(receiver) => update(receiver)?,
+        None => return Err(format!("no receiver with index {}", receiver_index).into()),
+    }
+    if secret.receivers.iter().all(|r| r.revealed) {
+        remove_file(&path)?;
+        return Ok(true);
+    }
+    let modified = std::fs::metadata(&path)?.modified()?;
+    // not a uuid, so the cleanup job ignores a left over file
+    let mut temp_path = path.as_ref().as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let write_result = write_temp_secret_file(&temp_path, &secret, modified)
+        .and_then(|_| Ok(std::fs::rename(&temp_path, &path)?));
+    if write_result.is_err() {
+        let _ = remove_file(&temp_path);
+    }
+    write_result?;
+    Ok(false)
+}
+
+/// Writes the secret to a temporary file with the given modification time.
+fn write_temp_secret_file<P: AsRef<Path>>(
+    temp_path: P,
+    secret: &Secret,
+    modified: std::time::SystemTime,
+) -> Result<(), Box<dyn Error>> {
+    let file = File::create(&temp_path)?;
+    serde_json::to_writer(&file, secret)?;
+    file.sync_all()?;
+    file.set_modified(modified)?;
+    Ok(())
+}
+
+/// Creates a random one-time code for an external receiver,
+/// e.g. `K7QM3XW9PA`. Ambiguous characters like `0` and `O`
+/// are not used.
+///
+/// # Returns
+///
+/// - `Result<String, Box<dyn Error>>`
+pub fn create_one_time_code() -> Result<String, Box<dyn Error>> {
+    let mut random_bytes = [0u8; ONE_TIME_CODE_LEN];
+    rand_bytes(&mut random_bytes)?;
+    // 256 is a multiple of 32, so every character is equally likely
+    Ok(random_bytes
+        .iter()
+        .map(|b| ONE_TIME_CODE_CHARS[*b as usize % ONE_TIME_CODE_CHARS.len()] as char)
+        .collect())
+}
+
+/// Hashes a one-time code, so that it is not stored in plaintext.
+///
+/// # Arguments
+///
+/// - `code`: one-time code
+///
+/// # Returns
+///
+/// - `String`: base64 encoded sha256 hash of the code
+pub fn hash_one_time_code(code: &str) -> String {
+    sha256(code.as_bytes()).to_base64_encoded()
+}
+
+/// Removes secret files that have not been revealed in time.
+/// This happens when
+///
+/// - the `max_secret_age_seconds` from the configuration file have
+///   past since the secret file was written or
+/// - the lifetime chosen by the sender has ended. Since the expiry
+///   timestamp is stored encrypted, this can only be checked after
+///   the rsa keys have been loaded.
+///
+/// Only files with a valid uuid as file name are taken into account,
+/// every removed secret is logged for auditing purposes. If an expiry
+/// notice is configured and the rsa keys are loaded, the sender of
+/// the secret is informed.
+///
+/// # Arguments
+///
+/// - `secret_directory`:    directory where the secret files are stored
+/// - `max_age_in_seconds`:  maximum age of a secret file
+/// - `master_key`:          master key to decrypt the expiry timestamp
+/// - `email_configuration`: used to send the optional expiry notice
+pub fn cleanup_expired_secret_files<P: AsRef<Path>>(
+    secret_directory: P,
+    max_age_in_seconds: i64,
+    master_key: &Arc<RwLock<Box<dyn MasterKey>>>,
+    email_configuration: &SendEMailConfiguration,
+) {
+    let time_to_delete = Utc::now() - Duration::seconds(max_age_in_seconds);
+    let dir_entries = match read_dir(&secret_directory) {
+        Ok(dir_entries) => dir_entries,
+        Err(e) => {
+            warn!(
+                "cannot read secret directory {}: {}",
+                &secret_directory.as_ref().display(),
+                &e
+            );
+            return;
+        }
+    };
+    for dir_entry in dir_entries.flatten() {
+        let path = dir_entry.path();
+        if !path.is_file() {
+            continue;
+        }
+        // do not touch files that have not been written by us
+        let uuid = match path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(|f| Uuid::parse_str(f).ok())
+        {
+            Some(uuid) => uuid,
+            None => continue,
+        };
+        let modified: DateTime<Utc> = match dir_entry.metadata().and_then(|m| m.modified()) {
+            Ok(modified) => modified.into(),
+            Err(e) => {
+                warn!(
+                    "cannot get modification time of secret file {}: {}",
+                    &path.display(),
+                    &e
+                );
+                continue;
+            }
+        };
+        let mut expired = modified < time_to_delete;
+        // the mail is sent after the lock has been released, a slow
+        // mail server must not block sealing or reloading the keys.
+        let expired_secret = {
+            let master_key_read_lock = master_key.read().unwrap();
+            let keys_loaded = master_key_read_lock.key_id().is_some();
+            if !expired && keys_loaded {
+                expired = match read_expiry_from_disk(&path, master_key_read_lock.as_ref()) {
+                    Ok(Some(expires_at)) => is_expired(&expires_at),
+                    Ok(None) => false,
+                    Err(e) => {
+                        warn!(
+                            "cannot read expiry of secret file {}: {}",
+                            &path.display(),
+                            &e
+                        );
+                        false
+                    }
+                };
+            }
+            if expired && keys_loaded && email_configuration.expiry_notice.is_some() {
+                match read_expiry_notice_from_disk(&path, master_key_read_lock.as_ref()) {
+                    Ok(expired_secret) => Some(expired_secret),
+                    Err(e) => {
+                        warn!(
+                            "cannot send expiry notice for secret {}: {}",
+                            &path.display(),
+                            &e
+                        );
+                        None
+                    }
+                }
+            } else {
+                None
+            }
+        };
+        if expired {
+            if let (Some(expired_secret), Some(expiry_notice)) =
+                (expired_secret, &email_configuration.expiry_notice)
+            {
+                match expired_secret.notify_sender(expiry_notice, email_configuration) {
+                    Ok(_) => info!("expiry notice sent to {}", &expired_secret.from_email),
+                    Err(e) => warn!(
+                        "cannot send expiry notice for secret {}: {}",
+                        &path.display(),
+                        &e
+                    ),
+                }
+            }
+            match remove_file(&path) {
+                Ok(_) => info!(
+                    "expired secret {} has been removed (stored since {})",
+                    &uuid, &modified
+                ),
+                Err(e) => warn!(
+                    "expired secret {} cannot be removed: {}",
+                    &path.display(),
+                    &e
+                ),
+            }
+        }
+    }
+}
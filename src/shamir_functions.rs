@@ -0,0 +1,271 @@
+use chrono::{DateTime, Duration, Utc};
+use openssl::rand::rand_bytes;
+use secstr::SecStr;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use zeroize::Zeroize;
+
+/// min number of shares that are needed to restore the password,
+/// otherwise every administrator could unlock the master key.
+pub const MIN_SHAMIR_THRESHOLD: u8 = 2;
+
+/// Holds the configuration for unlocking the master key with
+/// shares of the password that are entered by k of n administrators.
+#[derive(Clone, Deserialize, Debug)]
+pub struct ShamirConfiguration {
+    /// number of shares (k) that are needed to restore the password
+    pub threshold: u8,
+    /// time window in which the shares must be entered
+    pub max_share_age_seconds: i64,
+}
+
+/// Holds one share of a secret that has been split with
+/// Shamir's secret sharing in GF(2^8).
+pub struct ShamirShare {
+    /// x-coordinate of the share, never 0 because f(0) is the secret
+    pub x: u8,
+    /// f(x) for every byte of the secret
+    y: Vec<u8>,
+}
+
+impl ShamirShare {
+    /// Parses a share in the format `<x>-<y>`, both hex encoded.
+    ///
+    /// # Arguments
+    ///
+    /// - `share`: the share as printed by `split_secret()`
+    ///
+    /// # Returns
+    ///
+    /// - `Result<ShamirShare, Box<dyn Error>>`
+    pub fn parse(share: &str) -> Result<ShamirShare, Box<dyn Error>> {
+        let (x, y) = share
+            .trim()
+            .split_once('-')
+            .ok_or("share has no x-coordinate")?;
+        let x = u8::from_str_radix(x, 16)?;
+        if x == 0 {
+            return Err("x-coordinate of share must not be 0".into());
+        }
+        if y.is_empty() || y.len() % 2 != 0 || !y.is_ascii() {
+            return Err("share is not hex encoded".into());
+        }
+        let y = (0..y.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&y[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()?;
+        Ok(ShamirShare { x, y })
+    }
+}
+
+/// prints the share in the format that is parsed by `ShamirShare::parse()`
+impl fmt::Display for ShamirShare {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}-", self.x)?;
+        for b in &self.y {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ShamirShare {
+    fn drop(&mut self) {
+        self.y.zeroize();
+    }
+}
+
+/// multiplication in GF(2^8) with the AES polynomial
+/// x^8 + x^4 + x^3 + x + 1, without data dependent branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// multiplicative inverse in GF(2^8): a^254 = a^-1
+fn gf_inv(a: u8) -> u8 {
+    let mut inverse = 1u8;
+    let mut base = a;
+    let mut exponent = 254u8;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            inverse = gf_mul(inverse, base);
+        }
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+    inverse
+}
+
+/// Splits a secret, e.g. the password of the rsa private key,
+/// into `share_count` shares. Any `threshold` shares restore the
+/// secret, fewer shares reveal nothing about it.
+///
+/// # Arguments
+///
+/// - `secret`:      the bytes to split
+/// - `threshold`:   number of shares needed to restore the secret
+/// - `share_count`: number of shares to create
+///
+/// # Returns
+///
+/// - `Result<Vec<ShamirShare>, Box<dyn Error>>`
+pub fn split_secret(
+    secret: &[u8],
+    threshold: u8,
+    share_count: u8,
+) -> Result<Vec<ShamirShare>, Box<dyn Error>> {
+    if threshold < MIN_SHAMIR_THRESHOLD || threshold > share_count {
+        return Err(format!(
+            "threshold must be between {} and the number of shares",
+            MIN_SHAMIR_THRESHOLD
+        )
+        .into());
+    }
+    if secret.is_empty() {
+        return Err("cannot split an empty secret".into());
+    }
+    let mut shares: Vec<ShamirShare> = (1..=share_count)
+        .map(|x| ShamirShare {
+            x,
+            y: Vec::with_capacity(secret.len()),
+        })
+        .collect();
+    // f(x) = secret byte + random coefficients of degree 1 to threshold - 1
+    let mut coefficients = vec![0u8; threshold as usize];
+    for secret_byte in secret {
+        coefficients[0] = *secret_byte;
+        if let Err(e) = rand_bytes(&mut coefficients[1..]) {
+            coefficients.zeroize();
+            return Err(e.into());
+        }
+        for share in shares.iter_mut() {
+            let y = coefficients
+                .iter()
+                .rev()
+                .fold(0u8, |y, coefficient| gf_mul(y, share.x) ^ coefficient);
+            share.y.push(y);
+        }
+    }
+    coefficients.zeroize();
+    Ok(shares)
+}
+
+/// Restores the secret from the shares by Lagrange interpolation at x = 0.
+/// Too few shares or shares of different secrets produce garbage that is
+/// rejected later on, when the rsa private key cannot be decrypted.
+///
+/// # Arguments
+///
+/// - `shares`: at least `threshold` shares with different x-coordinates
+///
+/// # Returns
+///
+/// - `Result<SecStr, Box<dyn Error>>`
+pub fn combine_shares(shares: &[ShamirShare]) -> Result<SecStr, Box<dyn Error>> {
+    if shares.len() < MIN_SHAMIR_THRESHOLD as usize {
+        return Err(format!("at least {} shares are needed", MIN_SHAMIR_THRESHOLD).into());
+    }
+    let secret_length = shares[0].y.len();
+    for (i, share) in shares.iter().enumerate() {
+        if share.y.len() != secret_length {
+            return Err("shares belong to different secrets".into());
+        }
+        if share.x == 0 || shares[..i].iter().any(|s| s.x == share.x) {
+            return Err("shares must have different x-coordinates".into());
+        }
+    }
+    let mut secret = vec![0u8; secret_length];
+    for share in shares {
+        // Lagrange basis polynomial at 0, subtraction is xor in GF(2^8)
+        let basis = shares
+            .iter()
+            .filter(|other| other.x != share.x)
+            .fold(1u8, |basis, other| {
+                gf_mul(basis, gf_mul(other.x, gf_inv(other.x ^ share.x)))
+            });
+        for (secret_byte, y) in secret.iter_mut().zip(&share.y) {
+            *secret_byte ^= gf_mul(basis, *y);
+        }
+    }
+    Ok(SecStr::new(secret))
+}
+
+/// Collects the shares of the password that are entered by the
+/// administrators until the threshold is reached. Every administrator
+/// can enter one share, shares that are too old are discarded.
+#[derive(Default)]
+pub struct ShamirShareCollector {
+    shares: HashMap<String, (DateTime<Utc>, ShamirShare)>,
+}
+
+impl ShamirShareCollector {
+    /// Creates a new, empty collector
+    pub fn new() -> ShamirShareCollector {
+        ShamirShareCollector {
+            shares: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of shares that have been entered so far.
+    pub fn collected_shares(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// Adds the share of an administrator. The password is restored
+    /// and the collected shares are discarded once the threshold is reached.
+    ///
+    /// # Arguments
+    ///
+    /// - `user_name`:            the administrator who entered the share
+    /// - `share`:                the share
+    /// - `shamir_configuration`: threshold and time window
+    ///
+    /// # Returns
+    ///
+    /// - `Result<Option<SecStr>, Box<dyn Error>>`: the password if the threshold is reached
+    pub fn add_share(
+        &mut self,
+        user_name: &str,
+        share: ShamirShare,
+        shamir_configuration: &ShamirConfiguration,
+    ) -> Result<Option<SecStr>, Box<dyn Error>> {
+        self.remove_expired_shares(shamir_configuration.max_share_age_seconds);
+        if self
+            .shares
+            .iter()
+            .any(|(other_user_name, (_, other_share))| {
+                other_user_name != user_name && other_share.x == share.x
+            })
+        {
+            return Err("share has already been entered by another administrator".into());
+        }
+        self.shares
+            .insert(user_name.to_string(), (Utc::now(), share));
+        if self.shares.len() < shamir_configuration.threshold as usize {
+            return Ok(None);
+        }
+        let shares: Vec<ShamirShare> = self.shares.drain().map(|(_, (_, share))| share).collect();
+        combine_shares(&shares).map(Some)
+    }
+
+    /// Discards shares that are older than `max_age_in_seconds`.
+    pub fn remove_expired_shares(&mut self, max_age_in_seconds: i64) {
+        let time_to_delete = Utc::now() - Duration::seconds(max_age_in_seconds);
+        self.shares
+            .retain(|_, (time_stamp, _)| *time_stamp > time_to_delete);
+    }
+
+    /// Discards all collected shares.
+    pub fn clear(&mut self) {
+        self.shares.clear();
+    }
+}
@@ -1,4 +1,7 @@
-use lmtyas::aes_functions::{DecryptAes, EncryptAes};
+use lmtyas::aes_functions::{
+    create_passphrase_salt, unwrap_key_with_passphrase, wrap_key_with_passphrase, DecryptAes,
+    EncryptAes,
+};
 use regex::Regex;
 
 #[test]
@@ -45,3 +48,52 @@ fn aes_functions() {
         "encryption iv should not be equal after 2 calls!"
     );
 }
+
+#[test]
+fn aes_key_wrapped_with_passphrase() {
+    const PLAINTEXT: &str = "PASSWORD";
+    const PASSPHRASE: &str = "correct horse battery staple";
+    let aes_encrypted = PLAINTEXT.to_string().to_aes_enrypted_b64().unwrap();
+    let salt = create_passphrase_salt().unwrap();
+    let wrapped_key =
+        wrap_key_with_passphrase(&aes_encrypted.encryption_key, PASSPHRASE, &salt).unwrap();
+    assert_ne!(
+        aes_encrypted.encryption_key, wrapped_key,
+        "wrapped key should not be equal to the key!"
+    );
+    assert!(
+        unwrap_key_with_passphrase(&wrapped_key, "wrong passphrase", &salt).is_err(),
+        "key should not be unwrapped with a wrong passphrase!"
+    );
+    let unwrapped_key = unwrap_key_with_passphrase(&wrapped_key, PASSPHRASE, &salt).unwrap();
+    assert_eq!(
+        aes_encrypted.encryption_key, unwrapped_key,
+        "unwrapped key does not match the key!"
+    );
+    let decrypted = aes_encrypted
+        .encrypted_data
+        .decrypt_b64_aes(&unwrapped_key, &aes_encrypted.encryption_iv);
+    assert_eq!(
+        PLAINTEXT,
+        decrypted.unwrap(),
+        "aes decrypted message does not match plaintext!"
+    );
+}
+
+#[test]
+fn aes_detects_modified_data() {
+    let aes_encrypted = "PASSWORD".to_string().to_aes_enrypted_b64().unwrap();
+    let mut encrypted_data = aes_encrypted.encrypted_data.into_bytes();
+    encrypted_data[0] = if encrypted_data[0] == b'A' {
+        b'B'
+    } else {
+        b'A'
+    };
+    let modified_data = String::from_utf8(encrypted_data).unwrap();
+    assert!(
+        modified_data
+            .decrypt_b64_aes(&aes_encrypted.encryption_key, &aes_encrypted.encryption_iv)
+            .is_err(),
+        "modified aes encrypted data should not be decrypted!"
+    );
+}
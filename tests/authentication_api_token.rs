@@ -0,0 +1,32 @@
+use lmtyas::authentication_api_token::ApiToken;
+
+#[test]
+fn authentication_api_token() {
+    let api_token: ApiToken = serde_json::from_str(
+        r#"{
+            "name": "provisioning",
+            "token": "2b7f0e43-8c1c-4a7b-9d8e-0f6b9f3c1a55",
+            "allowed_senders": ["walter@acme.local"]
+        }"#,
+    )
+    .unwrap();
+    assert!(
+        api_token.matches("2b7f0e43-8c1c-4a7b-9d8e-0f6b9f3c1a55"),
+        "token should match!"
+    );
+    assert!(
+        !api_token.matches("2b7f0e43-8c1c-4a7b-9d8e-0f6b9f3c1a56"),
+        "token should not match!"
+    );
+    assert!(!api_token.matches(""), "empty token should not match!");
+    assert!(api_token.is_allowed_sender("walter@acme.local"));
+    assert!(
+        api_token.is_allowed_sender("Walter@ACME.local"),
+        "mail addresses are not case sensitive!"
+    );
+    assert!(
+        !api_token.is_allowed_sender("bob@acme.local"),
+        "token must not send secrets in the name of anyone else!"
+    );
+    assert!(!api_token.is_allowed_sender(""));
+}
@@ -0,0 +1,112 @@
+use lmtyas::cli_parser::*;
+
+#[test]
+fn cli_client_parameters() {
+    let arg_matches = build_cli_client_command()
+        .try_get_matches_from([
+            CLI_PROGRAM_NAME,
+            "--insecure",
+            CMD_SEND,
+            "--from",
+            "walter@acme.local",
+            "--to",
+            "bob@acme.local",
+            "--context",
+            "VPN",
+            "--secret-file",
+            "-",
+            "--valid-for-hours",
+            "24",
+        ])
+        .unwrap();
+    assert_eq!(
+        arg_matches.get_one::<String>(ARG_URL).unwrap(),
+        "https://127.0.0.1:8844",
+        "default url expected!"
+    );
+    assert!(arg_matches.get_flag(ARG_INSECURE));
+    let (command, send_arg_matches) = arg_matches.subcommand().unwrap();
+    assert_eq!(command, CMD_SEND);
+    assert_eq!(
+        send_arg_matches.get_one::<String>(ARG_TO).unwrap(),
+        "bob@acme.local"
+    );
+    assert_eq!(
+        send_arg_matches.get_one::<String>(ARG_CONTEXT).unwrap(),
+        "VPN"
+    );
+    assert_eq!(
+        send_arg_matches.get_one::<String>(ARG_SECRET_FILE).unwrap(),
+        "-"
+    );
+    assert_eq!(
+        send_arg_matches.get_one::<i64>(ARG_VALID_FOR_HOURS),
+        Some(&24)
+    );
+
+    let missing_receiver = build_cli_client_command().try_get_matches_from([
+        CLI_PROGRAM_NAME,
+        CMD_SEND,
+        "--from",
+        "walter@acme.local",
+        "--context",
+        "VPN",
+        "--secret-file",
+        "-",
+    ]);
+    assert!(
+        missing_receiver.is_err(),
+        "receiver must be a required parameter!"
+    );
+}
+
+#[test]
+fn cli_server_subcommands() {
+    let arg_matches = build_cli_command()
+        .try_get_matches_from([
+            "lmtyas",
+            CMD_KEYGEN,
+            "--private-key-file",
+            "private.key",
+            "--public-key-file",
+            "public.key",
+        ])
+        .unwrap();
+    let (command, keygen_arg_matches) = arg_matches.subcommand().unwrap();
+    assert_eq!(command, CMD_KEYGEN);
+    assert_eq!(
+        keygen_arg_matches.get_one::<u32>(ARG_KEY_BITS),
+        Some(&4096),
+        "default key size expected!"
+    );
+
+    let arg_matches = build_cli_command()
+        .try_get_matches_from([
+            "lmtyas",
+            CMD_VERIFY_CONFIG,
+            "--config-file",
+            "lmtyas-config.json",
+        ])
+        .unwrap();
+    let (command, verify_config_arg_matches) = arg_matches.subcommand().unwrap();
+    assert_eq!(command, CMD_VERIFY_CONFIG);
+    assert_eq!(
+        verify_config_arg_matches
+            .get_one::<String>(ARG_CONFIG_FILE)
+            .unwrap(),
+        "lmtyas-config.json"
+    );
+
+    assert!(
+        build_cli_command()
+            .try_get_matches_from(["lmtyas"])
+            .is_err(),
+        "the webservice needs a configuration file!"
+    );
+    assert!(
+        build_cli_command()
+            .try_get_matches_from(["lmtyas", CMD_CHANGE_PASSPHRASE])
+            .is_err(),
+        "private key file must be a required parameter!"
+    );
+}
@@ -0,0 +1,228 @@
+use lmtyas::configuration::{
+    ApplicationConfiguration, ConfigurationFile, DEFAULT_MAX_SECRET_AGE_SECONDS,
+};
+use secstr::SecStr;
+use std::error::Error;
+use std::path::Path;
+
+const WORKSPACE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+const RSA_PASSPHRASE: &str = "12345678901234";
+
+/// Reads the development configuration after `modify` has changed its json
+fn read_modified_configuration<F: FnOnce(&mut serde_json::Value)>(
+    modify: F,
+) -> Result<ConfigurationFile, Box<dyn Error>> {
+    let mut json: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(Path::new(WORKSPACE_DIR).join("conf.dev/lmtyas-config.json"))
+            .unwrap(),
+    )
+    .unwrap();
+    modify(&mut json);
+    let config_file_path = std::env::temp_dir().join(format!(
+        "lmtyas-config-{}-{:?}.json",
+        std::process::id(),
+        std::thread::current().id()
+    ));
+    std::fs::write(&config_file_path, json.to_string()).unwrap();
+    let configuration_file = ConfigurationFile::read_from_file(&config_file_path);
+    std::fs::remove_file(&config_file_path).unwrap();
+    configuration_file
+}
+
+/// Sets the password and loads the keys like the sysop page does
+fn unseal(application_configuration: &ApplicationConfiguration) {
+    application_configuration
+        .rsa_password
+        .write()
+        .unwrap()
+        .rsa_private_key_password = Some(SecStr::from(RSA_PASSPHRASE));
+    application_configuration.load_master_key().unwrap();
+}
+
+/// Checks that the password, the keys and the sessions are gone
+fn assert_sealed(application_configuration: &ApplicationConfiguration) {
+    assert!(application_configuration
+        .rsa_password
+        .read()
+        .unwrap()
+        .rsa_private_key_password
+        .is_none());
+    assert!(
+        application_configuration
+            .master_key
+            .read()
+            .unwrap()
+            .key_id()
+            .is_none(),
+        "keys must be dropped after sealing!"
+    );
+    assert!(application_configuration
+        .master_key_loaded_at
+        .read()
+        .unwrap()
+        .is_none());
+    assert!(
+        application_configuration
+            .shared_authenticated_users
+            .read()
+            .unwrap()
+            .authenticated_users_hashmap
+            .is_empty(),
+        "sessions must be removed after sealing!"
+    );
+}
+
+#[actix_rt::test]
+async fn seal() {
+    let application_configuration = ApplicationConfiguration::read_from_file(
+        Path::new(WORKSPACE_DIR).join("conf.dev/lmtyas-config.json"),
+    )
+    .await;
+    unseal(&application_configuration);
+    assert!(application_configuration
+        .master_key
+        .read()
+        .unwrap()
+        .key_id()
+        .is_some());
+    application_configuration
+        .shared_authenticated_users
+        .write()
+        .unwrap()
+        .new_cookie_uuid_for("walter", "Walter", "Linz", "walter@acme.local", "127.0.0.1")
+        .unwrap();
+
+    application_configuration.seal().unwrap();
+    assert_sealed(&application_configuration);
+
+    // the server is sealed again after `auto_seal_seconds`
+    unseal(&application_configuration);
+    application_configuration.auto_seal(3600);
+    assert!(application_configuration
+        .master_key_loaded_at
+        .read()
+        .unwrap()
+        .is_some());
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    application_configuration.auto_seal(1);
+    assert_sealed(&application_configuration);
+}
+
+#[test]
+fn max_secret_age_seconds() {
+    // configuration files of older versions do not have the setting
+    let configuration_file = read_modified_configuration(|json| {
+        json.as_object_mut()
+            .unwrap()
+            .remove("max_secret_age_seconds");
+    })
+    .unwrap();
+    assert_eq!(
+        configuration_file.max_secret_age_seconds,
+        DEFAULT_MAX_SECRET_AGE_SECONDS
+    );
+    assert!(read_modified_configuration(|json| {
+        json["max_secret_age_seconds"] = serde_json::json!(0);
+    })
+    .is_err());
+}
+
+#[test]
+fn auto_seal_seconds() {
+    let configuration_file = read_modified_configuration(|json| {
+        json.as_object_mut().unwrap().remove("auto_seal_seconds");
+    })
+    .unwrap();
+    assert!(configuration_file.auto_seal_seconds.is_none());
+    for invalid_seconds in [0, -1] {
+        assert!(
+            read_modified_configuration(|json| {
+                json["auto_seal_seconds"] = serde_json::json!(invalid_seconds);
+            })
+            .is_err(),
+            "auto_seal_seconds of {} must be rejected!",
+            invalid_seconds
+        );
+    }
+    let configuration_file = read_modified_configuration(|json| {
+        json["auto_seal_seconds"] = serde_json::json!(43200);
+    })
+    .unwrap();
+    assert_eq!(configuration_file.auto_seal_seconds, Some(43200));
+}
+
+#[test]
+fn secret_valid_for_hours() {
+    let configuration_file = read_modified_configuration(|json| {
+        json.as_object_mut()
+            .unwrap()
+            .remove("secret_valid_for_hours");
+    })
+    .unwrap();
+    assert!(configuration_file.secret_valid_for_hours.is_empty());
+    for invalid_hours in [0, -1, 169] {
+        assert!(
+            read_modified_configuration(|json| {
+                json["secret_valid_for_hours"] = serde_json::json!([1, invalid_hours]);
+            })
+            .is_err(),
+            "{} hours must be rejected with max_secret_age_seconds of 7 days!",
+            invalid_hours
+        );
+    }
+    assert!(read_modified_configuration(|json| {
+        json["secret_valid_for_hours"] = serde_json::json!([1, 168]);
+    })
+    .is_ok());
+}
+
+#[test]
+fn accept_pkcs1_links_until() {
+    let configuration_file = read_modified_configuration(|json| {
+        json.as_object_mut()
+            .unwrap()
+            .remove("accept_pkcs1_links_until");
+    })
+    .unwrap();
+    assert!(
+        !configuration_file.accepts_pkcs1_links(),
+        "PKCS#1 v1.5 links must not be accepted by default!"
+    );
+    let configuration_file = read_modified_configuration(|json| {
+        json["accept_pkcs1_links_until"] = serde_json::json!("2999-12-31T00:00:00Z");
+    })
+    .unwrap();
+    assert!(configuration_file.accepts_pkcs1_links());
+    let configuration_file = read_modified_configuration(|json| {
+        json["accept_pkcs1_links_until"] = serde_json::json!("2020-12-31T00:00:00Z");
+    })
+    .unwrap();
+    assert!(
+        !configuration_file.accepts_pkcs1_links(),
+        "PKCS#1 v1.5 links must not be accepted after the end date!"
+    );
+    assert!(read_modified_configuration(|json| {
+        json["accept_pkcs1_links_until"] = serde_json::json!("next year");
+    })
+    .is_err());
+}
+
+#[test]
+fn shamir_configuration_without_password_file() {
+    let shamir_configuration = serde_json::json!({
+        "threshold": 2,
+        "max_share_age_seconds": 600
+    });
+    assert!(
+        read_modified_configuration(|json| {
+            json["shamir_configuration"] = shamir_configuration.clone();
+            json["rsa_private_key_password_file"] = serde_json::json!("/etc/lmtyas/rsa-password");
+        })
+        .is_err(),
+        "a password file must not unlock the keys without the shares!"
+    );
+    assert!(read_modified_configuration(|json| {
+        json["shamir_configuration"] = shamir_configuration.clone();
+    })
+    .is_ok());
+}
@@ -0,0 +1,102 @@
+use lmtyas::ec_functions::ec_key_id;
+use lmtyas::master_key_trait::{read_master_key_from_files, MasterKey};
+use lmtyas::secret_functions::{Secret, SECRET_FORMAT_VERSION};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::symm::Cipher;
+use secstr::SecStr;
+
+const EC_PASSPHRASE: &str = "12345678901234";
+const PLAINTEXT: &str = "plaintext";
+
+/// Writes the key pair as passphrase protected PEM files
+/// and loads them as master key.
+fn load_master_key(name: &str, private_key: &PKey<Private>) -> Box<dyn MasterKey> {
+    let key_directory =
+        std::env::temp_dir().join(format!("lmtyas-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&key_directory).unwrap();
+    std::fs::write(
+        key_directory.join("private.key"),
+        private_key
+            .private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), EC_PASSPHRASE.as_bytes())
+            .unwrap(),
+    )
+    .unwrap();
+    std::fs::write(
+        key_directory.join("public.key"),
+        private_key.public_key_to_pem().unwrap(),
+    )
+    .unwrap();
+    let master_key = read_master_key_from_files(
+        key_directory.join("private.key"),
+        key_directory.join("public.key"),
+        None,
+        &SecStr::from(EC_PASSPHRASE),
+    );
+    std::fs::remove_dir_all(&key_directory).unwrap();
+    master_key.unwrap()
+}
+
+fn generate_p256_key() -> PKey<Private> {
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+    PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+}
+
+#[test]
+fn ec_functions() {
+    let p256_key = generate_p256_key();
+    let x25519_key = PKey::generate_x25519().unwrap();
+
+    for (name, private_key) in [("p256", &p256_key), ("x25519", &x25519_key)] {
+        let master_key = load_master_key(name, private_key);
+        assert_eq!(
+            master_key.key_id(),
+            Some(ec_key_id(private_key).unwrap()),
+            "{}: key id does not match the loaded key!",
+            name
+        );
+
+        let encrypted = master_key.encrypt_str(PLAINTEXT).unwrap();
+        assert_ne!(
+            encrypted,
+            master_key.encrypt_str(PLAINTEXT).unwrap(),
+            "{}: ec encrypted data should not be equal after 2 calls!",
+            name
+        );
+        assert_eq!(
+            PLAINTEXT,
+            master_key.decrypt_str(&encrypted).unwrap(),
+            "{}: ec decrypted message does not match plaintext!",
+            name
+        );
+        assert!(
+            master_key.decrypt_str_legacy(&encrypted).is_err(),
+            "{}: legacy data can only be decrypted with rsa keys!",
+            name
+        );
+
+        let secret: Secret = serde_json::from_str(
+            r#"{"FromEmail":"a","FromDisplayName":"b","ToEmail":"c","ToDisplayName":"d","Context":"e","Secret":"f"}"#,
+        )
+        .unwrap();
+        let encrypted_secret = secret.to_encrypted(master_key.as_ref()).unwrap();
+        assert_eq!(encrypted_secret.version, SECRET_FORMAT_VERSION);
+        assert_eq!(encrypted_secret.key_id, master_key.key_id());
+        let decrypted_secret = encrypted_secret.to_decrypted(master_key.as_ref()).unwrap();
+        assert_eq!(
+            decrypted_secret.secret, "f",
+            "{}: secret cannot be decrypted!",
+            name
+        );
+    }
+
+    // data of one key cannot be decrypted with another key of the same curve
+    let master_key = load_master_key("p256-first", &p256_key);
+    let other_master_key = load_master_key("p256-other", &generate_p256_key());
+    let encrypted = master_key.encrypt_str(PLAINTEXT).unwrap();
+    assert!(
+        other_master_key.decrypt_str(&encrypted).is_err(),
+        "data must not be decrypted with another key!"
+    );
+}
@@ -0,0 +1,231 @@
+use chrono::{Duration, Utc};
+use lmtyas::handler_functions::{
+    parse_bulk_secret_rows, split_receivers, unique_receiver_emails, BulkSecretResult,
+    BulkSecretRow, ReceiverEntry, SecretRequestPayload, TellSecretError,
+};
+use lmtyas::rsa_functions::RsaKeys;
+use secstr::SecStr;
+use std::path::Path;
+
+const WORKSPACE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+const BULK_CSV: &str = "receiver,context,secret,valid_for_hours
+bob@acme.local, VPN , s3cr3t ,24
+alice@acme.local,wifi,t0p-s3cr3t
+mallory@acme.local,printer
+eve@acme.local,mail,pa55w0rd,forever
+";
+
+/// Returns the rsa keys of the development configuration
+fn load_rsa_keys() -> RsaKeys {
+    let mut rsa_keys = RsaKeys::new();
+    rsa_keys
+        .read_from_files(
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_private.key"),
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_public.key"),
+            &SecStr::from("12345678901234"),
+        )
+        .unwrap();
+    rsa_keys
+}
+
+/// Returns a parsed row for building the report
+fn bulk_secret_row(receiver: &str) -> BulkSecretRow {
+    BulkSecretRow {
+        receiver: receiver.to_string(),
+        context: "VPN".to_string(),
+        secret: String::new(),
+        valid_for_hours: None,
+    }
+}
+
+#[test]
+fn parse_bulk_secret_rows_csv() {
+    let rows = parse_bulk_secret_rows(BULK_CSV.as_bytes());
+    assert_eq!(rows.len(), 4);
+
+    let row = rows[0].as_ref().unwrap();
+    assert_eq!(row.receiver, "bob@acme.local");
+    assert_eq!(row.context, "VPN", "fields must be trimmed!");
+    assert_eq!(row.secret, "s3cr3t", "fields must be trimmed!");
+    assert_eq!(row.valid_for_hours, Some(24));
+
+    let row = rows[1].as_ref().unwrap();
+    assert_eq!(row.receiver, "alice@acme.local");
+    assert_eq!(row.secret, "t0p-s3cr3t");
+    assert_eq!(row.valid_for_hours, None, "valid_for_hours is optional!");
+
+    // a missing secret or a lifetime that is no number makes the row invalid
+    for (index, row) in rows.iter().enumerate().skip(2) {
+        let e = row
+            .as_ref()
+            .err()
+            .expect("malformed row must not be parsed!");
+        let result = BulkSecretResult::invalid_row(index + 1, e);
+        assert_eq!(result.row, index + 1);
+        assert_eq!(result.status, "invalid");
+        assert!(!result.message.is_empty());
+        assert!(result.to_email.is_empty());
+    }
+}
+
+#[test]
+fn bulk_secret_result() {
+    let result = BulkSecretResult::from_tell_result(
+        1,
+        bulk_secret_row("bob@acme.local"),
+        Ok("2b7f0e43-8c1c-4a7b-9d8e-0f6b9f3c1a55".to_string()),
+    );
+    assert_eq!(result.row, 1);
+    assert_eq!(result.to_email, "bob@acme.local");
+    assert_eq!(result.context, "VPN");
+    assert_eq!(result.status, "stored");
+    assert!(result.message.is_empty());
+
+    for (tell_secret_error, status) in [
+        (
+            TellSecretError::InvalidInput("ERROR: invalid valid_for_hours".to_string()),
+            "invalid",
+        ),
+        (
+            TellSecretError::UnknownReceiver("ERROR: receiver unknown".to_string()),
+            "unknown receiver",
+        ),
+        (
+            TellSecretError::MailFailed("ERROR: cannot send mail".to_string()),
+            "mail failed",
+        ),
+        (
+            TellSecretError::Failed("ERROR: cannot store secret".to_string()),
+            "failed",
+        ),
+    ] {
+        let result = BulkSecretResult::from_tell_result(
+            2,
+            bulk_secret_row("alice@acme.local"),
+            Err(tell_secret_error),
+        );
+        assert_eq!(result.row, 2);
+        assert_eq!(result.to_email, "alice@acme.local");
+        assert_eq!(result.status, status);
+        assert!(
+            !result.message.starts_with("ERROR: "),
+            "the report must not look like an error response!"
+        );
+    }
+    let result = BulkSecretResult::from_tell_result(
+        3,
+        bulk_secret_row("eve@acme.local"),
+        Err(TellSecretError::Failed(
+            "ERROR: cannot store secret".to_string(),
+        )),
+    );
+    assert_eq!(result.message, "cannot store secret");
+}
+
+#[test]
+fn split_receivers_into_groups_and_emails() {
+    let receiver_entries =
+        split_receivers(" bob@acme.local, cn=netops,,CN=Admins , alice@acme.local ").unwrap();
+    assert_eq!(
+        receiver_entries,
+        vec![
+            ReceiverEntry::Email("bob@acme.local".to_string()),
+            ReceiverEntry::Group("netops".to_string()),
+            ReceiverEntry::Group("Admins".to_string()),
+            ReceiverEntry::Email("alice@acme.local".to_string()),
+        ]
+    );
+    assert!(split_receivers(" , ").unwrap().is_empty());
+    let too_long = format!("{}@acme.local", "a".repeat(128));
+    assert!(matches!(
+        split_receivers(&format!("bob@acme.local,{}", too_long)),
+        Err(TellSecretError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn unique_receiver_emails_after_group_expansion() {
+    let receiver_emails = unique_receiver_emails(vec![
+        "bob@acme.local".to_string(),
+        "alice@acme.local".to_string(),
+        "Bob@Acme.local".to_string(),
+    ])
+    .unwrap();
+    assert_eq!(receiver_emails, vec!["bob@acme.local", "alice@acme.local"]);
+    assert!(matches!(
+        unique_receiver_emails(Vec::new()),
+        Err(TellSecretError::InvalidInput(_))
+    ));
+
+    // a single receiver plus the members of a group, 25 receivers are allowed
+    let mut to_emails = vec!["bob@acme.local".to_string()];
+    to_emails.extend((1..25).map(|i| format!("member{}@acme.local", i)));
+    assert_eq!(unique_receiver_emails(to_emails.clone()).unwrap().len(), 25);
+    // duplicates do not count
+    to_emails.push("MEMBER1@acme.local".to_string());
+    assert_eq!(unique_receiver_emails(to_emails.clone()).unwrap().len(), 25);
+    to_emails.push("member25@acme.local".to_string());
+    assert!(matches!(
+        unique_receiver_emails(to_emails),
+        Err(TellSecretError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn secret_request_payload() {
+    let rsa_keys = load_rsa_keys();
+    let secret_request_payload = SecretRequestPayload {
+        requester: "bob@acme.local".to_string(),
+        expires_at: (Utc::now() + Duration::hours(1)).to_rfc3339(),
+        context: "VPN; token".to_string(),
+    };
+    let encrypted_url_payload = secret_request_payload
+        .to_encrypted_url_payload(&rsa_keys)
+        .unwrap();
+    assert!(
+        !encrypted_url_payload.contains("bob@acme.local") && !encrypted_url_payload.contains("VPN"),
+        "requester and context must be encrypted!"
+    );
+    let decrypted_payload =
+        SecretRequestPayload::from_encrypted_url_payload(&encrypted_url_payload, &rsa_keys)
+            .unwrap();
+    assert_eq!(decrypted_payload, secret_request_payload);
+    assert!(!decrypted_payload.is_expired());
+
+    let expired_payload = SecretRequestPayload {
+        expires_at: (Utc::now() - Duration::seconds(1)).to_rfc3339(),
+        ..secret_request_payload
+    };
+    let encrypted_expired_payload = expired_payload.to_encrypted_url_payload(&rsa_keys).unwrap();
+    assert!(
+        SecretRequestPayload::from_encrypted_url_payload(&encrypted_expired_payload, &rsa_keys)
+            .unwrap()
+            .is_expired(),
+        "secret request should have expired!"
+    );
+
+    // a modified payload or the parts of two different links are rejected
+    let (encrypted_data_key, encrypted_payload) = encrypted_url_payload.split_once(';').unwrap();
+    let (_, other_encrypted_payload) = encrypted_expired_payload.split_once(';').unwrap();
+    let mut tampered_payload = encrypted_payload.to_string();
+    let last_char = if tampered_payload.ends_with('A') {
+        "B"
+    } else {
+        "A"
+    };
+    tampered_payload.replace_range(tampered_payload.len() - 1.., last_char);
+    for invalid_url_payload in [
+        format!("{};{}", encrypted_data_key, tampered_payload),
+        format!("{};{}", encrypted_data_key, other_encrypted_payload),
+        encrypted_payload.to_string(),
+        String::new(),
+    ] {
+        assert!(
+            SecretRequestPayload::from_encrypted_url_payload(&invalid_url_payload, &rsa_keys)
+                .is_err(),
+            "invalid link must be rejected: {}",
+            &invalid_url_payload
+        );
+    }
+}
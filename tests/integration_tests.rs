@@ -5,8 +5,7 @@ pub use lmtyas::authentication_ldap::LdapLogin;
 use lmtyas::configuration::ApplicationConfiguration;
 #[cfg(any(feature = "ldap-auth", feature = "oidc-auth-ldap"))]
 use lmtyas::ldap_common::LdapSearchResult;
-#[cfg(feature = "mail-noauth-notls")]
-pub use lmtyas::mail_noauth_notls::SendEMail;
+pub use lmtyas::mail_configuration::SendEMail;
 use std::path::Path;
 
 /// testing the functions that need external services in one go.
@@ -0,0 +1,114 @@
+use lmtyas::configuration::ConfigurationFile;
+use lmtyas::key_management::{
+    change_private_key_passphrase, generate_rsa_key_files, verify_master_key,
+};
+use lmtyas::rsa_functions::{key_id, RsaKeys};
+use secstr::SecStr;
+use std::path::Path;
+
+const WORKSPACE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+const RSA_PASSPHRASE: &str = "12345678901234";
+const NEW_RSA_PASSPHRASE: &str = "43210987654321";
+
+#[test]
+fn key_management() {
+    let key_directory =
+        std::env::temp_dir().join(format!("lmtyas-key-management-{}", std::process::id()));
+    std::fs::create_dir_all(&key_directory).unwrap();
+    let private_key_file = key_directory.join("private.key");
+    let public_key_file = key_directory.join("public.key");
+
+    assert!(
+        generate_rsa_key_files(
+            &private_key_file,
+            &public_key_file,
+            1024,
+            &SecStr::from(RSA_PASSPHRASE)
+        )
+        .is_err(),
+        "rsa keys < 2048 bits must be rejected!"
+    );
+    assert!(
+        generate_rsa_key_files(
+            &private_key_file,
+            &public_key_file,
+            2048,
+            &SecStr::from("short")
+        )
+        .is_err(),
+        "short passphrase must be rejected!"
+    );
+    let rsa_key_id = generate_rsa_key_files(
+        &private_key_file,
+        &public_key_file,
+        2048,
+        &SecStr::from(RSA_PASSPHRASE),
+    )
+    .unwrap();
+    assert!(
+        generate_rsa_key_files(
+            &private_key_file,
+            &public_key_file,
+            2048,
+            &SecStr::from(RSA_PASSPHRASE)
+        )
+        .is_err(),
+        "existing keys must not be overwritten!"
+    );
+
+    change_private_key_passphrase(
+        &private_key_file,
+        &SecStr::from(RSA_PASSPHRASE),
+        &SecStr::from(NEW_RSA_PASSPHRASE),
+    )
+    .unwrap();
+    assert!(change_private_key_passphrase(
+        &private_key_file,
+        &SecStr::from(RSA_PASSPHRASE),
+        &SecStr::from(NEW_RSA_PASSPHRASE),
+    )
+    .is_err());
+    let mut rsa_keys = RsaKeys::new();
+    let old_passphrase_result = rsa_keys.read_from_files(
+        &private_key_file,
+        &public_key_file,
+        &SecStr::from(RSA_PASSPHRASE),
+    );
+    let new_passphrase_result = rsa_keys.read_from_files(
+        &private_key_file,
+        &public_key_file,
+        &SecStr::from(NEW_RSA_PASSPHRASE),
+    );
+    std::fs::remove_dir_all(&key_directory).unwrap();
+
+    assert!(old_passphrase_result.is_err());
+    new_passphrase_result.expect("cannot load keys with new passphrase!");
+    assert_eq!(
+        key_id(rsa_keys.rsa_private_key.as_ref().unwrap()).unwrap(),
+        rsa_key_id
+    );
+}
+
+#[test]
+fn verify_config() {
+    let configuration_file = ConfigurationFile::read_from_file(
+        Path::new(WORKSPACE_DIR).join("conf.dev/lmtyas-config.json"),
+    )
+    .unwrap();
+    let problems = configuration_file.verify();
+    // the self signed certificate is not part of the repository
+    if !Path::new(&configuration_file.ssl_private_key_file).exists() {
+        assert!(problems
+            .iter()
+            .any(|problem| problem.contains(&configuration_file.ssl_private_key_file)));
+    }
+    assert!(
+        !problems
+            .iter()
+            .any(|problem| problem.contains("mail_template")),
+        "mail templates of conf.dev must be found: {:?}",
+        problems
+    );
+    assert!(verify_master_key(&configuration_file, &SecStr::from(RSA_PASSPHRASE)).is_ok());
+    assert!(verify_master_key(&configuration_file, &SecStr::from(NEW_RSA_PASSPHRASE)).is_err());
+}
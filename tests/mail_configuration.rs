@@ -0,0 +1,152 @@
+use lmtyas::mail_configuration::{escape_html, SendEMailConfiguration};
+
+#[test]
+fn mail_configuration() {
+    let email_configuration: SendEMailConfiguration = serde_json::from_str(
+        r#"{
+            "mail_server_address": "127.0.0.1",
+            "mail_server_port": 2525,
+            "mail_from": "IT-department <do-not-reply@acme.local>",
+            "mail_subject": "Your new password for {Context}",
+            "mail_template_file": "conf.dev/mailtemplate.txt",
+            "mail_credentails": {
+                "mail_user": "lmtyas",
+                "mail_password": "s3cr3t"
+            },
+            "mail_ca_certificate_files": ["/etc/lmtyas/mail-ca.pem"]
+        }"#,
+    )
+    .unwrap();
+    assert!(
+        email_configuration.mail_credentails.is_some(),
+        "mail credentials should have been parsed!"
+    );
+    assert_eq!(
+        email_configuration
+            .mail_ca_certificate_files
+            .as_ref()
+            .unwrap()
+            .len(),
+        1,
+        "ca certificate files should have been parsed!"
+    );
+    let message = email_configuration.build_message(
+        "bruce.wayne@acme.local",
+        "Your new password for Gotham",
+        "Hello Bruce",
+        None,
+    );
+    assert!(message.is_ok(), "mail message should have been built!");
+    let message = email_configuration.build_message(
+        "not a mail address",
+        "Your new password for Gotham",
+        "Hello Bruce",
+        None,
+    );
+    assert!(message.is_err(), "invalid receiver should not be accepted!");
+    let message = email_configuration
+        .build_message(
+            "bruce.wayne@acme.local",
+            "Your new password for Gotham",
+            "Hello Bruce",
+            Some("<p>Hello Bruce</p>"),
+        )
+        .unwrap();
+    let formatted_message = String::from_utf8(message.formatted()).unwrap();
+    assert!(
+        formatted_message.contains("multipart/alternative"),
+        "html mails should be sent as multipart/alternative!"
+    );
+    assert!(
+        formatted_message.contains("text/plain") && formatted_message.contains("text/html"),
+        "both parts should be in the mail!"
+    );
+}
+
+#[test]
+fn html_escape() {
+    assert_eq!(
+        escape_html(r#"<script>alert("Joker's & Riddler's")</script>"#),
+        "&lt;script&gt;alert(&quot;Joker&#x27;s &amp; Riddler&#x27;s&quot;)&lt;/script&gt;"
+    );
+    assert_eq!(escape_html("Gotham City"), "Gotham City");
+}
+
+#[test]
+fn localized_mail_templates() {
+    let email_configuration: SendEMailConfiguration = serde_json::from_str(
+        r#"{
+            "mail_server_address": "127.0.0.1",
+            "mail_server_port": 2525,
+            "mail_from": "IT-department <do-not-reply@acme.local>",
+            "mail_subject": "Your new password for {Context}",
+            "mail_template_file": "conf.dev/mailtemplate.txt",
+            "localized_mail_templates": {
+                "de": {
+                    "mail_subject": "Ihr neues Passwort für {Context}",
+                    "mail_template_file": "conf.dev/mailtemplate.de.txt"
+                },
+                "fr-CA": {
+                    "mail_subject": "Votre nouveau mot de passe pour {Context}",
+                    "mail_template_file": "conf.dev/mailtemplate.fr.txt"
+                }
+            },
+            "fallback_language": "de"
+        }"#,
+    )
+    .unwrap();
+    let german = "Ihr neues Passwort für {Context}";
+    let french = "Votre nouveau mot de passe pour {Context}";
+    assert_eq!(
+        email_configuration
+            .mail_template_for_language(Some("de"))
+            .mail_subject,
+        german
+    );
+    assert_eq!(
+        email_configuration
+            .mail_template_for_language(Some("de_AT, en;q=0.8"))
+            .mail_subject,
+        german,
+        "primary language should be used if the region does not match!"
+    );
+    assert_eq!(
+        email_configuration
+            .mail_template_for_language(Some("FR-ca"))
+            .mail_subject,
+        french,
+        "languages should be compared case insensitive!"
+    );
+    assert_eq!(
+        email_configuration
+            .mail_template_for_language(Some("es"))
+            .mail_subject,
+        german,
+        "fallback language should be used!"
+    );
+    assert_eq!(
+        email_configuration
+            .mail_template_for_language(None)
+            .mail_subject,
+        german,
+        "fallback language should be used!"
+    );
+
+    let email_configuration: SendEMailConfiguration = serde_json::from_str(
+        r#"{
+            "mail_server_address": "127.0.0.1",
+            "mail_server_port": 2525,
+            "mail_from": "IT-department <do-not-reply@acme.local>",
+            "mail_subject": "Your new password for {Context}",
+            "mail_template_file": "conf.dev/mailtemplate.txt"
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(
+        email_configuration
+            .mail_template_for_language(Some("de"))
+            .mail_subject,
+        "Your new password for {Context}",
+        "default template should be used without localized templates!"
+    );
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "mail-file")]
+use lmtyas::mail_configuration::{SendEMail, SendEMailConfiguration};
+use std::fs::{read_dir, read_to_string, remove_dir_all};
+use std::path::Path;
+
+const WORKSPACE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+
+#[test]
+fn mail_file() {
+    let mail_directory = Path::new(WORKSPACE_DIR).join("ignore/test-mail-file");
+    let _ = remove_dir_all(&mail_directory);
+    let email_configuration: SendEMailConfiguration = serde_json::from_str(&format!(
+        r#"{{
+            "mail_server_address": "127.0.0.1",
+            "mail_server_port": 2525,
+            "mail_from": "IT-department <do-not-reply@acme.local>",
+            "mail_subject": "Your new password for {{Context}}",
+            "mail_template_file": "conf.dev/mailtemplate.txt",
+            "mail_file_directory": "{}"
+        }}"#,
+        mail_directory.display()
+    ))
+    .unwrap();
+
+    let send_mail_ok = email_configuration.send_mail("bob@acme.local", "mail_subject", "mail_body");
+    assert!(
+        send_mail_ok.is_ok(),
+        "mail should have been written to disk!"
+    );
+    let send_mail_fail =
+        email_configuration.send_mail("wrong mail address", "mail_subject", "mail_body");
+    assert!(
+        send_mail_fail.is_err(),
+        "should not be able to send mails with wrong address"
+    );
+
+    let mail_files: Vec<_> = read_dir(&mail_directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert_eq!(
+        mail_files.len(),
+        1,
+        "exactly one mail should have been written!"
+    );
+    assert_eq!(mail_files[0].extension().unwrap(), "eml");
+    let mail = read_to_string(&mail_files[0]).unwrap();
+    assert!(mail.contains("To: bob@acme.local"), "receiver is missing!");
+    assert!(
+        mail.contains("Subject: mail_subject"),
+        "subject is missing!"
+    );
+    assert!(mail.contains("mail_body"), "body is missing!");
+    remove_dir_all(&mail_directory).unwrap();
+}
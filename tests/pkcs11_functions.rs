@@ -0,0 +1,125 @@
+#![cfg(feature = "pkcs11")]
+use lmtyas::master_key_trait::MasterKey;
+use lmtyas::pkcs11_functions::{Pkcs11Configuration, Pkcs11Keys};
+use lmtyas::rsa_functions::RsaKeys;
+use secstr::SecStr;
+use std::path::Path;
+use std::process::Command;
+
+const WORKSPACE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+const RSA_PASSPHRASE: &str = "12345678901234";
+const TOKEN_PIN: &str = "98765432109876";
+const PLAINTEXT: &str = "plaintext";
+
+#[test]
+fn pkcs11_functions() {
+    // the test needs SoftHSM, the token is created in a new directory:
+    //
+    // `softhsm2-util --init-token --free --label lmtyas-test --pin ... --so-pin ...`
+    // `softhsm2-util --import key.pem --token lmtyas-test --label lmtyas --id 01 --pin ...`
+    let module_path = std::env::var("LMTYAS_PKCS11_MODULE")
+        .unwrap_or_else(|_| "/usr/lib/softhsm/libsofthsm2.so".to_string());
+    let token_directory = Path::new(WORKSPACE_DIR).join("ignore/test-softhsm");
+    let _ = std::fs::remove_dir_all(&token_directory);
+    std::fs::create_dir_all(token_directory.join("tokens")).unwrap();
+    let softhsm_conf = token_directory.join("softhsm2.conf");
+    std::fs::write(
+        &softhsm_conf,
+        format!(
+            "directories.tokendir = {}\n",
+            token_directory.join("tokens").display()
+        ),
+    )
+    .unwrap();
+    std::env::set_var("SOFTHSM2_CONF", &softhsm_conf);
+
+    let mut rsa_keys = RsaKeys::new();
+    rsa_keys
+        .read_from_files(
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_private.key"),
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_public.key"),
+            &SecStr::from(RSA_PASSPHRASE),
+        )
+        .unwrap();
+    // softhsm2-util only imports unencrypted PKCS#8 keys
+    let pkcs8_key = token_directory.join("key.pem");
+    let private_key = openssl::pkey::PKey::from_rsa(rsa_keys.rsa_private_key.clone().unwrap());
+    std::fs::write(
+        &pkcs8_key,
+        private_key.unwrap().private_key_to_pem_pkcs8().unwrap(),
+    )
+    .unwrap();
+    let init_token = Command::new("softhsm2-util")
+        .args(["--init-token", "--free", "--label", "lmtyas-test"])
+        .args(["--pin", TOKEN_PIN, "--so-pin", TOKEN_PIN])
+        .status()
+        .expect("cannot run softhsm2-util");
+    assert!(init_token.success(), "cannot create token!");
+    let import_key = Command::new("softhsm2-util")
+        .args(["--import", pkcs8_key.to_str().unwrap()])
+        .args(["--token", "lmtyas-test", "--label", "lmtyas", "--id", "01"])
+        .args(["--pin", TOKEN_PIN])
+        .status()
+        .expect("cannot run softhsm2-util");
+    std::fs::remove_file(&pkcs8_key).unwrap();
+    assert!(import_key.success(), "cannot import key!");
+    // a key that does not belong to the configured public key
+    let other_private_key =
+        openssl::pkey::PKey::from_rsa(openssl::rsa::Rsa::generate(2048).unwrap()).unwrap();
+    std::fs::write(
+        &pkcs8_key,
+        other_private_key.private_key_to_pem_pkcs8().unwrap(),
+    )
+    .unwrap();
+    let import_other_key = Command::new("softhsm2-util")
+        .args(["--import", pkcs8_key.to_str().unwrap()])
+        .args(["--token", "lmtyas-test", "--label", "other", "--id", "02"])
+        .args(["--pin", TOKEN_PIN])
+        .status()
+        .expect("cannot run softhsm2-util");
+    std::fs::remove_file(&pkcs8_key).unwrap();
+    assert!(import_other_key.success(), "cannot import other key!");
+
+    let pkcs11_configuration = Pkcs11Configuration {
+        module_path,
+        token_label: "lmtyas-test".to_string(),
+        key_label: "lmtyas".to_string(),
+    };
+    let other_pkcs11_configuration = Pkcs11Configuration {
+        key_label: "other".to_string(),
+        ..pkcs11_configuration.clone()
+    };
+    assert!(
+        Pkcs11Keys::open(
+            &other_pkcs11_configuration,
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_public.key"),
+            &SecStr::from(TOKEN_PIN),
+        )
+        .is_err(),
+        "a private key that does not match the public key must be rejected!"
+    );
+    let pkcs11_keys = Pkcs11Keys::open(
+        &pkcs11_configuration,
+        Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_public.key"),
+        &SecStr::from(TOKEN_PIN),
+    )
+    .unwrap();
+    assert_eq!(pkcs11_keys.key_id(), rsa_keys.rsa_key_id.clone());
+
+    // data encrypted by the key file is decrypted on the token and vice versa
+    let encrypted = rsa_keys.encrypt_str(PLAINTEXT).unwrap();
+    assert_eq!(
+        PLAINTEXT,
+        pkcs11_keys.decrypt_str(&encrypted).unwrap(),
+        "token cannot decrypt data of the rsa key!"
+    );
+    let encrypted = pkcs11_keys.encrypt_str(PLAINTEXT).unwrap();
+    assert_eq!(PLAINTEXT, rsa_keys.decrypt_str(&encrypted).unwrap());
+    assert!(
+        pkcs11_keys
+            .decrypt_str_with_key_id(&encrypted, Some("0000000000000000"))
+            .is_err(),
+        "unknown key id must be rejected!"
+    );
+    let _ = std::fs::remove_dir_all(&token_directory);
+}
@@ -1,4 +1,7 @@
-use lmtyas::rsa_functions::RsaKeys;
+use lmtyas::base64_trait::Base64StringConversions;
+use lmtyas::rsa_functions::{key_id, RsaKeys, RsaPrivateKeyPassword};
+use openssl::rsa::{Padding, Rsa};
+use openssl::symm::Cipher;
 use regex::Regex;
 use secstr::SecStr;
 use std::path::Path;
@@ -54,3 +57,121 @@ fn rsa_functions() {
         "rsa encrypted data should not be equal after 2 calls!"
     );
 }
+
+#[test]
+fn rsa_key_ring() {
+    const RSA_PASSPHRASE: &str = "12345678901234";
+    const PLAINTEXT: &str = "plaintext";
+
+    let secure_rsa_passphrase = SecStr::from(RSA_PASSPHRASE);
+    // the previous key of the key ring, protected by the same passphrase
+    let previous_rsa_key = Rsa::generate(2048).unwrap();
+    let previous_key_id = key_id(&previous_rsa_key).unwrap();
+    let key_ring_directory =
+        std::env::temp_dir().join(format!("lmtyas-key-ring-{}", std::process::id()));
+    std::fs::create_dir_all(&key_ring_directory).unwrap();
+    std::fs::write(
+        key_ring_directory.join("previous.key"),
+        previous_rsa_key
+            .private_key_to_pem_passphrase(Cipher::aes_256_cbc(), RSA_PASSPHRASE.as_bytes())
+            .unwrap(),
+    )
+    .unwrap();
+
+    let mut rsa_keys = RsaKeys::new();
+    rsa_keys
+        .read_from_files(
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_private.key"),
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_public.key"),
+            &secure_rsa_passphrase,
+        )
+        .unwrap();
+    let loaded = rsa_keys.read_key_ring_from_directory(&key_ring_directory, &secure_rsa_passphrase);
+    std::fs::remove_dir_all(&key_ring_directory).unwrap();
+    loaded.unwrap();
+
+    let primary_key_id = rsa_keys.rsa_key_id.clone().unwrap();
+    assert_eq!(
+        primary_key_id,
+        key_id(rsa_keys.rsa_public_key.as_ref().unwrap()).unwrap(),
+        "key id of the private and the public key must match!"
+    );
+    assert_eq!(
+        rsa_keys.previous_rsa_private_keys.len(),
+        1,
+        "previous key has not been loaded!"
+    );
+
+    // data encrypted with the previous key before the rotation
+    let mut buffer = vec![0; previous_rsa_key.size() as usize];
+    let length = previous_rsa_key
+        .public_encrypt(PLAINTEXT.as_bytes(), &mut buffer, Padding::PKCS1_OAEP)
+        .unwrap();
+    let encrypted = buffer[0..length].to_vec().to_base64_encoded();
+    assert_eq!(
+        PLAINTEXT,
+        rsa_keys
+            .decrypt_str_with_key_id(&encrypted, Some(&previous_key_id))
+            .unwrap(),
+        "data of the previous key cannot be decrypted with its key id!"
+    );
+    assert_eq!(
+        PLAINTEXT,
+        rsa_keys.decrypt_str(&encrypted).unwrap(),
+        "data of the previous key cannot be decrypted without key id!"
+    );
+    assert!(
+        rsa_keys
+            .decrypt_str_with_key_id(&encrypted, Some(&primary_key_id))
+            .is_err(),
+        "data of the previous key must not be decrypted by the primary key!"
+    );
+    assert!(
+        rsa_keys
+            .decrypt_str_with_key_id(&encrypted, Some("0000000000000000"))
+            .is_err(),
+        "unknown key id must be rejected!"
+    );
+
+    // legacy data of the previous key, the primary key may decrypt
+    // PKCS#1 v1.5 data without error to garbage
+    for _ in 0..10 {
+        let length = previous_rsa_key
+            .public_encrypt(PLAINTEXT.as_bytes(), &mut buffer, Padding::PKCS1)
+            .unwrap();
+        let encrypted = buffer[0..length].to_vec().to_base64_encoded();
+        assert_eq!(
+            PLAINTEXT,
+            rsa_keys.decrypt_str_pkcs1(&encrypted).unwrap(),
+            "legacy data of the previous key cannot be decrypted!"
+        );
+    }
+}
+
+#[test]
+fn rsa_private_key_password_file() {
+    const RSA_PASSPHRASE: &str = "12345678901234";
+    let password_file =
+        std::env::temp_dir().join(format!("lmtyas-rsa-password-{}", std::process::id()));
+    // editors and `echo` add a line break
+    std::fs::write(&password_file, format!("{}\n", RSA_PASSPHRASE)).unwrap();
+    let rsa_password = RsaPrivateKeyPassword::read_from_file(&password_file);
+    std::fs::write(&password_file, "\r\n").unwrap();
+    let empty_rsa_password = RsaPrivateKeyPassword::read_from_file(&password_file);
+    std::fs::remove_file(&password_file).unwrap();
+
+    let rsa_private_key_password = rsa_password.unwrap().rsa_private_key_password.unwrap();
+    assert_eq!(rsa_private_key_password, SecStr::from(RSA_PASSPHRASE));
+    let mut rsa_keys = RsaKeys::new();
+    rsa_keys
+        .read_from_files(
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_private.key"),
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_public.key"),
+            &rsa_private_key_password,
+        )
+        .expect("cannot load rsa keys with password from file!");
+    assert!(
+        empty_rsa_password.is_err(),
+        "empty password file must be rejected!"
+    );
+}
@@ -0,0 +1,329 @@
+use chrono::{Duration, Utc};
+use lmtyas::aes_functions::EncryptAes;
+use lmtyas::base64_trait::Base64StringConversions;
+use lmtyas::mail_configuration::SendEMailConfiguration;
+use lmtyas::master_key_trait::MasterKey;
+use lmtyas::rsa_functions::RsaKeys;
+use lmtyas::secret_functions::{
+    cleanup_expired_secret_files, create_one_time_code, hash_one_time_code, is_expired,
+    mark_receiver_revealed, record_failed_attempt, Attachment, Receiver, Secret,
+    SECRET_FORMAT_LEGACY, SECRET_FORMAT_RSA_OAEP, SECRET_FORMAT_VERSION,
+};
+use openssl::rsa::Padding;
+use openssl::symm::{encrypt, Cipher};
+use secstr::SecStr;
+use std::fs::{create_dir_all, metadata, read_dir, remove_dir_all, write, File};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+const WORKSPACE_DIR: &str = env!("CARGO_MANIFEST_DIR");
+const OLD_SECRET: &str = "b4b4a6b0-0c7d-11ee-8000-99a8db5c4385";
+const NEW_SECRET: &str = "c3e9d3d2-0c7d-11ee-8000-99a8db5c4385";
+const NO_SECRET: &str = "not-a-secret.txt";
+
+#[test]
+fn cleanup_expired_secrets() {
+    let secret_directory = Path::new(WORKSPACE_DIR).join("ignore/test-expired-secrets");
+    create_dir_all(&secret_directory).unwrap();
+    write(secret_directory.join(OLD_SECRET), "old").unwrap();
+    write(secret_directory.join(NO_SECRET), "keep me").unwrap();
+    // wait 2 seconds, so that the first files are old enough for cleanup
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    write(secret_directory.join(NEW_SECRET), "new").unwrap();
+
+    // without loaded rsa keys only the age of the files is checked
+    let master_key: Arc<RwLock<Box<dyn MasterKey>>> =
+        Arc::new(RwLock::new(Box::new(RsaKeys::new())));
+    let email_configuration: SendEMailConfiguration = serde_json::from_str(
+        r#"{
+            "mail_server_address": "127.0.0.1",
+            "mail_server_port": 2525,
+            "mail_from": "IT-department <do-not-reply@acme.local>",
+            "mail_subject": "Your new password for {Context}",
+            "mail_template_file": "conf.dev/mailtemplate.txt"
+        }"#,
+    )
+    .unwrap();
+    cleanup_expired_secret_files(&secret_directory, 1, &master_key, &email_configuration);
+
+    assert!(
+        !secret_directory.join(OLD_SECRET).exists(),
+        "expired secret should have been removed!"
+    );
+    assert!(
+        secret_directory.join(NEW_SECRET).exists(),
+        "secret should not have been removed yet!"
+    );
+    assert!(
+        secret_directory.join(NO_SECRET).exists(),
+        "files without uuid as name should not be touched!"
+    );
+    remove_dir_all(&secret_directory).unwrap();
+}
+
+#[test]
+fn secret_expiry() {
+    let past = (Utc::now() - Duration::hours(1)).to_rfc3339();
+    let future = (Utc::now() + Duration::hours(1)).to_rfc3339();
+    assert!(is_expired(&past), "secret should have expired!");
+    assert!(!is_expired(&future), "secret should not have expired yet!");
+    assert!(
+        is_expired("not a timestamp"),
+        "invalid timestamps should be handled as expired!"
+    );
+}
+
+#[test]
+fn reveal_secret_for_multiple_receivers() {
+    let secret_directory = Path::new(WORKSPACE_DIR).join("ignore/test-multiple-receivers");
+    create_dir_all(&secret_directory).unwrap();
+    let path = secret_directory.join(NEW_SECRET);
+    write(
+        &path,
+        r#"{"FromEmail":"a","FromDisplayName":"b","ToEmail":"c","ToDisplayName":"d","Context":"e","Secret":"f",
+            "Receivers":[{"ToEmail":"c","ToDisplayName":"d"},{"ToEmail":"g","ToDisplayName":"h"}]}"#,
+    )
+    .unwrap();
+    let modified = SystemTime::now() - std::time::Duration::from_secs(3600);
+    File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+
+    assert!(
+        !mark_receiver_revealed(&path, 1).unwrap(),
+        "secret should be kept for the first receiver!"
+    );
+    assert!(path.exists(), "secret should not have been removed yet!");
+    assert_eq!(
+        metadata(&path).unwrap().modified().unwrap(),
+        modified,
+        "modification time of the secret must not change!"
+    );
+    assert_eq!(
+        read_dir(&secret_directory).unwrap().count(),
+        1,
+        "temporary file must have been renamed!"
+    );
+    assert!(
+        mark_receiver_revealed(&path, 1).is_err(),
+        "receiver must not reveal the secret twice!"
+    );
+    assert!(
+        mark_receiver_revealed(&path, 2).is_err(),
+        "unknown receiver must not reveal the secret!"
+    );
+    assert!(
+        mark_receiver_revealed(&path, 0).unwrap(),
+        "secret should be removed after the last receiver!"
+    );
+    assert!(!path.exists(), "secret should have been removed!");
+    remove_dir_all(&secret_directory).unwrap();
+}
+
+#[test]
+fn one_time_codes_of_external_receivers() {
+    let code = create_one_time_code().unwrap();
+    assert_eq!(code.len(), 10, "one-time code has unexpected length!");
+    assert!(
+        code.chars()
+            .all(|c| "ABCDEFGHJKLMNPQRSTUVWXYZ23456789".contains(c)),
+        "one-time code contains unexpected characters!"
+    );
+    let receiver = Receiver {
+        to_email: "bob@contractor.example".to_string(),
+        to_display_name: "bob@contractor.example".to_string(),
+        revealed: false,
+        code_hash: Some(hash_one_time_code(&code)),
+        failed_attempts: 0,
+    };
+    assert!(receiver.code_matches(&code), "code should match!");
+    assert!(
+        receiver.code_matches(&format!(" {} ", code.to_lowercase())),
+        "code should match regardless of case and whitespace!"
+    );
+    assert!(
+        !receiver.code_matches("WRONGCODE2"),
+        "code should not match!"
+    );
+
+    let secret_directory = Path::new(WORKSPACE_DIR).join("ignore/test-external-receivers");
+    create_dir_all(&secret_directory).unwrap();
+    let path = secret_directory.join(NEW_SECRET);
+    write(
+        &path,
+        r#"{"FromEmail":"a","FromDisplayName":"b","ToEmail":"c","ToDisplayName":"d","Context":"e","Secret":"f",
+            "Receivers":[{"ToEmail":"c","ToDisplayName":"d","CodeHash":"g"}]}"#,
+    )
+    .unwrap();
+    assert!(
+        !record_failed_attempt(&path, 0, 2).unwrap(),
+        "receiver should not be locked out after one wrong code!"
+    );
+    assert!(path.exists(), "secret should not have been removed yet!");
+    assert!(
+        record_failed_attempt(&path, 0, 2).unwrap(),
+        "receiver should be locked out after two wrong codes!"
+    );
+    assert!(
+        !path.exists(),
+        "secret should have been removed after the last receiver has been locked out!"
+    );
+    remove_dir_all(&secret_directory).unwrap();
+}
+
+#[test]
+fn read_secrets_of_all_format_versions() {
+    const PLAINTEXT: &str = "PASSWORD";
+    let mut rsa_keys = RsaKeys::new();
+    rsa_keys
+        .read_from_files(
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_private.key"),
+            Path::new(WORKSPACE_DIR).join("ignore/lmtyas_rsa_public.key"),
+            &SecStr::from("12345678901234"),
+        )
+        .unwrap();
+
+    // current format: fields encrypted by a data key, secrets
+    // are no longer limited by the rsa key size
+    let plaintext = PLAINTEXT.repeat(1024);
+    let aes_encrypted = plaintext.to_aes_enrypted_b64().unwrap();
+    let secret: Secret = serde_json::from_str(&format!(
+        r#"{{"FromEmail":"a","FromDisplayName":"b","ToEmail":"c","ToDisplayName":"d","Context":"e","Secret":"{}"}}"#,
+        &aes_encrypted.encrypted_data
+    ))
+    .unwrap();
+    let encrypted_secret = secret.to_encrypted(&rsa_keys).unwrap();
+    assert_eq!(encrypted_secret.version, SECRET_FORMAT_VERSION);
+    assert!(encrypted_secret.data_key.is_some(), "data key is missing!");
+    let stored_secret: Secret =
+        serde_json::from_str(&serde_json::to_string(&encrypted_secret).unwrap()).unwrap();
+    let decrypted_secret = stored_secret.to_decrypted(&rsa_keys).unwrap();
+    assert_eq!(decrypted_secret.context, "e");
+    assert_eq!(
+        decrypted_secret
+            .decrypt_aes_secret(&aes_encrypted.encryption_key, &aes_encrypted.encryption_iv)
+            .unwrap(),
+        plaintext
+    );
+
+    // every field encrypted with rsa OAEP padding
+    let oaep_secret: Secret = serde_json::from_str(&format!(
+        r#"{{"Version":{},"FromEmail":"{}","FromDisplayName":"{}","ToEmail":"{}","ToDisplayName":"{}","Context":"{}","Secret":"{}"}}"#,
+        SECRET_FORMAT_RSA_OAEP,
+        rsa_keys.encrypt_str("a").unwrap(),
+        rsa_keys.encrypt_str("b").unwrap(),
+        rsa_keys.encrypt_str("c").unwrap(),
+        rsa_keys.encrypt_str("d").unwrap(),
+        rsa_keys.encrypt_str("e").unwrap(),
+        rsa_keys.encrypt_str("f").unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(oaep_secret.to_decrypted(&rsa_keys).unwrap().context, "e");
+
+    // legacy format without version: rsa PKCS#1 v1.5 padding and AES-CBC
+    let public_key = rsa_keys.rsa_public_key.as_ref().unwrap();
+    let rsa_encrypt_pkcs1 = |plaintext: &str| {
+        let mut buf = vec![0; public_key.size() as usize];
+        public_key
+            .public_encrypt(plaintext.as_bytes(), &mut buf, Padding::PKCS1)
+            .unwrap();
+        buf.to_base64_encoded()
+    };
+    let key = [1u8; 32];
+    let iv = [2u8; 16];
+    let aes_encrypted_secret =
+        encrypt(Cipher::aes_256_cbc(), &key, Some(&iv), PLAINTEXT.as_bytes())
+            .unwrap()
+            .to_base64_urlsafe_encoded();
+    let legacy_secret: Secret = serde_json::from_str(&format!(
+        r#"{{"FromEmail":"{}","FromDisplayName":"{}","ToEmail":"{}","ToDisplayName":"{}","Context":"{}","Secret":"{}"}}"#,
+        rsa_encrypt_pkcs1("a"),
+        rsa_encrypt_pkcs1("b"),
+        rsa_encrypt_pkcs1("c"),
+        rsa_encrypt_pkcs1("d"),
+        rsa_encrypt_pkcs1("e"),
+        rsa_encrypt_pkcs1(&aes_encrypted_secret),
+    ))
+    .unwrap();
+    assert_eq!(legacy_secret.version, SECRET_FORMAT_LEGACY);
+    let decrypted_secret = legacy_secret.to_decrypted(&rsa_keys).unwrap();
+    assert_eq!(decrypted_secret.context, "e");
+    assert_eq!(
+        decrypted_secret
+            .decrypt_aes_secret(
+                &key.to_base64_urlsafe_encoded(),
+                &iv.to_base64_urlsafe_encoded()
+            )
+            .unwrap(),
+        PLAINTEXT
+    );
+    assert!(
+        rsa_keys
+            .decrypt_str_with_legacy_fallback(&rsa_encrypt_pkcs1("link"))
+            .is_ok(),
+        "links mailed before OAEP padding should still be accepted!"
+    );
+    assert!(
+        rsa_keys.decrypt_str(&rsa_encrypt_pkcs1("link")).is_err(),
+        "links with PKCS#1 v1.5 padding must only be accepted on request!"
+    );
+}
+
+#[test]
+fn attachment_is_encrypted_with_the_key_of_the_secret() {
+    const CONTENT: &str = "UEsDBBQAAAAIAA==";
+    let aes_encrypted = "PASSWORD".to_string().to_aes_enrypted_b64().unwrap();
+    let other_aes_encrypted = "PASSWORD".to_string().to_aes_enrypted_b64().unwrap();
+    let mut attachment = Attachment {
+        file_name: "vpn.ovpn".to_string(),
+        content_type: "application/x-openvpn-profile".to_string(),
+        content: CONTENT.to_string(),
+    };
+    attachment
+        .encrypt_content(&aes_encrypted.encryption_key)
+        .unwrap();
+    assert_ne!(attachment.content, CONTENT, "content should be encrypted!");
+    let encrypted_content = attachment.content.clone();
+    assert!(
+        attachment
+            .decrypt_content(&other_aes_encrypted.encryption_key)
+            .is_err(),
+        "content should not be decrypted with another key!"
+    );
+    attachment.content = encrypted_content;
+    attachment
+        .decrypt_content(&aes_encrypted.encryption_key)
+        .unwrap();
+    assert_eq!(attachment.content, CONTENT);
+}
+
+#[test]
+fn passphrase_is_not_logged() {
+    let secret: Secret = serde_json::from_str(
+        r#"{"FromEmail":"a","FromDisplayName":"b","ToEmail":"c","ToDisplayName":"d","Context":"e",
+            "Secret":"c2VjcmV0IHBhc3N3b3Jk","Passphrase":"correct horse battery staple",
+            "Attachment":{"FileName":"id_rsa","ContentType":"text/plain","Content":"LS0tLS1CRUdJTg=="}}"#,
+    )
+    .unwrap();
+    let debug_output = format!("{:?}", &secret);
+    assert!(debug_output.contains("<redacted>"));
+    assert!(
+        !debug_output.contains("correct horse"),
+        "passphrase must not appear in the log!"
+    );
+    assert!(debug_output.contains("<20 bytes>"));
+    assert!(
+        !debug_output.contains("c2VjcmV0IHBhc3N3b3Jk"),
+        "secret must not appear in the log!"
+    );
+    assert!(debug_output.contains("id_rsa"));
+    assert!(debug_output.contains("<16 bytes>"));
+    assert!(
+        !debug_output.contains("LS0tLS1CRUdJTg=="),
+        "content of the attachment must not appear in the log!"
+    );
+}
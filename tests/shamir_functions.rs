@@ -0,0 +1,99 @@
+use lmtyas::shamir_functions::{
+    combine_shares, split_secret, ShamirConfiguration, ShamirShare, ShamirShareCollector,
+};
+use secstr::SecStr;
+
+const RSA_PASSPHRASE: &str = "12345678901234";
+
+#[test]
+fn shamir_functions() {
+    let shares = split_secret(RSA_PASSPHRASE.as_bytes(), 3, 5).unwrap();
+    assert_eq!(shares.len(), 5);
+
+    // every combination of 3 shares restores the secret
+    for i in 0..5 {
+        for j in i + 1..5 {
+            for k in j + 1..5 {
+                let combination: Vec<ShamirShare> = [i, j, k]
+                    .iter()
+                    .map(|n| ShamirShare::parse(&shares[*n].to_string()).unwrap())
+                    .collect();
+                assert_eq!(
+                    combine_shares(&combination).unwrap(),
+                    SecStr::from(RSA_PASSPHRASE),
+                    "shares {}, {}, {} do not restore the secret!",
+                    i,
+                    j,
+                    k
+                );
+            }
+        }
+    }
+    let too_few_shares: Vec<ShamirShare> = shares[..2]
+        .iter()
+        .map(|share| ShamirShare::parse(&share.to_string()).unwrap())
+        .collect();
+    assert_ne!(
+        combine_shares(&too_few_shares).unwrap(),
+        SecStr::from(RSA_PASSPHRASE),
+        "2 shares must not restore the secret!"
+    );
+
+    assert!(split_secret(RSA_PASSPHRASE.as_bytes(), 1, 5).is_err());
+    assert!(split_secret(RSA_PASSPHRASE.as_bytes(), 6, 5).is_err());
+    assert!(ShamirShare::parse("00-abcd").is_err());
+    assert!(ShamirShare::parse("01-abc").is_err());
+    assert!(ShamirShare::parse("01-zz").is_err());
+}
+
+#[test]
+fn shamir_share_collector() {
+    let shamir_configuration = ShamirConfiguration {
+        threshold: 2,
+        max_share_age_seconds: 600,
+    };
+    let shares = split_secret(RSA_PASSPHRASE.as_bytes(), 2, 3).unwrap();
+    let share = |n: usize| ShamirShare::parse(&shares[n].to_string()).unwrap();
+
+    let mut collector = ShamirShareCollector::new();
+    assert!(collector
+        .add_share("walter", share(0), &shamir_configuration)
+        .unwrap()
+        .is_none());
+    // entering a share again replaces the first one
+    assert!(collector
+        .add_share("walter", share(1), &shamir_configuration)
+        .unwrap()
+        .is_none());
+    assert_eq!(collector.collected_shares(), 1);
+    assert!(
+        collector
+            .add_share("bob", share(1), &shamir_configuration)
+            .is_err(),
+        "the same share must not be entered by two administrators!"
+    );
+    let rsa_password = collector
+        .add_share("bob", share(2), &shamir_configuration)
+        .unwrap();
+    assert_eq!(rsa_password, Some(SecStr::from(RSA_PASSPHRASE)));
+    assert_eq!(
+        collector.collected_shares(),
+        0,
+        "shares must be discarded after the password has been restored!"
+    );
+
+    // shares outside of the time window are discarded
+    let expired_configuration = ShamirConfiguration {
+        threshold: 2,
+        max_share_age_seconds: 0,
+    };
+    assert!(collector
+        .add_share("walter", share(0), &expired_configuration)
+        .unwrap()
+        .is_none());
+    assert!(collector
+        .add_share("bob", share(1), &expired_configuration)
+        .unwrap()
+        .is_none());
+    assert_eq!(collector.collected_shares(), 1);
+}
@@ -3,6 +3,26 @@ rsaForm.addEventListener('submit', function (e) {
     e.preventDefault();
     setRsaPassword();
 });
+const rsaShareForm = document.getElementById("RsaPasswordShareForm");
+rsaShareForm.addEventListener('submit', function (e) {
+    e.preventDefault();
+    addRsaPasswordShare();
+});
+const reloadRsaKeysForm = document.getElementById("ReloadRsaKeysForm");
+reloadRsaKeysForm.addEventListener('submit', function (e) {
+    e.preventDefault();
+    reloadRsaKeys();
+});
+const sealServerForm = document.getElementById("SealServerForm");
+sealServerForm.addEventListener('submit', function (e) {
+    e.preventDefault();
+    sealServer();
+});
+const bulkSendForm = document.getElementById("BulkSendForm");
+bulkSendForm.addEventListener('submit', function (e) {
+    e.preventDefault();
+    sendBulkFile();
+});
 var keepAliveCount = 0;
 var keep_alive_interval = initKeepAliveInterval("/authenticated/keep_session_alive");
 
@@ -30,6 +50,24 @@ function systemIsNotReadyLocal() {
 
     document.getElementById("ServiceIsReady").classList.remove("lmtyas-block");
     document.getElementById("ServiceIsReady").classList.add("lmtyas-none");
+
+    queryWebService("/authenticated/sysop/get/rsa_password_shares", displayRsaPasswordShares, function () { });
+}
+
+function displayRsaPasswordShares(resulttext) {
+    try {
+        var data = JSON.parse(resulttext);
+        if (data.sharesRequired > 0) {
+            rsaForm.classList.add("lmtyas-none");
+            rsaShareForm.classList.remove("lmtyas-none");
+            document.getElementById("RsaPasswordSharesCollected").textContent =
+                data.sharesCollected + " of " + data.sharesRequired + " shares collected";
+            document.getElementById("RsaPasswordShare").focus();
+        }
+    }
+    catch (e) {
+        console.log(e);
+    }
 }
 
 function systemIsReadyLocal() {
@@ -38,6 +76,15 @@ function systemIsReadyLocal() {
 
     document.getElementById("ServiceIsReady").classList.remove("lmtyas-none");
     document.getElementById("ServiceIsReady").classList.add("lmtyas-block");
+
+    document.getElementById("ReloadRsaKeys").classList.remove("lmtyas-none");
+    document.getElementById("ReloadRsaKeys").classList.add("lmtyas-block");
+
+    document.getElementById("SealServer").classList.remove("lmtyas-none");
+    document.getElementById("SealServer").classList.add("lmtyas-block");
+
+    document.getElementById("BulkSend").classList.remove("lmtyas-none");
+    document.getElementById("BulkSend").classList.add("lmtyas-block");
 }
 
 function setRsaPassword() {
@@ -66,4 +113,118 @@ function displaySubmission(resulttext) {
 function errorOnSubmission() {
     console.log("errorOnSubmission()");
     stopForm(rsaForm, 6);
-}
\ No newline at end of file
+}
+
+function addRsaPasswordShare() {
+    let share = encodeURIComponent(document.getElementById("RsaPasswordShare").value.trim());
+    document.getElementById("RsaPasswordShare").value = "";
+    let url = "/authenticated/sysop/add_rsa_password_share/" + share;
+    sendToWebService(url, displayShareSubmission, errorOnShareSubmission, 5);
+}
+
+function displayShareSubmission(resulttext) {
+    if (typeof resulttext !== 'undefined') {
+        if (resulttext == "OK") {
+            stopForm(rsaShareForm, 10);
+            showSuccessMessage("Password for RSA private key has been restored from the shares.");
+        }
+        else {
+            displayRsaPasswordShares(resulttext);
+            showSuccessMessageWithTimer("Share has been accepted.", 10);
+        }
+    }
+}
+
+function errorOnShareSubmission() {
+    console.log("errorOnShareSubmission()");
+}
+
+function reloadRsaKeys() {
+    document.getElementById("ReloadRsaKeysButton").classList.add("lmtyas-hidden");
+    sendToWebService("/authenticated/sysop/reload_rsa_keys", displayReload, errorOnReload, "", 10);
+}
+
+function displayReload(resulttext) {
+    document.getElementById("ReloadRsaKeysButton").classList.remove("lmtyas-hidden");
+    if (resulttext == "OK") {
+        showSuccessMessageWithTimer("RSA key ring has been reloaded.", 10);
+    }
+}
+
+function errorOnReload() {
+    console.log("errorOnReload()");
+    document.getElementById("ReloadRsaKeysButton").classList.remove("lmtyas-hidden");
+}
+
+function sealServer() {
+    if (!confirm("Seal the server? Every user is logged out and the password must be entered again.")) {
+        return;
+    }
+    document.getElementById("SealServerButton").classList.add("lmtyas-hidden");
+    sendToWebService("/authenticated/sysop/seal", displaySeal, errorOnSeal, "", 10);
+}
+
+function displaySeal(resulttext) {
+    if (resulttext == "OK") {
+        stopForm(sealServerForm, 10);
+        showSuccessMessage("Server has been sealed.");
+    }
+}
+
+function errorOnSeal() {
+    console.log("errorOnSeal()");
+    document.getElementById("SealServerButton").classList.remove("lmtyas-hidden");
+}
+
+function sendBulkFile() {
+    const file = document.getElementById("BulkSendFile").files[0];
+    if (typeof file === 'undefined') {
+        return;
+    }
+    const reader = new FileReader();
+    reader.onload = function () {
+        document.getElementById("BulkSendButton").classList.add("lmtyas-hidden");
+        sendToWebService("/authenticated/sysop/bulk_send_secrets", displayBulkReport, errorOnBulkSend, reader.result, 10, 300000);
+    };
+    reader.onerror = function () {
+        showErrorMessageWithTimer("ERROR: could not read csv file", 10);
+    };
+    reader.readAsText(file);
+}
+
+function displayBulkReport(resulttext) {
+    document.getElementById("BulkSendButton").classList.remove("lmtyas-hidden");
+    try {
+        const results = JSON.parse(resulttext);
+        const tbody = document.getElementById("BulkSendReportBody");
+        tbody.replaceChildren();
+        let stored = 0;
+        for (const result of results) {
+            let tr = document.createElement("tr");
+            for (const value of [result.Row, result.ToEmail, result.Context, result.Status, result.Message]) {
+                let td = document.createElement("td");
+                td.textContent = value;
+                tr.appendChild(td);
+            }
+            tbody.appendChild(tr);
+            if (result.Status == "stored") {
+                stored++;
+            }
+        }
+        document.getElementById("BulkSendReport").classList.remove("lmtyas-none");
+        if (stored == results.length) {
+            showSuccessMessageWithTimer(stored + " secrets stored, the receivers will be notified via email.", 10);
+        }
+        else {
+            showErrorMessageWithTimer((results.length - stored) + " of " + results.length + " secrets failed, see report.", 10);
+        }
+    }
+    catch (e) {
+        console.log(e);
+    }
+}
+
+function errorOnBulkSend() {
+    console.log("errorOnBulkSend()");
+    document.getElementById("BulkSendButton").classList.remove("lmtyas-hidden");
+}
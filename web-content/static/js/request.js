@@ -0,0 +1,60 @@
+const requestForm = document.getElementById("SecretRequestForm");
+requestForm.addEventListener('submit', function (e) {
+    e.preventDefault();
+    sendFormData();
+});
+var keepAliveCount = 0;
+var keep_alive_interval = initKeepAliveInterval("/authenticated/keep_session_alive");
+keepSessionAlive("/authenticated/keep_session_alive");
+
+queryWebService("/system/is_server_ready", validateSystemStatus, systemIsNotReady);
+queryWebService("/authenticated/user/get/details/from", displayFromData, function () { });
+queryWebService("/system/get/mail-hint", setMailHint, function () { });
+
+function setMailHint(resulttext) {
+    try {
+        var data = JSON.parse(resulttext);
+        if (data.MailHint) {
+            const imprint = document.getElementById("SenderEmail");
+            imprint.setAttribute('placeholder', data.MailHint);
+        }
+    }
+    catch (e) {
+        console.log(e);
+    }
+}
+
+function displaySubmission(resulttext) {
+    if (typeof resulttext !== 'undefined') {
+        disableFormInputs(requestForm);
+        if (resulttext == "OK") {
+            showSuccessMessageWithTimer("Request sent, the sender will be notified via email.", 5);
+            startBackHomeTimer(6);
+        }
+    }
+}
+
+function displayFromData(result) {
+    if (typeof result !== 'undefined') {
+        let data = JSON.parse(result);
+        document.getElementById("FromDisplayName").value = data.DisplayName;
+        document.getElementById("FromEmail").value = data.Email;
+        document.getElementById("SenderEmail").focus();
+    }
+}
+
+function sendFormData() {
+    let jsonObject = {
+        SenderEmail: document.getElementById("SenderEmail").value,
+        Context: document.getElementById("Context").value,
+    };
+    let jsonString = JSON.stringify(jsonObject);
+    console.log(jsonString);
+    sendToWebService("/authenticated/secret/request", displaySubmission, errorOnSubmission, jsonString, 5);
+    document.getElementById("SubmitButton").classList.add("lmtyas-hidden");
+}
+
+function errorOnSubmission() {
+    console.log("errorOnSubmission()");
+    stopForm(requestForm, 6);
+}
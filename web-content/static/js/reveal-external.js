@@ -0,0 +1,113 @@
+const secretId = findGetParameterNoDecodeURIComponent("secret_id");
+const codeForm = document.getElementById("OneTimeCodeForm");
+const revealForm = document.getElementById("RevealSecretForm");
+const passphraseForm = document.getElementById("PassphraseForm");
+
+if (secretId != null && typeof (secretId !== 'undefined')) {
+    queryWebService("/system/is_server_ready", validateSystemStatus, systemIsNotReady);
+    codeForm.addEventListener('submit', function (e) {
+        e.preventDefault();
+        sendCode();
+    });
+    passphraseForm.addEventListener('submit', function (e) {
+        e.preventDefault();
+        sendCode();
+    });
+    document.getElementById("Code").focus();
+}
+else {
+    showErrorMessage("No SecretId found!");
+    stopForm(codeForm, 5);
+}
+
+function sendCode() {
+    let jsonObject = {
+        Code: document.getElementById("Code").value,
+    };
+    const passphrase = document.getElementById("Passphrase").value;
+    if (passphrase !== "") {
+        jsonObject.Passphrase = passphrase;
+    }
+    let jsonString = JSON.stringify(jsonObject);
+    sendToWebService("/external/secret/reveal/" + secretId, displaySecret, errorOnReveal, jsonString, 5);
+    document.getElementById("SubmitButton").classList.add("lmtyas-hidden");
+    document.getElementById("PassphraseButton").classList.add("lmtyas-hidden");
+}
+
+function errorOnReveal() {
+    console.log("errorOnReveal()");
+    stopForm(codeForm, 6);
+    stopForm(passphraseForm, 6);
+}
+
+function displaySecret(resulttext) {
+    if (typeof resulttext !== 'undefined') {
+        let data = JSON.parse(resulttext);
+        codeForm.classList.add("lmtyas-none");
+        if (data.PassphraseRequired) {
+            passphraseForm.classList.remove("lmtyas-none");
+            passphraseForm.classList.add("lmtyas-block");
+            document.getElementById("PassphraseButton").classList.remove("lmtyas-hidden");
+            document.getElementById("Passphrase").focus();
+            return;
+        }
+        passphraseForm.classList.add("lmtyas-none");
+        document.getElementById("FromDisplayName").value = data.FromDisplayName;
+        document.getElementById("FromEmail").value = data.FromEmail;
+        document.getElementById("Context").value = data.Context;
+        let secret;
+        try {
+            // decode password to transfer special characters
+            secret = decodeURIComponent(escape(window.atob(data.Secret)));
+        }
+        catch (error) {
+            console.log("ERROR: could not convert secret from base64: " + error);
+            showErrorMessage("ERROR: could not convert secret from base64");
+        }
+        const secretArea = document.getElementById("Secret");
+        secretArea.value = secret;
+        // show multi line secrets like ssh keys completely
+        if (typeof secret !== 'undefined') {
+            secretArea.rows = Math.min(Math.max(secret.split("\n").length, 3), 30);
+        }
+        displayAttachment(data.Attachment);
+        revealForm.classList.remove("lmtyas-none");
+        revealForm.classList.add("lmtyas-block");
+    }
+}
+
+// the attachment is only kept in the browser until it has been downloaded once
+function displayAttachment(attachment) {
+    if (typeof attachment === 'undefined' || attachment === null) {
+        return;
+    }
+    const attachmentBlock = document.getElementById("AttachmentBlock");
+    const downloadButton = document.getElementById("DownloadButton");
+    downloadButton.textContent = "Download " + attachment.FileName;
+    downloadButton.addEventListener('click', function () {
+        try {
+            const content = window.atob(attachment.Content);
+            const bytes = new Uint8Array(content.length);
+            for (let i = 0; i < content.length; i++) {
+                bytes[i] = content.charCodeAt(i);
+            }
+            const url = URL.createObjectURL(new Blob([bytes], { type: attachment.ContentType }));
+            const link = document.createElement("a");
+            link.href = url;
+            link.download = attachment.FileName;
+            document.body.appendChild(link);
+            link.click();
+            document.body.removeChild(link);
+            URL.revokeObjectURL(url);
+        }
+        catch (error) {
+            console.log("ERROR: could not convert attachment from base64: " + error);
+            showErrorMessage("ERROR: could not convert attachment from base64");
+        }
+        attachment = null;
+        downloadButton.disabled = true;
+        downloadButton.textContent = "Downloaded";
+    }, { once: true });
+    attachmentBlock.classList.remove("lmtyas-none");
+    attachmentBlock.classList.add("lmtyas-block");
+}
@@ -1,5 +1,10 @@
 const secretId = findGetParameterNoDecodeURIComponent("secret_id");
 const revealForm = document.getElementById("RevealSecretForm");
+const passphraseForm = document.getElementById("PassphraseForm");
+passphraseForm.addEventListener('submit', function (e) {
+    e.preventDefault();
+    sendPassphrase();
+});
 
 var keepAliveCount = 0;
 if (secretId != null && typeof (secretId !== 'undefined')) {
@@ -15,12 +20,28 @@ else {
 
 function errorOnReveal() {
     console.log("errorOnReveal()");
+    stopForm(passphraseForm, 6);
     stopForm(revealForm, 6);
 }
 
+function sendPassphrase() {
+    let jsonString = JSON.stringify({
+        Passphrase: document.getElementById("Passphrase").value,
+    });
+    sendToWebService("/authenticated/secret/reveal/" + secretId, displaySecret, errorOnReveal, jsonString, 5);
+    document.getElementById("PassphraseButton").classList.add("lmtyas-hidden");
+}
+
 function displaySecret(resulttext) {
     if (typeof resulttext !== 'undefined') {
         let data = JSON.parse(resulttext);
+        if (data.PassphraseRequired) {
+            passphraseForm.classList.remove("lmtyas-none");
+            passphraseForm.classList.add("lmtyas-block");
+            document.getElementById("Passphrase").focus();
+            return;
+        }
+        passphraseForm.classList.add("lmtyas-none");
         document.getElementById("FromDisplayName").value = data.FromDisplayName;
         document.getElementById("FromEmail").value = data.FromEmail;
         document.getElementById("Context").value = data.Context;
@@ -33,8 +54,50 @@ function displaySecret(resulttext) {
             console.log("ERROR: could not convert secret from base64: " + output);
             showErrorMessage("ERROR: could not convert secret from base64");
         }
-        document.getElementById("Secret").value = secret;
+        const secretArea = document.getElementById("Secret");
+        secretArea.value = secret;
+        // show multi line secrets like ssh keys completely
+        if (typeof secret !== 'undefined') {
+            secretArea.rows = Math.min(Math.max(secret.split("\n").length, 3), 30);
+        }
+        displayAttachment(data.Attachment);
         document.getElementById("RevealSecretForm").classList.remove("lmtyas-none");
         document.getElementById("RevealSecretForm").classList.add("lmtyas-block");
     }
 }
+
+// the attachment is only kept in the browser until it has been downloaded once
+function displayAttachment(attachment) {
+    if (typeof attachment === 'undefined' || attachment === null) {
+        return;
+    }
+    const attachmentBlock = document.getElementById("AttachmentBlock");
+    const downloadButton = document.getElementById("DownloadButton");
+    downloadButton.textContent = "Download " + attachment.FileName;
+    downloadButton.addEventListener('click', function () {
+        try {
+            const content = window.atob(attachment.Content);
+            const bytes = new Uint8Array(content.length);
+            for (let i = 0; i < content.length; i++) {
+                bytes[i] = content.charCodeAt(i);
+            }
+            const url = URL.createObjectURL(new Blob([bytes], { type: attachment.ContentType }));
+            const link = document.createElement("a");
+            link.href = url;
+            link.download = attachment.FileName;
+            document.body.appendChild(link);
+            link.click();
+            document.body.removeChild(link);
+            URL.revokeObjectURL(url);
+        }
+        catch (error) {
+            console.log("ERROR: could not convert attachment from base64: " + error);
+            showErrorMessage("ERROR: could not convert attachment from base64");
+        }
+        attachment = null;
+        downloadButton.disabled = true;
+        downloadButton.textContent = "Downloaded";
+    }, { once: true });
+    attachmentBlock.classList.remove("lmtyas-none");
+    attachmentBlock.classList.add("lmtyas-block");
+}
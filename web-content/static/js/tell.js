@@ -10,6 +10,63 @@ keepSessionAlive("/authenticated/keep_session_alive");
 queryWebService("/system/is_server_ready", validateSystemStatus, systemIsNotReady);
 queryWebService("/authenticated/user/get/details/from", displayFromData, function () { });
 queryWebService("/system/get/mail-hint", setMailHint, function () { });
+queryWebService("/system/get/secret-valid-for-hours", setValidForHours, function () { });
+queryWebService("/system/get/max-attachment-bytes", setMaxAttachmentBytes, function () { });
+var maxAttachmentBytes = null;
+// the form is pre-filled if someone has requested this secret
+const requestId = findGetParameterNoDecodeURIComponent("request_id");
+if (requestId != null && typeof (requestId !== 'undefined')) {
+    queryWebService("/authenticated/secret/request/" + requestId, displayRequestData, errorOnSubmission);
+}
+
+function displayRequestData(resulttext) {
+    if (typeof resulttext !== 'undefined') {
+        let data = JSON.parse(resulttext);
+        const toEmail = document.getElementById("ToEmail");
+        toEmail.value = data.ToEmail;
+        toEmail.readOnly = true;
+        document.getElementById("Context").value = data.Context;
+        document.getElementById("Secret").focus();
+    }
+}
+
+function setValidForHours(resulttext) {
+    try {
+        var data = JSON.parse(resulttext);
+        const validFor = document.getElementById("ValidForHours");
+        for (const hours of data.ValidForHours) {
+            let option = document.createElement("option");
+            option.value = hours;
+            if (hours % 24 == 0) {
+                option.text = (hours / 24) + ((hours == 24) ? " day" : " days");
+            }
+            else {
+                option.text = hours + ((hours == 1) ? " hour" : " hours");
+            }
+            validFor.appendChild(option);
+        }
+    }
+    catch (e) {
+        console.log(e);
+    }
+}
+
+function setMaxAttachmentBytes(resulttext) {
+    try {
+        var data = JSON.parse(resulttext);
+        if (data.MaxAttachmentBytes) {
+            maxAttachmentBytes = data.MaxAttachmentBytes;
+            document.getElementById("AttachmentHint").textContent =
+                "Files up to " + Math.floor(maxAttachmentBytes / 1024) + " KiB, the receiver can download it once.";
+            const attachmentBlock = document.getElementById("AttachmentBlock");
+            attachmentBlock.classList.remove("lmtyas-none");
+            attachmentBlock.classList.add("lmtyas-block");
+        }
+    }
+    catch (e) {
+        console.log(e);
+    }
+}
 
 function setMailHint(resulttext) {
     try {
@@ -39,11 +96,40 @@ function displayFromData(result) {
         let data = JSON.parse(result);
         document.getElementById("FromDisplayName").value = data.DisplayName;
         document.getElementById("FromEmail").value = data.Email;
-        document.getElementById("ToEmail").focus();
+        if (!document.getElementById("ToEmail").readOnly) {
+            document.getElementById("ToEmail").focus();
+        }
     }
 }
 
 function sendFormData() {
+    const attachmentInput = document.getElementById("Attachment");
+    if (maxAttachmentBytes === null || attachmentInput.files.length == 0) {
+        sendSecret(null);
+        return;
+    }
+    const file = attachmentInput.files[0];
+    if (file.size > maxAttachmentBytes) {
+        showErrorMessage("ERROR: the file is larger than " + maxAttachmentBytes + " bytes");
+        return;
+    }
+    const reader = new FileReader();
+    reader.onload = function () {
+        // strip the data url prefix, only the base64 encoded content is sent
+        const dataUrl = reader.result;
+        sendSecret({
+            FileName: file.name,
+            ContentType: file.type,
+            Content: dataUrl.substring(dataUrl.indexOf(",") + 1),
+        });
+    };
+    reader.onerror = function () {
+        showErrorMessage("ERROR: could not read the file");
+    };
+    reader.readAsDataURL(file);
+}
+
+function sendSecret(attachment) {
     let secret;
     try {
         // encode password to transfer special characters
@@ -61,8 +147,18 @@ function sendFormData() {
         Context: document.getElementById("Context").value,
         Secret: secret,
     };
+    const validFor = document.getElementById("ValidForHours").value;
+    if (validFor !== "") {
+        jsonObject.ValidForHours = parseInt(validFor);
+    }
+    const passphrase = document.getElementById("Passphrase").value;
+    if (passphrase !== "") {
+        jsonObject.Passphrase = passphrase;
+    }
+    if (attachment !== null) {
+        jsonObject.Attachment = attachment;
+    }
     let jsonString = JSON.stringify(jsonObject);
-    console.log(jsonString);
     sendToWebService("/authenticated/secret/tell", displaySubmission, errorOnSubmission, jsonString, 5);
     document.getElementById("SubmitButton").classList.add("lmtyas-hidden");
 }